@@ -0,0 +1,13 @@
+#![no_main]
+
+use codec::Decode;
+use libfuzzer_sys::fuzz_target;
+use swap::mock::{Call, Test};
+
+fuzz_target!(|data: &[u8]| {
+    substrate_uniswap_fuzz::run(data, |input| {
+        token::Call::<Test>::decode(input)
+            .ok()
+            .map(Call::TokenModule)
+    });
+});
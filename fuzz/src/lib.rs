@@ -0,0 +1,159 @@
+//! Shared harness of the fuzz targets: a mock runtime seeded with two tokens and a trade pair,
+//! a loop dispatching the calls decoded from the fuzzer input, and the conservation checks run
+//! after every call.
+
+use codec::Decode;
+use frame_support::{storage::IterableStorageMap, traits::Currency};
+use sp_core::H256;
+use sp_runtime::traits::Dispatchable;
+use std::collections::BTreeMap;
+use swap::{mock::*, PoolKind};
+use token::{Store, TokenType};
+
+/// Accounts signing the decoded calls. A signer byte out of range dispatches as root.
+pub const ACCOUNTS: [u64; 3] = [1, 2, 3];
+
+const NATIVE_BALANCE: u128 = 1_000_000_000;
+const TOTAL_SUPPLY: u128 = 1_000_000_000_000;
+
+fn setup() {
+    System::set_block_number(1);
+
+    for who in ACCOUNTS.iter() {
+        let _ = Balances::deposit_creating(who, NATIVE_BALANCE);
+    }
+
+    let base = TokenModule::do_issue(
+        ACCOUNTS[0],
+        b"6666".to_vec(),
+        TOTAL_SUPPLY,
+        TokenType::Normal,
+    )
+    .expect("issuing works");
+    let quote = TokenModule::do_issue(
+        ACCOUNTS[0],
+        b"8888".to_vec(),
+        TOTAL_SUPPLY,
+        TokenType::Normal,
+    )
+    .expect("issuing works");
+    for who in ACCOUNTS[1..].iter() {
+        TokenModule::do_transfer(ACCOUNTS[0], *who, base, TOTAL_SUPPLY / 4, None)
+            .expect("the issuer holds the supply");
+        TokenModule::do_transfer(ACCOUNTS[0], *who, quote, TOTAL_SUPPLY / 4, None)
+            .expect("the issuer holds the supply");
+    }
+
+    SwapModule::do_create_trade_pair(ACCOUNTS[0], base, quote, PoolKind::ConstantProduct, 30)
+        .expect("pair is new");
+    let tp_hash =
+        SwapModule::trade_pair_hash_by_base_quote((base, quote, 30)).expect("pair was created");
+    SwapModule::do_add_liquidity(ACCOUNTS[0], tp_hash, 1_000_000, Some(2_000_000))
+        .expect("the issuer holds both tokens");
+}
+
+/// Every token balance is the sum of its free, frozen and vesting parts, the balances of a token
+/// add up to its total supply, the wrapped native token is backed one to one and no native
+/// currency is created.
+fn check_conservation(native_issuance: u128) {
+    let mut supplies: BTreeMap<H256, u128> = BTreeMap::new();
+    for ((who, hash), balance) in <TokenModule as Store>::BalanceOf::iter() {
+        let free = TokenModule::free_balance_of((who, hash));
+        let frozen = TokenModule::freezed_balance_of((who, hash));
+        let vesting = TokenModule::vesting((who, hash)).map_or(0, |schedule| schedule.locked);
+        assert_eq!(
+            free.checked_add(frozen)
+                .and_then(|b| b.checked_add(vesting)),
+            Some(balance),
+            "balance of {} in {:?} is not free plus frozen plus vesting",
+            who,
+            hash
+        );
+
+        let supply = supplies.entry(hash).or_insert(0);
+        *supply = supply
+            .checked_add(balance)
+            .unwrap_or_else(|| panic!("balances of {:?} overflow", hash));
+    }
+
+    for index in 0..TokenModule::token_index() {
+        let hash = TokenModule::token_hash_by_index(index).expect("tokens are indexed");
+        let token = TokenModule::token(hash).expect("indexed tokens exist");
+        assert_eq!(
+            supplies.get(&hash).copied().unwrap_or(0),
+            token.total_supply,
+            "balances of {:?} do not add up to its supply",
+            hash
+        );
+    }
+
+    if let Some(hash) = TokenModule::wrapped_native_token_hash() {
+        assert_eq!(
+            Balances::free_balance(&TokenModule::account_id()),
+            TokenModule::token(hash)
+                .expect("wrapped token exists")
+                .total_supply
+        );
+    }
+
+    assert_eq!(Balances::total_issuance(), native_issuance);
+}
+
+/// Dispatch the calls `decode_call` reads from `data`, each preceded by a signer byte, until
+/// the input runs out or no longer decodes.
+pub fn run(data: &[u8], decode_call: fn(&mut &[u8]) -> Option<Call>) {
+    new_test_ext().execute_with(|| {
+        setup();
+        let native_issuance = Balances::total_issuance();
+        check_conservation(native_issuance);
+
+        let mut input = data;
+        while let Ok(signer) = u8::decode(&mut input) {
+            let call = match decode_call(&mut input) {
+                Some(call) => call,
+                None => break,
+            };
+            let origin = match ACCOUNTS.get(signer as usize) {
+                Some(who) => Origin::signed(*who),
+                None => Origin::root(),
+            };
+
+            let _ = call.dispatch(origin);
+            check_conservation(native_issuance);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::Encode;
+
+    /// Swaps of the largest balance, which overflowed the constant product math.
+    const HUGE_SWAPS: &[u8] = include_bytes!("../corpus/swap_calls/regression-huge-swaps");
+
+    #[test]
+    fn huge_swaps_regression() {
+        // the corpus entry names the tokens and the pair the harness sets up
+        let expected = new_test_ext().execute_with(|| {
+            setup();
+            let base = TokenModule::token_hash_by_index(0).expect("base was issued");
+            let quote = TokenModule::token_hash_by_index(1).expect("quote was issued");
+            let tp_hash = SwapModule::trade_pair_hash_by_base_quote((base, quote, 30))
+                .expect("pair was created");
+
+            let calls: Vec<Call> = vec![
+                swap::Call::<Test>::swap_buy(tp_hash, u128::max_value()).into(),
+                swap::Call::<Test>::swap_sell(tp_hash, u128::max_value()).into(),
+                swap::Call::<Test>::swap_by_tokens(quote, base, u128::max_value()).into(),
+            ];
+            calls
+                .iter()
+                .flat_map(|call| (1u8, call).encode())
+                .collect::<Vec<u8>>()
+        });
+        assert_eq!(HUGE_SWAPS, &expected[..]);
+
+        run(HUGE_SWAPS, |input| Call::decode(input).ok());
+    }
+}
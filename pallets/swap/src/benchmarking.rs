@@ -0,0 +1,643 @@
+//! Benchmarks for the swap pallet.
+
+#![cfg(feature = "runtime-benchmarks")]
+
+use super::*;
+use concentrated::{MAX_INITIALIZED_TICKS, MAX_TICK, MAX_TICKS_CROSSED, MIN_TICK};
+use frame_benchmarking::{benchmarks, whitelisted_caller};
+use frame_system::RawOrigin;
+use sp_std::prelude::*;
+
+const TOKEN_SUPPLY: u32 = 1_000_000_000;
+const INITIAL_LIQUIDITY: u32 = 1_000_000;
+const TRADE_AMOUNT: u32 = 100_000;
+/// Quote per base of StableSwap pairs, far enough from the peg for the invariant to take many
+/// iterations to solve.
+const STABLE_IMBALANCE: u32 = 100;
+/// Supply of the tokens of concentrated liquidity pairs, holding a full range position of
+/// `CONCENTRATED_LIQUIDITY`.
+const CONCENTRATED_SUPPLY: u128 = 1 << 120;
+const CONCENTRATED_LIQUIDITY: u128 = 1 << 100;
+/// Amount swapped in concentrated liquidity pairs, moving the price past every nested position.
+const CONCENTRATED_TRADE_AMOUNT: u128 = 1 << 96;
+/// Amount swapped in concentrated liquidity pairs for their positions to earn fees, moving the
+/// price by a few ticks.
+const FEE_TRADE_AMOUNT: u128 = 1 << 88;
+/// Ticks between the boundaries of nested positions.
+const TICK_STEP: i32 = 10;
+/// First tick of the positions out of the way of the swaps, filling the initialized ticks.
+const FAR_TICK: i32 = 100_000;
+
+fn issue_tokens<T: Trait>(caller: &T::AccountId) -> Result<(T::Hash, T::Hash), &'static str> {
+    issue_tokens_with_supply::<T>(caller, TOKEN_SUPPLY.into())
+}
+
+fn issue_tokens_with_supply<T: Trait>(
+    caller: &T::AccountId,
+    supply: BalanceOf<T>,
+) -> Result<(T::Hash, T::Hash), &'static str> {
+    let base = T::Currency::issue(caller, b"BASE".to_vec(), supply, TokenType::Normal)?;
+    let quote = T::Currency::issue(caller, b"QUOTE".to_vec(), supply, TokenType::Normal)?;
+
+    Ok((base, quote))
+}
+
+/// The highest fee tier, so that swaps accrue the largest protocol fee.
+fn fee_tier<T: Trait>() -> u32 {
+    T::FeeTiers::get().into_iter().max().unwrap_or_default()
+}
+
+/// A constant product pair of two new tokens, with liquidity added by `caller`.
+fn create_pair<T: Trait>(caller: &T::AccountId) -> Result<T::Hash, &'static str> {
+    let (base, quote) = issue_tokens::<T>(caller)?;
+    create_pair_in_tier::<T>(caller, base, quote, fee_tier::<T>())
+}
+
+/// A constant product pair of `base` and `quote` in `fee_tier`, with liquidity added by `caller`.
+fn create_pair_in_tier<T: Trait>(
+    caller: &T::AccountId,
+    base: T::Hash,
+    quote: T::Hash,
+    fee_tier: u32,
+) -> Result<T::Hash, &'static str> {
+    Module::<T>::insert_trade_pair(
+        caller.clone(),
+        base,
+        quote,
+        PoolKind::ConstantProduct,
+        fee_tier,
+    )?;
+    let hash = Module::<T>::trade_pair_hash_by_base_quote((base, quote, fee_tier))
+        .ok_or("trade pair not created")?;
+    Module::<T>::do_add_liquidity(
+        caller.clone(),
+        hash,
+        INITIAL_LIQUIDITY.into(),
+        Some((2 * INITIAL_LIQUIDITY).into()),
+    )?;
+
+    Ok(hash)
+}
+
+/// A StableSwap pair of two new tokens, with imbalanced liquidity added by `caller`.
+fn create_stable_pair<T: Trait>(caller: &T::AccountId) -> Result<T::Hash, &'static str> {
+    let (base, quote) = issue_tokens::<T>(caller)?;
+    let fee_tier = fee_tier::<T>();
+    Module::<T>::insert_trade_pair(caller.clone(), base, quote, PoolKind::StableSwap, fee_tier)?;
+    let hash = Module::<T>::trade_pair_hash_by_base_quote((base, quote, fee_tier))
+        .ok_or("trade pair not created")?;
+    Module::<T>::do_add_liquidity(
+        caller.clone(),
+        hash,
+        INITIAL_LIQUIDITY.into(),
+        Some((STABLE_IMBALANCE * INITIAL_LIQUIDITY).into()),
+    )?;
+
+    Ok(hash)
+}
+
+/// A concentrated liquidity pair of `base` and `quote` in `fee_tier` priced at one, with
+/// positions of `caller` over the full range and between the ticks `TICK_STEP * i` away from
+/// the price on both sides for `i` up to `ticks`, and the initialized ticks filled up to two
+/// short of the bound by positions far from the price.
+fn create_concentrated_pair<T: Trait>(
+    caller: &T::AccountId,
+    base: T::Hash,
+    quote: T::Hash,
+    fee_tier: u32,
+    ticks: u32,
+) -> Result<T::Hash, &'static str> {
+    Module::<T>::insert_trade_pair(
+        caller.clone(),
+        base,
+        quote,
+        PoolKind::Concentrated,
+        fee_tier,
+    )?;
+    let hash = Module::<T>::trade_pair_hash_by_base_quote((base, quote, fee_tier))
+        .ok_or("trade pair not created")?;
+    Module::<T>::do_initialize_concentrated_pool(hash, 1 << 64)?;
+
+    Module::<T>::do_mint_position(
+        caller.clone(),
+        hash,
+        MIN_TICK,
+        MAX_TICK,
+        CONCENTRATED_LIQUIDITY,
+    )?;
+    for i in 1..=ticks as i32 {
+        Module::<T>::do_mint_position(
+            caller.clone(),
+            hash,
+            -TICK_STEP * i,
+            TICK_STEP * i,
+            CONCENTRATED_LIQUIDITY >> 10,
+        )?;
+    }
+
+    let mut tick = FAR_TICK;
+    while Module::<T>::initialized_ticks(hash).len() < MAX_INITIALIZED_TICKS as usize - 2 {
+        Module::<T>::do_mint_position(caller.clone(), hash, tick, tick + 1, 1)?;
+        tick += 2;
+    }
+
+    Ok(hash)
+}
+
+fn issue_weighted_tokens<T: Trait>(
+    caller: &T::AccountId,
+    n: u32,
+) -> Result<Vec<T::Hash>, &'static str> {
+    let tokens = (0..n)
+        .map(|i| {
+            T::Currency::issue(
+                caller,
+                [b'W', b'0' + i as u8].to_vec(),
+                TOKEN_SUPPLY.into(),
+                TokenType::Normal,
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(tokens)
+}
+
+/// A weighted pool of `n` new tokens with equal weights, seeded by `caller`.
+fn create_equal_weighted_pool<T: Trait>(
+    caller: &T::AccountId,
+    n: u32,
+) -> Result<(T::Hash, Vec<T::Hash>), &'static str> {
+    let tokens = issue_weighted_tokens::<T>(caller, n)?;
+    Module::<T>::do_create_weighted_pool(
+        caller.clone(),
+        tokens.clone(),
+        vec![1; n as usize],
+        vec![INITIAL_LIQUIDITY.into(); n as usize],
+    )?;
+    let index = Module::<T>::weighted_pool_index() - 1;
+    let hash = Module::<T>::weighted_pool_hash_by_index(index).ok_or("pool not created")?;
+
+    Ok((hash, tokens))
+}
+
+/// Pay the protocol fee to `recipient`.
+fn enable_protocol_fee<T: Trait>(recipient: &T::AccountId) {
+    ProtocolFeeRecipient::<T>::put(recipient);
+    ProtocolFeeShare::put(Permill::from_parts(166_667));
+}
+
+/// A call doing nothing, run as the borrower logic of flash swaps and loans.
+fn remark<T: Trait>() -> <T as Trait>::Call {
+    frame_system::Call::<T>::remark(Vec::new()).into()
+}
+
+benchmarks! {
+    _ { }
+
+    create_trade_pair {
+        let caller: T::AccountId = whitelisted_caller();
+        let (base, quote) = issue_tokens::<T>(&caller)?;
+        let fee_tier = fee_tier::<T>();
+    }: _(RawOrigin::Signed(caller), base, quote, PoolKind::ConstantProduct, fee_tier)
+    verify {
+        assert!(Module::<T>::trade_pair_hash_by_base_quote((base, quote, fee_tier)).is_some());
+    }
+
+    // minting the protocol fee
+    add_liquidity {
+        let caller: T::AccountId = whitelisted_caller();
+        enable_protocol_fee::<T>(&caller);
+        let hash = create_pair::<T>(&caller)?;
+        Module::<T>::do_swap_buy(caller.clone(), hash, TRADE_AMOUNT.into())?;
+    }: _(RawOrigin::Signed(caller), hash, TRADE_AMOUNT.into(), None)
+    verify {
+        assert!(Module::<T>::protocol_fees_accrued(hash) > Zero::zero());
+    }
+
+    // minting the protocol fee
+    remove_liquidity {
+        let caller: T::AccountId = whitelisted_caller();
+        enable_protocol_fee::<T>(&caller);
+        let hash = create_pair::<T>(&caller)?;
+        Module::<T>::do_swap_buy(caller.clone(), hash, TRADE_AMOUNT.into())?;
+    }: _(RawOrigin::Signed(caller), hash, TRADE_AMOUNT.into())
+    verify {
+        assert!(Module::<T>::protocol_fees_accrued(hash) > Zero::zero());
+    }
+
+    // checking the circuit breaker
+    swap_buy {
+        let caller: T::AccountId = whitelisted_caller();
+        let hash = create_pair::<T>(&caller)?;
+        CircuitBreakers::<T>::insert(hash, Permill::one());
+    }: _(RawOrigin::Signed(caller), hash, TRADE_AMOUNT.into())
+    verify {
+        assert!(Module::<T>::block_start_price(hash).is_some());
+    }
+
+    // checking the circuit breaker
+    swap_sell {
+        let caller: T::AccountId = whitelisted_caller();
+        let hash = create_pair::<T>(&caller)?;
+        CircuitBreakers::<T>::insert(hash, Permill::one());
+    }: _(RawOrigin::Signed(caller), hash, TRADE_AMOUNT.into())
+    verify {
+        assert!(Module::<T>::block_start_price(hash).is_some());
+    }
+
+    // delisting the pair
+    delist_trade_pair {
+        let caller: T::AccountId = whitelisted_caller();
+        let hash = create_pair::<T>(&caller)?;
+    }: _(RawOrigin::Root, hash)
+    verify {
+        assert!(Module::<T>::is_delisted(hash));
+    }
+
+    // solving the invariant of imbalanced reserves and checking the circuit breaker
+    swap_buy_stable {
+        let caller: T::AccountId = whitelisted_caller();
+        let hash = create_stable_pair::<T>(&caller)?;
+        CircuitBreakers::<T>::insert(hash, Permill::one());
+    }: swap_buy(RawOrigin::Signed(caller), hash, TRADE_AMOUNT.into())
+    verify {
+        assert!(Module::<T>::block_start_price(hash).is_some());
+    }
+
+    // solving the invariant of imbalanced reserves and checking the circuit breaker
+    swap_sell_stable {
+        let caller: T::AccountId = whitelisted_caller();
+        let hash = create_stable_pair::<T>(&caller)?;
+        CircuitBreakers::<T>::insert(hash, Permill::one());
+    }: swap_sell(RawOrigin::Signed(caller), hash, TRADE_AMOUNT.into())
+    verify {
+        assert!(Module::<T>::block_start_price(hash).is_some());
+    }
+
+    // crossing `t` initialized ticks out of the most a pair holds and checking the circuit
+    // breaker
+    swap_buy_concentrated {
+        let t in 0 .. MAX_TICKS_CROSSED;
+        let caller: T::AccountId = whitelisted_caller();
+        let (base, quote) =
+            issue_tokens_with_supply::<T>(&caller, CONCENTRATED_SUPPLY.saturated_into())?;
+        let hash = create_concentrated_pair::<T>(&caller, base, quote, fee_tier::<T>(), t)?;
+        CircuitBreakers::<T>::insert(hash, Permill::one());
+    }: swap_buy(RawOrigin::Signed(caller), hash, CONCENTRATED_TRADE_AMOUNT.saturated_into())
+    verify {
+        let pool = Module::<T>::concentrated_pool(hash).expect("pool was initialized");
+        assert!(pool.tick < -TICK_STEP * t as i32);
+    }
+
+    // crossing `t` initialized ticks out of the most a pair holds and checking the circuit
+    // breaker
+    swap_sell_concentrated {
+        let t in 0 .. MAX_TICKS_CROSSED;
+        let caller: T::AccountId = whitelisted_caller();
+        let (base, quote) =
+            issue_tokens_with_supply::<T>(&caller, CONCENTRATED_SUPPLY.saturated_into())?;
+        let hash = create_concentrated_pair::<T>(&caller, base, quote, fee_tier::<T>(), t)?;
+        CircuitBreakers::<T>::insert(hash, Permill::one());
+    }: swap_sell(RawOrigin::Signed(caller), hash, CONCENTRATED_TRADE_AMOUNT.saturated_into())
+    verify {
+        let pool = Module::<T>::concentrated_pool(hash).expect("pool was initialized");
+        assert!(pool.tick >= TICK_STEP * t as i32);
+    }
+
+    // quoting a swap crossing the most ticks in every fee tier, concentrated liquidity pairs
+    // reading a tick for every crossing, and checking the circuit breaker
+    swap_by_tokens {
+        let t in 1 .. T::FeeTiers::get().len() as u32;
+        let caller: T::AccountId = whitelisted_caller();
+        let (base, quote) =
+            issue_tokens_with_supply::<T>(&caller, CONCENTRATED_SUPPLY.saturated_into())?;
+        for fee_tier in T::FeeTiers::get().into_iter().take(t as usize) {
+            let hash =
+                create_concentrated_pair::<T>(&caller, base, quote, fee_tier, MAX_TICKS_CROSSED)?;
+            CircuitBreakers::<T>::insert(hash, Permill::one());
+        }
+        let quote_amount = T::Currency::free_balance(quote, &caller);
+    }: _(
+        RawOrigin::Signed(caller.clone()),
+        base,
+        quote,
+        CONCENTRATED_TRADE_AMOUNT.saturated_into()
+    )
+    verify {
+        assert!(T::Currency::free_balance(quote, &caller) > quote_amount);
+    }
+
+    // bisecting for the amount in up to the step bound in every fee tier, each step quoting a
+    // swap crossing up to the most ticks
+    best_trade_pair_for_amount_out {
+        let t in 1 .. T::FeeTiers::get().len() as u32;
+        let caller: T::AccountId = whitelisted_caller();
+        let (base, quote) =
+            issue_tokens_with_supply::<T>(&caller, CONCENTRATED_SUPPLY.saturated_into())?;
+        for fee_tier in T::FeeTiers::get().into_iter().take(t as usize) {
+            create_concentrated_pair::<T>(&caller, base, quote, fee_tier, MAX_TICKS_CROSSED)?;
+        }
+        // the bisection from the largest amount in takes more steps than the bound
+        let amount_out: BalanceOf<T> = (CONCENTRATED_TRADE_AMOUNT >> 2).saturated_into();
+        let max_amount_in: BalanceOf<T> = CONCENTRATED_TRADE_AMOUNT.saturated_into();
+    }: {
+        Module::<T>::best_trade_pair_for_amount_out(base, quote, amount_out, max_amount_in);
+    }
+    verify {
+        assert!(Module::<T>::best_trade_pair_for_amount_out(
+            base,
+            quote,
+            amount_out,
+            max_amount_in
+        )
+        .is_some());
+    }
+
+    // checking the invariant of a StableSwap pair, failing at the last step as the borrower
+    // logic does not pay the pair back
+    flash_swap {
+        let caller: T::AccountId = whitelisted_caller();
+        let hash = create_stable_pair::<T>(&caller)?;
+        let data = remark::<T>().encode();
+    }: {
+        let _ = Module::<T>::flash_swap(
+            RawOrigin::Signed(caller.clone()).into(),
+            hash,
+            TRADE_AMOUNT.into(),
+            Zero::zero(),
+            data,
+        );
+    }
+    verify {
+        assert!(!Module::<T>::is_locked(hash));
+    }
+
+    // failing at the last step as the borrower call does not pay the pair back
+    flash_loan {
+        let caller: T::AccountId = whitelisted_caller();
+        let hash = create_pair::<T>(&caller)?;
+        let tp = Module::<T>::trade_pair(hash).ok_or("trade pair not created")?;
+    }: {
+        let _ = Module::<T>::flash_loan(
+            RawOrigin::Signed(caller.clone()).into(),
+            hash,
+            tp.base,
+            TRADE_AMOUNT.into(),
+            Box::new(remark::<T>()),
+        );
+    }
+    verify {
+        assert!(!Module::<T>::is_locked(hash));
+    }
+
+    ramp_amplification {
+        let caller: T::AccountId = whitelisted_caller();
+        let hash = create_stable_pair::<T>(&caller)?;
+        let future_block = frame_system::Module::<T>::block_number() + 100u32.into();
+    }: _(RawOrigin::Root, hash, stable::MAX_AMPLIFICATION, future_block)
+    verify {
+        assert!(Module::<T>::amplification(hash).is_some());
+    }
+
+    set_protocol_fee {
+        let caller: T::AccountId = whitelisted_caller();
+    }: _(RawOrigin::Root, Some(caller.clone()), Permill::from_parts(166_667))
+    verify {
+        assert_eq!(Module::<T>::protocol_fee_recipient(), Some(caller));
+    }
+
+    pause_pair {
+        let caller: T::AccountId = whitelisted_caller();
+        let hash = create_pair::<T>(&caller)?;
+    }: _(RawOrigin::Root, hash)
+    verify {
+        assert!(Module::<T>::is_pair_paused(hash));
+    }
+
+    unpause_pair {
+        let caller: T::AccountId = whitelisted_caller();
+        let hash = create_pair::<T>(&caller)?;
+        PausedPairs::<T>::insert(hash, true);
+    }: _(RawOrigin::Root, hash)
+    verify {
+        assert!(!Module::<T>::is_pair_paused(hash));
+    }
+
+    pause_all {
+    }: _(RawOrigin::Root)
+    verify {
+        assert!(Module::<T>::is_all_paused());
+    }
+
+    unpause_all {
+        AllPaused::put(true);
+    }: _(RawOrigin::Root)
+    verify {
+        assert!(!Module::<T>::is_all_paused());
+    }
+
+    // removing the circuit breaker along with the price at the start of the block
+    set_circuit_breaker {
+        let caller: T::AccountId = whitelisted_caller();
+        let hash = create_pair::<T>(&caller)?;
+        CircuitBreakers::<T>::insert(hash, Permill::one());
+        Module::<T>::do_swap_buy(caller.clone(), hash, TRADE_AMOUNT.into())?;
+    }: _(RawOrigin::Root, hash, None)
+    verify {
+        assert!(Module::<T>::block_start_price(hash).is_none());
+    }
+
+    initialize_concentrated_pool {
+        let caller: T::AccountId = whitelisted_caller();
+        let (base, quote) = issue_tokens::<T>(&caller)?;
+        let fee_tier = fee_tier::<T>();
+        Module::<T>::insert_trade_pair(
+            caller.clone(),
+            base,
+            quote,
+            PoolKind::Concentrated,
+            fee_tier,
+        )?;
+        let hash = Module::<T>::trade_pair_hash_by_base_quote((base, quote, fee_tier))
+            .ok_or("trade pair not created")?;
+    }: _(RawOrigin::Signed(caller), hash, 1 << 64)
+    verify {
+        assert!(Module::<T>::concentrated_pool(hash).is_some());
+    }
+
+    // initializing both ticks of the position, the second one filling the initialized ticks
+    mint_position {
+        let caller: T::AccountId = whitelisted_caller();
+        let (base, quote) =
+            issue_tokens_with_supply::<T>(&caller, CONCENTRATED_SUPPLY.saturated_into())?;
+        let hash = create_concentrated_pair::<T>(&caller, base, quote, fee_tier::<T>(), 0)?;
+    }: _(
+        RawOrigin::Signed(caller),
+        hash,
+        -TICK_STEP,
+        TICK_STEP,
+        CONCENTRATED_LIQUIDITY
+    )
+    verify {
+        assert_eq!(
+            Module::<T>::initialized_ticks(hash).len(),
+            MAX_INITIALIZED_TICKS as usize
+        );
+    }
+
+    // accounting the fees earned by the position and clearing both of its ticks
+    decrease_position {
+        let caller: T::AccountId = whitelisted_caller();
+        let (base, quote) =
+            issue_tokens_with_supply::<T>(&caller, CONCENTRATED_SUPPLY.saturated_into())?;
+        // the nested position, minted right after the full range one
+        let position_id = Module::<T>::next_position_id() + 1;
+        let hash = create_concentrated_pair::<T>(&caller, base, quote, fee_tier::<T>(), 1)?;
+        Module::<T>::do_swap_buy(caller.clone(), hash, FEE_TRADE_AMOUNT.saturated_into())?;
+        let position = Module::<T>::position(position_id).ok_or("position not minted")?;
+    }: _(RawOrigin::Signed(caller), position_id, position.liquidity)
+    verify {
+        assert!(Module::<T>::initialized_ticks(hash).len() < MAX_INITIALIZED_TICKS as usize - 2);
+    }
+
+    collect_position {
+        let caller: T::AccountId = whitelisted_caller();
+        let (base, quote) =
+            issue_tokens_with_supply::<T>(&caller, CONCENTRATED_SUPPLY.saturated_into())?;
+        // the full range position
+        let position_id = Module::<T>::next_position_id();
+        let hash = create_concentrated_pair::<T>(&caller, base, quote, fee_tier::<T>(), 0)?;
+        Module::<T>::do_swap_buy(caller.clone(), hash, FEE_TRADE_AMOUNT.saturated_into())?;
+        Module::<T>::do_decrease_position(
+            caller.clone(),
+            position_id,
+            CONCENTRATED_LIQUIDITY >> 1,
+        )?;
+    }: _(RawOrigin::Signed(caller), position_id)
+    verify {
+        let position = Module::<T>::position(position_id).expect("position was minted");
+        assert!(position.tokens_owed_base.is_zero() && position.tokens_owed_quote.is_zero());
+    }
+
+    create_weighted_pool {
+        let n in (weighted::MIN_WEIGHTED_TOKENS as u32) .. (weighted::MAX_WEIGHTED_TOKENS as u32);
+        let caller: T::AccountId = whitelisted_caller();
+        let tokens = issue_weighted_tokens::<T>(&caller, n)?;
+        let index = Module::<T>::weighted_pool_index();
+    }: _(
+        RawOrigin::Signed(caller),
+        tokens,
+        vec![1; n as usize],
+        vec![INITIAL_LIQUIDITY.into(); n as usize]
+    )
+    verify {
+        assert!(Module::<T>::weighted_pool_hash_by_index(index).is_some());
+    }
+
+    join_pool {
+        let n in (weighted::MIN_WEIGHTED_TOKENS as u32) .. (weighted::MAX_WEIGHTED_TOKENS as u32);
+        let caller: T::AccountId = whitelisted_caller();
+        let (hash, _) = create_equal_weighted_pool::<T>(&caller, n)?;
+        let pool = Module::<T>::weighted_pool(hash).ok_or("pool not created")?;
+        let max_amounts_in = vec![INITIAL_LIQUIDITY.into(); n as usize];
+    }: _(RawOrigin::Signed(caller), hash, TRADE_AMOUNT.into(), max_amounts_in)
+    verify {
+        let new_pool = Module::<T>::weighted_pool(hash).expect("pool was created");
+        assert!(new_pool.pool_token_issued_amount > pool.pool_token_issued_amount);
+    }
+
+    join_pool_single {
+        let caller: T::AccountId = whitelisted_caller();
+        let (hash, tokens) =
+            create_equal_weighted_pool::<T>(&caller, weighted::MAX_WEIGHTED_TOKENS as u32)?;
+        let pool = Module::<T>::weighted_pool(hash).ok_or("pool not created")?;
+    }: _(RawOrigin::Signed(caller), hash, tokens[0], TRADE_AMOUNT.into(), Zero::zero())
+    verify {
+        let new_pool = Module::<T>::weighted_pool(hash).expect("pool was created");
+        assert!(new_pool.pool_token_issued_amount > pool.pool_token_issued_amount);
+    }
+
+    exit_pool {
+        let n in (weighted::MIN_WEIGHTED_TOKENS as u32) .. (weighted::MAX_WEIGHTED_TOKENS as u32);
+        let caller: T::AccountId = whitelisted_caller();
+        let (hash, _) = create_equal_weighted_pool::<T>(&caller, n)?;
+        let pool = Module::<T>::weighted_pool(hash).ok_or("pool not created")?;
+        let min_amounts_out = vec![Zero::zero(); n as usize];
+    }: _(RawOrigin::Signed(caller), hash, TRADE_AMOUNT.into(), min_amounts_out)
+    verify {
+        let new_pool = Module::<T>::weighted_pool(hash).expect("pool was created");
+        assert!(new_pool.pool_token_issued_amount < pool.pool_token_issued_amount);
+    }
+
+    exit_pool_single {
+        let caller: T::AccountId = whitelisted_caller();
+        let (hash, tokens) =
+            create_equal_weighted_pool::<T>(&caller, weighted::MAX_WEIGHTED_TOKENS as u32)?;
+        let pool = Module::<T>::weighted_pool(hash).ok_or("pool not created")?;
+    }: _(RawOrigin::Signed(caller), hash, tokens[0], TRADE_AMOUNT.into(), Zero::zero())
+    verify {
+        let new_pool = Module::<T>::weighted_pool(hash).expect("pool was created");
+        assert!(new_pool.pool_token_issued_amount < pool.pool_token_issued_amount);
+    }
+
+    swap_weighted {
+        let caller: T::AccountId = whitelisted_caller();
+        let (hash, tokens) =
+            create_equal_weighted_pool::<T>(&caller, weighted::MAX_WEIGHTED_TOKENS as u32)?;
+        let amount_out = T::Currency::free_balance(tokens[1], &caller);
+    }: _(
+        RawOrigin::Signed(caller.clone()),
+        hash,
+        tokens[0],
+        TRADE_AMOUNT.into(),
+        tokens[1],
+        Zero::zero()
+    )
+    verify {
+        assert!(T::Currency::free_balance(tokens[1], &caller) > amount_out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{new_test_ext, Test};
+    use frame_support::assert_ok;
+
+    #[test]
+    fn benchmarks_work() {
+        new_test_ext().execute_with(|| {
+            assert_ok!(test_benchmark_create_trade_pair::<Test>());
+            assert_ok!(test_benchmark_delist_trade_pair::<Test>());
+            assert_ok!(test_benchmark_add_liquidity::<Test>());
+            assert_ok!(test_benchmark_remove_liquidity::<Test>());
+            assert_ok!(test_benchmark_swap_buy::<Test>());
+            assert_ok!(test_benchmark_swap_sell::<Test>());
+            assert_ok!(test_benchmark_swap_buy_stable::<Test>());
+            assert_ok!(test_benchmark_swap_sell_stable::<Test>());
+            assert_ok!(test_benchmark_swap_buy_concentrated::<Test>());
+            assert_ok!(test_benchmark_swap_sell_concentrated::<Test>());
+            assert_ok!(test_benchmark_swap_by_tokens::<Test>());
+            assert_ok!(test_benchmark_best_trade_pair_for_amount_out::<Test>());
+            assert_ok!(test_benchmark_flash_swap::<Test>());
+            assert_ok!(test_benchmark_flash_loan::<Test>());
+            assert_ok!(test_benchmark_ramp_amplification::<Test>());
+            assert_ok!(test_benchmark_set_protocol_fee::<Test>());
+            assert_ok!(test_benchmark_pause_pair::<Test>());
+            assert_ok!(test_benchmark_unpause_pair::<Test>());
+            assert_ok!(test_benchmark_pause_all::<Test>());
+            assert_ok!(test_benchmark_unpause_all::<Test>());
+            assert_ok!(test_benchmark_set_circuit_breaker::<Test>());
+            assert_ok!(test_benchmark_initialize_concentrated_pool::<Test>());
+            assert_ok!(test_benchmark_mint_position::<Test>());
+            assert_ok!(test_benchmark_decrease_position::<Test>());
+            assert_ok!(test_benchmark_collect_position::<Test>());
+            assert_ok!(test_benchmark_create_weighted_pool::<Test>());
+            assert_ok!(test_benchmark_join_pool::<Test>());
+            assert_ok!(test_benchmark_join_pool_single::<Test>());
+            assert_ok!(test_benchmark_exit_pool::<Test>());
+            assert_ok!(test_benchmark_exit_pool_single::<Test>());
+            assert_ok!(test_benchmark_swap_weighted::<Test>());
+        });
+    }
+}
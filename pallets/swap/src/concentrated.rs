@@ -0,0 +1,811 @@
+//! Concentrated liquidity, as in Uniswap V3.
+//!
+//! Prices are quote per base and are tracked as their square root in Q64.64 fixed point.
+//! Tick `i` is the price `1.0001^i`; a position provides liquidity `L` between two ticks, and
+//! swaps move the price across initialized ticks, changing the active liquidity as they go.
+
+use codec::{Decode, Encode};
+use frame_support::{dispatch, ensure};
+use sp_core::U256;
+use sp_runtime::{
+    traits::{Saturating, Zero},
+    RuntimeDebug,
+};
+use sp_std::{
+    convert::{TryFrom, TryInto},
+    prelude::*,
+};
+
+use crate::{
+    BalanceOf, ConcentratedPools, Error, InitializedTicks, Module, NextPositionId, PoolKind,
+    Positions, RawEvent, Ticks, Trait,
+};
+use token::MultiCurrency;
+
+/// Lowest tick, at a price of about `2^-64`.
+pub const MIN_TICK: i32 = -443636;
+/// Highest tick, at a price of about `2^64`.
+pub const MAX_TICK: i32 = 443636;
+/// Square root price at `MIN_TICK`.
+pub const MIN_SQRT_PRICE: u128 = 4295048017;
+/// Square root price at `MAX_TICK`.
+pub const MAX_SQRT_PRICE: u128 = 79226673515401279992447579062;
+const FEE_DENOMINATOR: u128 = 1_000_000;
+/// Most initialized ticks of a pair, bounding the ticks read by every swap.
+pub const MAX_INITIALIZED_TICKS: u32 = 512;
+/// Most initialized ticks a swap crosses, bounding the ticks written by every swap.
+pub const MAX_TICKS_CROSSED: u32 = 32;
+
+/// `2^128 / sqrt(1.0001)^(2^i)`
+const SQRT_RATIOS: [u128; 19] = [
+    0xfffcb933bd6fad37aa2d162d1a594001,
+    0xfff97272373d413259a46990580e2139,
+    0xfff2e50f5f656932ef12357cf3c7fdcb,
+    0xffe5caca7e10e4e61c3624eaa0941ccf,
+    0xffcb9843d60f6159c9db58835c926643,
+    0xff973b41fa98c081472e6896dfb254bf,
+    0xff2ea16466c96a3843ec78b326b52860,
+    0xfe5dee046a99a2a811c461f1969c3052,
+    0xfcbe86c7900a88aedcffc83b479aa3a3,
+    0xf987a7253ac413176f2b074cf7815e53,
+    0xf3392b0822b70005940c7a398e4b70f2,
+    0xe7159475a2c29b7443b29c7fa6e889d8,
+    0xd097f3bdfd2022b8845ad8f792aa5825,
+    0xa9f746462d870fdf8a65dc1f90e061e4,
+    0x70d869a156d2a1b890bb3df62baf32f6,
+    0x31be135f97d08fd981231505542fcfa5,
+    0x9aa508b5b7a84e1c677de54f3e99bc8,
+    0x5d6af8dedb81196699c329225ee604,
+    0x2216e584f5fa1ea926041bedfe97,
+];
+
+/// State of a concentrated liquidity pair.
+#[derive(Encode, Decode, Clone, Copy, Default, PartialEq, Eq, RuntimeDebug)]
+pub struct ConcentratedPool {
+    /// Square root of the current price, Q64.64.
+    pub sqrt_price: u128,
+    /// Greatest tick whose price is not above the current price.
+    pub tick: i32,
+    /// Liquidity of the positions in range.
+    pub liquidity: u128,
+    /// Base fees earned per unit of liquidity since the pool started, Q64.64, wrapping.
+    pub fee_growth_global_base: u128,
+    /// Quote fees earned per unit of liquidity since the pool started, Q64.64, wrapping.
+    pub fee_growth_global_quote: u128,
+}
+
+/// An initialized tick, the boundary of at least one position.
+#[derive(Encode, Decode, Clone, Copy, Default, PartialEq, Eq, RuntimeDebug)]
+pub struct Tick {
+    /// Liquidity of the positions using this tick as a boundary.
+    pub liquidity_gross: u128,
+    /// Liquidity added to the active liquidity when the price crosses this tick upwards.
+    pub liquidity_net: i128,
+    /// Base fee growth on the other side of this tick from the current price.
+    pub fee_growth_outside_base: u128,
+    /// Quote fee growth on the other side of this tick from the current price.
+    pub fee_growth_outside_quote: u128,
+}
+
+/// Liquidity provided by `owner` between `tick_lower` and `tick_upper`.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct Position<AccountId, Hash, Balance> {
+    pub owner: AccountId,
+    pub tp_hash: Hash,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+    /// Base fee growth inside the range when the fees were last accounted.
+    pub fee_growth_inside_base_last: u128,
+    /// Quote fee growth inside the range when the fees were last accounted.
+    pub fee_growth_inside_quote_last: u128,
+    /// Base withdrawn from the range and fees not collected yet.
+    pub tokens_owed_base: Balance,
+    /// Quote withdrawn from the range and fees not collected yet.
+    pub tokens_owed_quote: Balance,
+}
+
+/// Outcome of a swap against a concentrated pool, applied once the tokens have moved.
+pub struct ConcentratedSwap {
+    pub amount_out: u128,
+    pool: ConcentratedPool,
+    crossed_ticks: Vec<(i32, Tick)>,
+}
+
+impl ConcentratedSwap {
+    /// Square root price of the pool once the swap is applied.
+    pub fn sqrt_price(&self) -> u128 {
+        self.pool.sqrt_price
+    }
+}
+
+fn to_u128(value: U256) -> Option<u128> {
+    if value > U256::from(u128::max_value()) {
+        return None;
+    }
+
+    Some(value.low_u128())
+}
+
+fn div_rounding_up(numerator: U256, denominator: U256) -> U256 {
+    let (quotient, remainder) = numerator.div_mod(denominator);
+    if remainder.is_zero() {
+        quotient
+    } else {
+        quotient + U256::one()
+    }
+}
+
+fn add_liquidity_delta(liquidity: u128, delta: i128) -> Option<u128> {
+    if delta < 0 {
+        liquidity.checked_sub(delta.checked_neg()? as u128)
+    } else {
+        liquidity.checked_add(delta as u128)
+    }
+}
+
+/// Square root price of `tick`, rounded up. `tick` must be within `MIN_TICK..=MAX_TICK`.
+pub fn sqrt_price_at_tick(tick: i32) -> u128 {
+    let abs_tick = (tick as i64).abs() as u32;
+
+    let mut ratio = if abs_tick & 1 != 0 {
+        U256::from(SQRT_RATIOS[0])
+    } else {
+        U256::one() << 128
+    };
+    for (i, sqrt_ratio) in SQRT_RATIOS.iter().enumerate().skip(1) {
+        if abs_tick & (1 << i) != 0 {
+            ratio = (ratio * U256::from(*sqrt_ratio)) >> 128;
+        }
+    }
+    if tick > 0 {
+        ratio = U256::max_value() / ratio;
+    }
+
+    // Q128.128 to Q64.64
+    div_rounding_up(ratio, U256::one() << 64).low_u128()
+}
+
+/// Greatest tick whose square root price is not above `sqrt_price`.
+pub fn tick_at_sqrt_price(sqrt_price: u128) -> i32 {
+    let (mut low, mut high) = (MIN_TICK, MAX_TICK);
+    while low < high {
+        let mid = (low + high + 1) >> 1;
+        if sqrt_price_at_tick(mid) <= sqrt_price {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+
+    low
+}
+
+/// Base amount worth `liquidity` between two square root prices.
+pub fn amount_base_delta(
+    sqrt_price_a: u128,
+    sqrt_price_b: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> Option<u128> {
+    let (lower, upper) = if sqrt_price_a < sqrt_price_b {
+        (U256::from(sqrt_price_a), U256::from(sqrt_price_b))
+    } else {
+        (U256::from(sqrt_price_b), U256::from(sqrt_price_a))
+    };
+    if lower.is_zero() {
+        return None;
+    }
+
+    // L / sqrt(lower) - L / sqrt(upper)
+    let numerator = U256::from(liquidity) << 64;
+    let amount = if round_up {
+        div_rounding_up(numerator, lower) - numerator / upper
+    } else {
+        (numerator / lower).saturating_sub(div_rounding_up(numerator, upper))
+    };
+
+    to_u128(amount)
+}
+
+/// Quote amount worth `liquidity` between two square root prices.
+pub fn amount_quote_delta(
+    sqrt_price_a: u128,
+    sqrt_price_b: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> Option<u128> {
+    let (lower, upper) = if sqrt_price_a < sqrt_price_b {
+        (sqrt_price_a, sqrt_price_b)
+    } else {
+        (sqrt_price_b, sqrt_price_a)
+    };
+
+    // L * (sqrt(upper) - sqrt(lower))
+    let product = U256::from(liquidity) * U256::from(upper - lower);
+    let amount = if round_up {
+        div_rounding_up(product, U256::one() << 64)
+    } else {
+        product >> 64
+    };
+
+    to_u128(amount)
+}
+
+/// Square root price once `amount` of base is added to `liquidity` at `sqrt_price`, rounded up.
+fn next_sqrt_price_from_base_in(sqrt_price: u128, liquidity: u128, amount: u128) -> Option<u128> {
+    // L / (L / sqrt(P) + amount)
+    let numerator = U256::from(liquidity) << 64;
+    let denominator = (numerator / U256::from(sqrt_price)).checked_add(U256::from(amount))?;
+    if denominator.is_zero() {
+        return None;
+    }
+
+    Some(to_u128(div_rounding_up(numerator, denominator))?.min(sqrt_price))
+}
+
+/// Square root price once `amount` of quote is added to `liquidity` at `sqrt_price`, rounded
+/// down.
+fn next_sqrt_price_from_quote_in(sqrt_price: u128, liquidity: u128, amount: u128) -> Option<u128> {
+    // sqrt(P) + amount / L
+    let delta = (U256::from(amount) << 64).checked_div(U256::from(liquidity))?;
+
+    to_u128(U256::from(sqrt_price).checked_add(delta)?)
+}
+
+/// Swap as much of `amount_remaining` as possible without moving the price past
+/// `sqrt_price_target`, returning the new square root price and the amounts in, out and fee.
+/// `fee_per_million` is charged on the input and must be below one million.
+fn compute_swap_step(
+    sqrt_price_current: u128,
+    sqrt_price_target: u128,
+    liquidity: u128,
+    amount_remaining: u128,
+    fee_per_million: u128,
+    base_in: bool,
+) -> Option<(u128, u128, u128, u128)> {
+    let amount_remaining_less_fee = to_u128(
+        U256::from(amount_remaining) * U256::from(FEE_DENOMINATOR - fee_per_million)
+            / U256::from(FEE_DENOMINATOR),
+    )?;
+
+    let amount_to_target = if base_in {
+        amount_base_delta(sqrt_price_target, sqrt_price_current, liquidity, true)?
+    } else {
+        amount_quote_delta(sqrt_price_current, sqrt_price_target, liquidity, true)?
+    };
+
+    let sqrt_price_next = if amount_remaining_less_fee >= amount_to_target {
+        sqrt_price_target
+    } else if base_in {
+        next_sqrt_price_from_base_in(sqrt_price_current, liquidity, amount_remaining_less_fee)?
+    } else {
+        next_sqrt_price_from_quote_in(sqrt_price_current, liquidity, amount_remaining_less_fee)?
+    };
+    let reached_target = sqrt_price_next == sqrt_price_target;
+
+    let amount_in = if reached_target {
+        amount_to_target
+    } else if base_in {
+        amount_base_delta(sqrt_price_next, sqrt_price_current, liquidity, true)?
+    } else {
+        amount_quote_delta(sqrt_price_current, sqrt_price_next, liquidity, true)?
+    };
+    let amount_out = if base_in {
+        amount_quote_delta(sqrt_price_next, sqrt_price_current, liquidity, false)?
+    } else {
+        amount_base_delta(sqrt_price_current, sqrt_price_next, liquidity, false)?
+    };
+
+    let fee_amount = if reached_target {
+        to_u128(div_rounding_up(
+            U256::from(amount_in) * U256::from(fee_per_million),
+            U256::from(FEE_DENOMINATOR - fee_per_million),
+        ))?
+    } else {
+        // the price did not reach the target, so the rest of the input is taken as fee
+        amount_remaining.saturating_sub(amount_in)
+    };
+
+    Some((sqrt_price_next, amount_in, amount_out, fee_amount))
+}
+
+/// Base and quote amounts worth `liquidity` between `tick_lower` and `tick_upper` at the
+/// current price of `pool`.
+fn position_amounts(
+    pool: &ConcentratedPool,
+    tick_lower: i32,
+    tick_upper: i32,
+    liquidity: u128,
+    round_up: bool,
+) -> Option<(u128, u128)> {
+    let sqrt_price_lower = sqrt_price_at_tick(tick_lower);
+    let sqrt_price_upper = sqrt_price_at_tick(tick_upper);
+
+    if pool.tick < tick_lower {
+        Some((
+            amount_base_delta(sqrt_price_lower, sqrt_price_upper, liquidity, round_up)?,
+            0,
+        ))
+    } else if pool.tick < tick_upper {
+        Some((
+            amount_base_delta(pool.sqrt_price, sqrt_price_upper, liquidity, round_up)?,
+            amount_quote_delta(sqrt_price_lower, pool.sqrt_price, liquidity, round_up)?,
+        ))
+    } else {
+        Some((
+            0,
+            amount_quote_delta(sqrt_price_lower, sqrt_price_upper, liquidity, round_up)?,
+        ))
+    }
+}
+
+impl<T: Trait> Module<T> {
+    pub fn do_initialize_concentrated_pool(
+        hash: T::Hash,
+        sqrt_price: u128,
+    ) -> dispatch::DispatchResult {
+        let tp = Self::trade_pair(hash).ok_or(Error::<T>::NoMatchingTradePair)?;
+        ensure!(
+            tp.kind == PoolKind::Concentrated,
+            Error::<T>::UnsupportedPoolKind
+        );
+        ensure!(
+            !ConcentratedPools::<T>::contains_key(hash),
+            Error::<T>::PoolAlreadyInitialized
+        );
+        ensure!(
+            sqrt_price >= MIN_SQRT_PRICE && sqrt_price < MAX_SQRT_PRICE,
+            Error::<T>::InvalidSqrtPrice
+        );
+
+        let tick = tick_at_sqrt_price(sqrt_price);
+        ConcentratedPools::<T>::insert(
+            hash,
+            ConcentratedPool {
+                sqrt_price,
+                tick,
+                ..Default::default()
+            },
+        );
+
+        Self::deposit_event(RawEvent::ConcentratedPoolInitialized(
+            hash, sqrt_price, tick,
+        ));
+
+        Ok(())
+    }
+
+    pub fn do_mint_position(
+        sender: T::AccountId,
+        hash: T::Hash,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity: u128,
+    ) -> dispatch::DispatchResult {
+        let tp = Self::trade_pair(hash).ok_or(Error::<T>::NoMatchingTradePair)?;
+        ensure!(
+            tp.kind == PoolKind::Concentrated,
+            Error::<T>::UnsupportedPoolKind
+        );
+        ensure!(!Self::is_delisted(hash), Error::<T>::TradePairDelisted);
+        Self::ensure_not_locked(hash)?;
+        Self::ensure_not_paused(hash)?;
+        let mut pool = Self::concentrated_pool(hash).ok_or(Error::<T>::PoolNotInitialized)?;
+
+        ensure!(
+            tick_lower < tick_upper && tick_lower >= MIN_TICK && tick_upper <= MAX_TICK,
+            Error::<T>::InvalidTickRange
+        );
+        ensure!(liquidity > 0, Error::<T>::LiquidityIsZero);
+
+        let (base_amount, quote_amount) =
+            position_amounts(&pool, tick_lower, tick_upper, liquidity, true)
+                .ok_or(Error::<T>::ConcentratedMathOverflow)?;
+        let base_amount = Self::to_balance(base_amount)?;
+        let quote_amount = Self::to_balance(quote_amount)?;
+
+        T::Currency::ensure_can_withdraw(tp.base, &sender, base_amount)?;
+        T::Currency::ensure_can_withdraw(tp.quote, &sender, quote_amount)?;
+
+        if base_amount > Zero::zero() {
+            T::Currency::transfer(tp.base, &sender, &tp.account, base_amount)?;
+        }
+        if quote_amount > Zero::zero() {
+            T::Currency::transfer(tp.quote, &sender, &tp.account, quote_amount)?;
+        }
+
+        Self::update_tick(hash, &pool, tick_lower, liquidity, true, false)?;
+        Self::update_tick(hash, &pool, tick_upper, liquidity, true, true)?;
+        if tick_lower <= pool.tick && pool.tick < tick_upper {
+            pool.liquidity = pool
+                .liquidity
+                .checked_add(liquidity)
+                .ok_or(Error::<T>::ConcentratedMathOverflow)?;
+        }
+
+        let (fee_growth_inside_base, fee_growth_inside_quote) =
+            Self::fee_growth_inside(hash, &pool, tick_lower, tick_upper);
+        let position_id = Self::next_position_id();
+        Positions::<T>::insert(
+            position_id,
+            Position {
+                owner: sender.clone(),
+                tp_hash: hash,
+                tick_lower,
+                tick_upper,
+                liquidity,
+                fee_growth_inside_base_last: fee_growth_inside_base,
+                fee_growth_inside_quote_last: fee_growth_inside_quote,
+                tokens_owed_base: Zero::zero(),
+                tokens_owed_quote: Zero::zero(),
+            },
+        );
+        NextPositionId::mutate(|n| *n += 1);
+        ConcentratedPools::<T>::insert(hash, pool);
+
+        Self::sync(&tp);
+        Self::deposit_event(RawEvent::PositionMinted(
+            sender,
+            hash,
+            position_id,
+            base_amount,
+            quote_amount,
+        ));
+
+        Ok(())
+    }
+
+    /// Take `liquidity` out of a position. The amounts are owed to the position until
+    /// collected.
+    pub fn do_decrease_position(
+        sender: T::AccountId,
+        position_id: u64,
+        liquidity: u128,
+    ) -> dispatch::DispatchResult {
+        let mut position = Self::position(position_id).ok_or(Error::<T>::NoMatchingPosition)?;
+        ensure!(position.owner == sender, Error::<T>::NotPositionOwner);
+        Self::ensure_not_locked(position.tp_hash)?;
+        ensure!(liquidity > 0, Error::<T>::LiquidityIsZero);
+        ensure!(
+            position.liquidity >= liquidity,
+            Error::<T>::PositionLiquidityTooLow
+        );
+
+        let hash = position.tp_hash;
+        let mut pool = Self::concentrated_pool(hash).ok_or(Error::<T>::PoolNotInitialized)?;
+        Self::update_position_fees(&mut position, &pool)?;
+
+        Self::update_tick(hash, &pool, position.tick_lower, liquidity, false, false)?;
+        Self::update_tick(hash, &pool, position.tick_upper, liquidity, false, true)?;
+        if position.tick_lower <= pool.tick && pool.tick < position.tick_upper {
+            pool.liquidity -= liquidity;
+        }
+
+        let (base_amount, quote_amount) = position_amounts(
+            &pool,
+            position.tick_lower,
+            position.tick_upper,
+            liquidity,
+            false,
+        )
+        .ok_or(Error::<T>::ConcentratedMathOverflow)?;
+        let base_amount = Self::to_balance(base_amount)?;
+        let quote_amount = Self::to_balance(quote_amount)?;
+
+        position.liquidity -= liquidity;
+        position.tokens_owed_base = position.tokens_owed_base.saturating_add(base_amount);
+        position.tokens_owed_quote = position.tokens_owed_quote.saturating_add(quote_amount);
+        Positions::<T>::insert(position_id, position);
+        ConcentratedPools::<T>::insert(hash, pool);
+
+        Self::deposit_event(RawEvent::PositionDecreased(
+            sender,
+            position_id,
+            base_amount,
+            quote_amount,
+        ));
+
+        Ok(())
+    }
+
+    /// Send the amounts owed to a position to its owner, removing the position once it is
+    /// empty.
+    pub fn do_collect_position(sender: T::AccountId, position_id: u64) -> dispatch::DispatchResult {
+        let mut position = Self::position(position_id).ok_or(Error::<T>::NoMatchingPosition)?;
+        ensure!(position.owner == sender, Error::<T>::NotPositionOwner);
+
+        let tp = Self::trade_pair(position.tp_hash).ok_or(Error::<T>::NoMatchingTradePair)?;
+        Self::ensure_not_locked(tp.tp_hash)?;
+        if position.liquidity > 0 {
+            let pool = Self::concentrated_pool(tp.tp_hash).ok_or(Error::<T>::PoolNotInitialized)?;
+            Self::update_position_fees(&mut position, &pool)?;
+        }
+
+        let base_amount = position.tokens_owed_base;
+        let quote_amount = position.tokens_owed_quote;
+        if base_amount > Zero::zero() {
+            T::Currency::transfer(tp.base, &tp.account, &sender, base_amount)?;
+        }
+        if quote_amount > Zero::zero() {
+            T::Currency::transfer(tp.quote, &tp.account, &sender, quote_amount)?;
+        }
+
+        if position.liquidity == 0 {
+            Positions::<T>::remove(position_id);
+        } else {
+            position.tokens_owed_base = Zero::zero();
+            position.tokens_owed_quote = Zero::zero();
+            Positions::<T>::insert(position_id, position);
+        }
+
+        Self::sync(&tp);
+        Self::deposit_event(RawEvent::PositionCollected(
+            sender,
+            position_id,
+            base_amount,
+            quote_amount,
+        ));
+
+        Ok(())
+    }
+
+    /// Swap `amount_in` of base, or of quote if `base_in` is false, against a concentrated
+    /// pool, crossing at most `MAX_TICKS_CROSSED` initialized ticks as the price moves. Nothing
+    /// is written to storage.
+    pub(crate) fn compute_concentrated_swap(
+        hash: T::Hash,
+        base_in: bool,
+        amount_in: u128,
+        fee_per_million: u128,
+    ) -> Result<ConcentratedSwap, dispatch::DispatchError> {
+        let mut pool = Self::concentrated_pool(hash).ok_or(Error::<T>::PoolNotInitialized)?;
+        let initialized_ticks = Self::initialized_ticks(hash);
+        let mut crossed_ticks = Vec::new();
+
+        let mut amount_remaining = amount_in;
+        let mut amount_out = 0u128;
+        while amount_remaining > 0 {
+            let next_tick = if base_in {
+                if pool.sqrt_price <= MIN_SQRT_PRICE {
+                    break;
+                }
+                let index = match initialized_ticks.binary_search(&pool.tick) {
+                    Ok(index) => Some(index),
+                    Err(index) => index.checked_sub(1),
+                };
+                index
+                    .map(|index| initialized_ticks[index])
+                    .unwrap_or(MIN_TICK)
+            } else {
+                if pool.sqrt_price >= MAX_SQRT_PRICE {
+                    break;
+                }
+                let index = match initialized_ticks.binary_search(&pool.tick) {
+                    Ok(index) => index + 1,
+                    Err(index) => index,
+                };
+                initialized_ticks.get(index).copied().unwrap_or(MAX_TICK)
+            };
+            let sqrt_price_target = sqrt_price_at_tick(next_tick);
+
+            let (sqrt_price_next, step_in, step_out, fee_amount) = compute_swap_step(
+                pool.sqrt_price,
+                sqrt_price_target,
+                pool.liquidity,
+                amount_remaining,
+                fee_per_million,
+                base_in,
+            )
+            .ok_or(Error::<T>::ConcentratedMathOverflow)?;
+            amount_remaining = amount_remaining.saturating_sub(step_in.saturating_add(fee_amount));
+            amount_out = amount_out
+                .checked_add(step_out)
+                .ok_or(Error::<T>::ConcentratedMathOverflow)?;
+
+            if pool.liquidity > 0 {
+                let growth =
+                    ((U256::from(fee_amount) << 64) / U256::from(pool.liquidity)).low_u128();
+                if base_in {
+                    pool.fee_growth_global_base = pool.fee_growth_global_base.wrapping_add(growth);
+                } else {
+                    pool.fee_growth_global_quote =
+                        pool.fee_growth_global_quote.wrapping_add(growth);
+                }
+            }
+
+            if sqrt_price_next == sqrt_price_target {
+                if let Some(mut tick) = Self::tick(hash, next_tick) {
+                    ensure!(
+                        crossed_ticks.len() < MAX_TICKS_CROSSED as usize,
+                        Error::<T>::TooManyTicksCrossed
+                    );
+                    tick.fee_growth_outside_base = pool
+                        .fee_growth_global_base
+                        .wrapping_sub(tick.fee_growth_outside_base);
+                    tick.fee_growth_outside_quote = pool
+                        .fee_growth_global_quote
+                        .wrapping_sub(tick.fee_growth_outside_quote);
+
+                    let liquidity_net = if base_in {
+                        tick.liquidity_net
+                            .checked_neg()
+                            .ok_or(Error::<T>::ConcentratedMathOverflow)?
+                    } else {
+                        tick.liquidity_net
+                    };
+                    pool.liquidity = add_liquidity_delta(pool.liquidity, liquidity_net)
+                        .ok_or(Error::<T>::ConcentratedMathOverflow)?;
+
+                    crossed_ticks.push((next_tick, tick));
+                }
+                pool.tick = if base_in { next_tick - 1 } else { next_tick };
+            } else {
+                pool.tick = tick_at_sqrt_price(sqrt_price_next);
+            }
+            pool.sqrt_price = sqrt_price_next;
+        }
+
+        ensure!(amount_remaining == 0, Error::<T>::InsufficientLiquidity);
+
+        Ok(ConcentratedSwap {
+            amount_out,
+            pool,
+            crossed_ticks,
+        })
+    }
+
+    pub(crate) fn apply_concentrated_swap(hash: T::Hash, swap: ConcentratedSwap) {
+        ConcentratedPools::<T>::insert(hash, swap.pool);
+        for (index, tick) in swap.crossed_ticks {
+            Ticks::<T>::insert(hash, index, tick);
+        }
+    }
+
+    /// Add or remove `liquidity` at the lower or upper boundary `index` of a position.
+    fn update_tick(
+        hash: T::Hash,
+        pool: &ConcentratedPool,
+        index: i32,
+        liquidity: u128,
+        add: bool,
+        upper: bool,
+    ) -> dispatch::DispatchResult {
+        let liquidity_delta =
+            i128::try_from(liquidity).map_err(|_| Error::<T>::ConcentratedMathOverflow)?;
+
+        let initialized = Ticks::<T>::contains_key(hash, index);
+        let mut tick = Self::tick(hash, index).unwrap_or_else(|| {
+            // by convention, all the fees so far were earned below the tick
+            if index <= pool.tick {
+                Tick {
+                    fee_growth_outside_base: pool.fee_growth_global_base,
+                    fee_growth_outside_quote: pool.fee_growth_global_quote,
+                    ..Default::default()
+                }
+            } else {
+                Default::default()
+            }
+        });
+
+        tick.liquidity_gross = if add {
+            tick.liquidity_gross.checked_add(liquidity)
+        } else {
+            tick.liquidity_gross.checked_sub(liquidity)
+        }
+        .ok_or(Error::<T>::ConcentratedMathOverflow)?;
+
+        // liquidity enters the range at its lower tick and leaves it at its upper tick
+        let net_delta = if add != upper {
+            liquidity_delta
+        } else {
+            -liquidity_delta
+        };
+        tick.liquidity_net = tick
+            .liquidity_net
+            .checked_add(net_delta)
+            .ok_or(Error::<T>::ConcentratedMathOverflow)?;
+
+        if tick.liquidity_gross == 0 {
+            Ticks::<T>::remove(hash, index);
+            InitializedTicks::<T>::mutate(hash, |ticks| {
+                if let Ok(i) = ticks.binary_search(&index) {
+                    ticks.remove(i);
+                }
+            });
+        } else {
+            if !initialized {
+                InitializedTicks::<T>::try_mutate(hash, |ticks| {
+                    ensure!(
+                        ticks.len() < MAX_INITIALIZED_TICKS as usize,
+                        Error::<T>::TooManyInitializedTicks
+                    );
+                    if let Err(i) = ticks.binary_search(&index) {
+                        ticks.insert(i, index);
+                    }
+                    Ok::<_, Error<T>>(())
+                })?;
+            }
+            Ticks::<T>::insert(hash, index, tick);
+        }
+
+        Ok(())
+    }
+
+    /// Base and quote fee growth per unit of liquidity between two ticks.
+    fn fee_growth_inside(
+        hash: T::Hash,
+        pool: &ConcentratedPool,
+        tick_lower: i32,
+        tick_upper: i32,
+    ) -> (u128, u128) {
+        let lower = Self::tick(hash, tick_lower).unwrap_or_default();
+        let upper = Self::tick(hash, tick_upper).unwrap_or_default();
+
+        let inside = |global: u128, lower_outside: u128, upper_outside: u128| {
+            let below = if pool.tick >= tick_lower {
+                lower_outside
+            } else {
+                global.wrapping_sub(lower_outside)
+            };
+            let above = if pool.tick < tick_upper {
+                upper_outside
+            } else {
+                global.wrapping_sub(upper_outside)
+            };
+            global.wrapping_sub(below).wrapping_sub(above)
+        };
+
+        (
+            inside(
+                pool.fee_growth_global_base,
+                lower.fee_growth_outside_base,
+                upper.fee_growth_outside_base,
+            ),
+            inside(
+                pool.fee_growth_global_quote,
+                lower.fee_growth_outside_quote,
+                upper.fee_growth_outside_quote,
+            ),
+        )
+    }
+
+    /// Credit `position` with the fees earned by its range since they were last accounted.
+    fn update_position_fees(
+        position: &mut Position<T::AccountId, T::Hash, BalanceOf<T>>,
+        pool: &ConcentratedPool,
+    ) -> dispatch::DispatchResult {
+        let (fee_growth_inside_base, fee_growth_inside_quote) = Self::fee_growth_inside(
+            position.tp_hash,
+            pool,
+            position.tick_lower,
+            position.tick_upper,
+        );
+
+        let earned = |growth: u128, last: u128| {
+            ((U256::from(growth.wrapping_sub(last)) * U256::from(position.liquidity)) >> 64)
+                .low_u128()
+        };
+        let base_fees = Self::to_balance(earned(
+            fee_growth_inside_base,
+            position.fee_growth_inside_base_last,
+        ))?;
+        let quote_fees = Self::to_balance(earned(
+            fee_growth_inside_quote,
+            position.fee_growth_inside_quote_last,
+        ))?;
+
+        position.fee_growth_inside_base_last = fee_growth_inside_base;
+        position.fee_growth_inside_quote_last = fee_growth_inside_quote;
+        position.tokens_owed_base = position.tokens_owed_base.saturating_add(base_fees);
+        position.tokens_owed_quote = position.tokens_owed_quote.saturating_add(quote_fees);
+
+        Ok(())
+    }
+
+    pub(crate) fn to_balance(amount: u128) -> Result<BalanceOf<T>, dispatch::DispatchError> {
+        amount
+            .try_into()
+            .map_err(|_| Error::<T>::ConcentratedMathOverflow.into())
+    }
+}
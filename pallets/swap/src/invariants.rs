@@ -0,0 +1,326 @@
+//! Property tests running random sequences of liquidity, swap and transfer calls against the
+//! mock runtime and checking the invariants of a constant product pair after every step. Amounts
+//! and supplies span the whole balance range, where calls asking for more than the caller holds
+//! have to fail rather than overflow.
+
+use crate::{mock::*, PoolKind};
+use frame_support::traits::{OnFinalize, OnInitialize};
+use proptest::prelude::*;
+use sp_core::{H256, U256};
+use token::*;
+
+const ACCOUNTS: [u64; 3] = [10, 20, 30];
+const TOTAL_SUPPLY: u128 = 1_000_000_000_000;
+
+#[derive(Clone, Debug)]
+enum Action {
+    AddLiquidity {
+        who: usize,
+        base_amount: u128,
+        quote_amount: Option<u128>,
+    },
+    RemoveLiquidity {
+        who: usize,
+        amount: u128,
+    },
+    SwapBuy {
+        who: usize,
+        amount: u128,
+    },
+    SwapSell {
+        who: usize,
+        amount: u128,
+    },
+    /// Transfer of the base or quote token, `to` being the pair account when out of range.
+    Transfer {
+        from: usize,
+        to: usize,
+        quote: bool,
+        amount: u128,
+    },
+    TransferLiquidity {
+        from: usize,
+        to: usize,
+        amount: u128,
+    },
+}
+
+fn amount() -> impl Strategy<Value = u128> {
+    prop_oneof![
+        0..1_000u128,
+        0..1_000_000u128,
+        0..1_000_000_000u128,
+        0..2 * TOTAL_SUPPLY,
+        any::<u128>(),
+        u128::max_value() - 1_000_000..=u128::max_value(),
+    ]
+}
+
+fn who() -> impl Strategy<Value = usize> {
+    0..ACCOUNTS.len()
+}
+
+fn action() -> impl Strategy<Value = Action> {
+    prop_oneof![
+        (who(), amount(), proptest::option::of(amount())).prop_map(
+            |(who, base_amount, quote_amount)| Action::AddLiquidity {
+                who,
+                base_amount,
+                quote_amount,
+            }
+        ),
+        (who(), amount()).prop_map(|(who, amount)| Action::RemoveLiquidity { who, amount }),
+        (who(), amount()).prop_map(|(who, amount)| Action::SwapBuy { who, amount }),
+        (who(), amount()).prop_map(|(who, amount)| Action::SwapSell { who, amount }),
+        (who(), 0..ACCOUNTS.len() + 1, any::<bool>(), amount()).prop_map(
+            |(from, to, quote, amount)| Action::Transfer {
+                from,
+                to,
+                quote,
+                amount,
+            }
+        ),
+        (who(), who(), amount()).prop_map(|(from, to, amount)| Action::TransferLiquidity {
+            from,
+            to,
+            amount
+        }),
+    ]
+}
+
+struct Pair {
+    base: H256,
+    quote: H256,
+    hash: H256,
+    /// Supply of both the base and quote token.
+    supply: u128,
+}
+
+fn setup(fee_tier: u32, supply: u128) -> Pair {
+    System::set_block_number(1);
+    System::on_initialize(1);
+
+    let base =
+        TokenModule::do_issue(ACCOUNTS[0], b"6666".to_vec(), supply, TokenType::Normal).unwrap();
+    let quote =
+        TokenModule::do_issue(ACCOUNTS[0], b"8888".to_vec(), supply, TokenType::Normal).unwrap();
+    for who in ACCOUNTS[1..].iter() {
+        TokenModule::do_transfer(ACCOUNTS[0], *who, base, supply / 4, None).unwrap();
+        TokenModule::do_transfer(ACCOUNTS[0], *who, quote, supply / 4, None).unwrap();
+    }
+
+    SwapModule::do_create_trade_pair(
+        ACCOUNTS[0],
+        base,
+        quote,
+        PoolKind::ConstantProduct,
+        fee_tier,
+    )
+    .unwrap();
+    let hash = SwapModule::trade_pair_hash_by_base_quote((base, quote, fee_tier)).unwrap();
+
+    Pair {
+        base,
+        quote,
+        hash,
+        supply,
+    }
+}
+
+/// Apply `action`, which has to fail when it spends more than the caller holds.
+fn apply(pair: &Pair, action: &Action) -> Result<(), TestCaseError> {
+    let tp = SwapModule::trade_pair(pair.hash).unwrap();
+    let free = |who: usize, token: H256| TokenModule::free_balance_of((ACCOUNTS[who], token));
+
+    let (result, amount, available) = match *action {
+        Action::AddLiquidity {
+            who,
+            base_amount,
+            quote_amount,
+        } => {
+            let available = free(who, pair.base);
+            let result = SwapModule::add_liquidity(
+                Origin::signed(ACCOUNTS[who]),
+                pair.hash,
+                base_amount,
+                quote_amount,
+            );
+            (result, base_amount, available)
+        }
+        Action::RemoveLiquidity { who, amount } => {
+            let available = free(who, tp.liquidity_token_hash);
+            let result =
+                SwapModule::remove_liquidity(Origin::signed(ACCOUNTS[who]), pair.hash, amount);
+            (result, amount, available)
+        }
+        Action::SwapBuy { who, amount } => {
+            let available = free(who, pair.base);
+            let result = SwapModule::swap_buy(Origin::signed(ACCOUNTS[who]), pair.hash, amount)
+                .map(|_| ())
+                .map_err(|e| e.error);
+            (result, amount, available)
+        }
+        Action::SwapSell { who, amount } => {
+            let available = free(who, pair.quote);
+            let result = SwapModule::swap_sell(Origin::signed(ACCOUNTS[who]), pair.hash, amount)
+                .map(|_| ())
+                .map_err(|e| e.error);
+            (result, amount, available)
+        }
+        Action::Transfer {
+            from,
+            to,
+            quote,
+            amount,
+        } => {
+            let to = ACCOUNTS.get(to).copied().unwrap_or(tp.account);
+            let token = if quote { pair.quote } else { pair.base };
+            let available = free(from, token);
+            let result =
+                TokenModule::transfer(Origin::signed(ACCOUNTS[from]), token, to, amount, None);
+            (result, amount, available)
+        }
+        Action::TransferLiquidity { from, to, amount } => {
+            let available = free(from, tp.liquidity_token_hash);
+            let result = TokenModule::transfer(
+                Origin::signed(ACCOUNTS[from]),
+                tp.liquidity_token_hash,
+                ACCOUNTS[to],
+                amount,
+                None,
+            );
+            (result, amount, available)
+        }
+    };
+
+    // other failing calls are fine, only the state they leave behind matters
+    if amount > available {
+        prop_assert!(
+            result.is_err(),
+            "{:?} spent more than the {} held",
+            action,
+            available
+        );
+    }
+
+    Ok(())
+}
+
+/// (base reserve, quote reserve, liquidity tokens issued)
+fn pool_state(pair: &Pair) -> (u128, u128, u128) {
+    let tp = SwapModule::trade_pair(pair.hash).unwrap();
+    (
+        TokenModule::balance_of((tp.account, pair.base)),
+        TokenModule::balance_of((tp.account, pair.quote)),
+        tp.liquidity_token_issued_amount,
+    )
+}
+
+fn k((base, quote, _): (u128, u128, u128)) -> U256 {
+    U256::from(base) * U256::from(quote)
+}
+
+fn check_invariants(
+    pair: &Pair,
+    action: &Action,
+    before: (u128, u128, u128),
+) -> Result<(), TestCaseError> {
+    let after = pool_state(pair);
+    let tp = SwapModule::trade_pair(pair.hash).unwrap();
+
+    // k never decreases, except by the share of the liquidity removed, which is rounded in
+    // favour of the pool
+    match action {
+        Action::RemoveLiquidity { .. } => {
+            let (_, _, supply_before) = before;
+            let (_, _, supply_after) = after;
+            // in 512 bits, as k alone takes up to 256 with the whole balance range
+            prop_assert!(
+                k(after).full_mul(U256::from(supply_before) * U256::from(supply_before))
+                    >= k(before).full_mul(U256::from(supply_after) * U256::from(supply_after)),
+                "k per liquidity token decreased from {:?} to {:?}",
+                before,
+                after
+            );
+        }
+        _ => prop_assert!(
+            k(after) >= k(before),
+            "k decreased from {:?} to {:?}",
+            before,
+            after
+        ),
+    }
+
+    // every liquidity token issued is held by an account, and redeeming all of them takes at
+    // most the pool
+    let (reserve_base, reserve_quote, supply) = after;
+    let shares: Vec<u128> = ACCOUNTS
+        .iter()
+        .map(|who| TokenModule::balance_of((*who, tp.liquidity_token_hash)))
+        .collect();
+    prop_assert_eq!(shares.iter().sum::<u128>(), supply);
+    if supply > 0 {
+        let redeemable = |reserve: u128| -> U256 {
+            shares
+                .iter()
+                .map(|share| U256::from(reserve) * U256::from(*share) / U256::from(supply))
+                .fold(U256::zero(), |total, amount| total + amount)
+        };
+        prop_assert!(redeemable(reserve_base) <= U256::from(reserve_base));
+        prop_assert!(redeemable(reserve_quote) <= U256::from(reserve_quote));
+    }
+
+    // tokens only move around
+    for token in [pair.base, pair.quote].iter() {
+        let held: u128 = ACCOUNTS
+            .iter()
+            .chain(Some(&tp.account))
+            .map(|who| {
+                let balance = TokenModule::balance_of((*who, *token));
+                assert_eq!(
+                    balance,
+                    TokenModule::free_balance_of((*who, *token))
+                        + TokenModule::freezed_balance_of((*who, *token))
+                );
+                balance
+            })
+            .sum();
+        prop_assert_eq!(held, pair.supply);
+        prop_assert_eq!(
+            TokenModule::token(*token).unwrap().total_supply,
+            pair.supply
+        );
+    }
+
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(128))]
+
+    #[test]
+    fn constant_product_invariants(
+        fee_tier in prop::sample::select(vec![0u32, 5, 30, 100]),
+        supply in prop::sample::select(vec![TOTAL_SUPPLY, u128::max_value()]),
+        actions in prop::collection::vec(action(), 1..50),
+    ) {
+        new_test_ext().execute_with(|| -> Result<(), TestCaseError> {
+            let pair = setup(fee_tier, supply);
+
+            for (n, action) in actions.iter().enumerate() {
+                let before = pool_state(&pair);
+                apply(&pair, action)?;
+                check_invariants(&pair, action, before)?;
+
+                // a new block every few steps, which resets the per block price records
+                if n % 5 == 4 {
+                    System::on_finalize(System::block_number());
+                    System::set_block_number(System::block_number() + 1);
+                    System::on_initialize(System::block_number());
+                }
+            }
+
+            Ok(())
+        })?;
+    }
+}
@@ -1,24 +1,157 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use frame_support::{
-    decl_error, decl_event, decl_module, decl_storage, dispatch, ensure, traits::Randomness,
+    decl_error, decl_event, decl_module, decl_storage, dispatch, ensure,
+    traits::{EnsureOrigin, Get, Randomness},
+    transactional,
+    weights::{GetDispatchInfo, Weight},
     Parameter,
 };
-use frame_system::ensure_signed;
+use frame_system::{ensure_root, ensure_signed};
+use sp_core::U256;
 use sp_io::hashing::blake2_256;
-use sp_runtime::traits::{AtLeast32Bit, Bounded, Hash, Member, Zero};
-use sp_std::prelude::*;
+use sp_runtime::{
+    traits::{
+        AtLeast32Bit, Bounded, CheckedAdd, Dispatchable, Hash, Member, One, Saturating, Zero,
+    },
+    PerThing, Permill, RuntimeDebug, SaturatedConversion,
+};
+use sp_std::{convert::TryInto, prelude::*};
 
 use codec::{Decode, Encode};
-use token::*;
-#[cfg(test)]
-mod mock;
+use token::{MultiAsset, MultiCurrency, TokenType};
+pub mod concentrated;
+pub use concentrated::{ConcentratedPool, Position, Tick};
+pub mod stable;
+pub use stable::Amplification;
+pub mod migrations;
+pub mod weighted;
+pub use migrations::Releases;
+
+mod benchmarking;
+
+/// The mock runtime of the tests, also driven by the fuzz targets.
+#[cfg(any(test, feature = "fuzzing"))]
+pub mod mock;
 
 #[cfg(test)]
 mod tests;
 
-pub trait Trait: token::Trait + frame_system::Trait {
+#[cfg(test)]
+mod invariants;
+
+/// Fee tiers are in basis points.
+const FEE_TIER_DENOMINATOR: u32 = 10_000;
+
+/// Most bisection steps taken to find the amount in for an amount out, in each pair.
+const AMOUNT_IN_SEARCH_STEPS: u32 = 64;
+
+/// Largest integer whose square is at most `value`, by the Babylonian method.
+fn integer_sqrt(value: U256) -> U256 {
+    if value <= U256::from(3u8) {
+        return if value.is_zero() {
+            U256::zero()
+        } else {
+            U256::one()
+        };
+    }
+
+    let mut z = value;
+    let mut x = value / 2 + 1;
+    while x < z {
+        z = x;
+        x = (value / x + x) / 2;
+    }
+    z
+}
+
+/// Borrower logic run in the middle of a flash swap.
+pub trait FlashSwapHandler<AccountId, Hash, Balance> {
+    /// Called once `base_amount` and `quote_amount` have been sent to `borrower`. Before
+    /// returning, the pair account has to be paid back so that the constant product of its
+    /// reserves, net of the fee of the pair, is not lower than before.
+    fn on_flash_swap(
+        borrower: &AccountId,
+        tp_hash: Hash,
+        pair_account: &AccountId,
+        base_amount: Balance,
+        quote_amount: Balance,
+        data: &[u8],
+    ) -> dispatch::DispatchResult;
+
+    /// Weight of the borrower logic run for `data`, charged on top of the flash swap.
+    fn weight(_data: &[u8]) -> Weight {
+        0
+    }
+}
+
+impl<AccountId, Hash, Balance> FlashSwapHandler<AccountId, Hash, Balance> for () {
+    fn on_flash_swap(
+        _borrower: &AccountId,
+        _tp_hash: Hash,
+        _pair_account: &AccountId,
+        _base_amount: Balance,
+        _quote_amount: Balance,
+        _data: &[u8],
+    ) -> dispatch::DispatchResult {
+        Ok(())
+    }
+}
+
+/// Borrower logic dispatching the flash swap data, decoded as a call, as the borrower, like
+/// flash loans do.
+pub struct DispatchCall<T>(sp_std::marker::PhantomData<T>);
+
+impl<T: Trait> FlashSwapHandler<T::AccountId, T::Hash, BalanceOf<T>> for DispatchCall<T> {
+    fn on_flash_swap(
+        borrower: &T::AccountId,
+        _tp_hash: T::Hash,
+        _pair_account: &T::AccountId,
+        _base_amount: BalanceOf<T>,
+        _quote_amount: BalanceOf<T>,
+        data: &[u8],
+    ) -> dispatch::DispatchResult {
+        let call = <T as Trait>::Call::decode(&mut &data[..])
+            .map_err(|_| Error::<T>::InvalidFlashSwapCall)?;
+
+        call.dispatch(frame_system::RawOrigin::Signed(borrower.clone()).into())
+            .map(|_| ())
+            .map_err(|e| e.error)
+    }
+
+    fn weight(data: &[u8]) -> Weight {
+        <T as Trait>::Call::decode(&mut &data[..])
+            .map(|call| call.get_dispatch_info().weight)
+            .unwrap_or(0)
+    }
+}
+
+type BalanceOf<T> =
+    <<T as Trait>::Currency as MultiCurrency<<T as frame_system::Trait>::AccountId>>::Balance;
+
+pub trait Trait: frame_system::Trait {
     type Event: From<Event<Self>> + Into<<Self as frame_system::Trait>::Event>;
+    /// The multi-currency system holding the traded tokens and the liquidity tokens.
+    type Currency: MultiAsset<Self::AccountId, CurrencyId = Self::Hash>;
+    /// The borrower logic of flash swaps.
+    type FlashSwapHandler: FlashSwapHandler<Self::AccountId, Self::Hash, BalanceOf<Self>>;
+    /// The overarching call type, dispatched by flash loan borrowers.
+    type Call: Parameter
+        + Dispatchable<Origin = Self::Origin>
+        + GetDispatchInfo
+        + From<frame_system::Call<Self>>;
+    /// Fee on flash loans, credited to the liquidity providers of the pair.
+    type FlashLoanFee: Get<Permill>;
+    /// Amplification coefficient of newly created StableSwap pairs.
+    type InitialAmplification: Get<u128>;
+    /// Swap fees, in basis points, that trade pairs can be created with.
+    type FeeTiers: Get<Vec<u32>>;
+    /// Fee tier given to the trade pairs of the release without fee tiers, one of `FeeTiers`.
+    type DefaultFeeTier: Get<u32>;
+    /// Who may create trade pairs with `create_trade_pair`.
+    type ListingPolicy: Get<ListingPolicy>;
+    /// Origin creating trade pairs with `force_create_trade_pair`, whatever the listing policy.
+    type ListingOrigin: EnsureOrigin<Self::Origin>;
     type Price: Parameter
         + Default
         + Member
@@ -27,6 +160,161 @@ pub trait Trait: token::Trait + frame_system::Trait {
         + Copy
         + From<u128>
         + Into<u128>;
+    /// Weights of the benchmarked calls.
+    type WeightInfo: WeightInfo;
+}
+
+/// Weights of the benchmarked calls, generated by `benchmarking`.
+pub trait WeightInfo {
+    fn create_trade_pair() -> Weight;
+    fn delist_trade_pair() -> Weight;
+    fn add_liquidity() -> Weight;
+    fn remove_liquidity() -> Weight;
+    fn swap_buy() -> Weight;
+    fn swap_sell() -> Weight;
+    fn swap_buy_stable() -> Weight;
+    fn swap_sell_stable() -> Weight;
+    fn swap_buy_concentrated(t: u32) -> Weight;
+    fn swap_sell_concentrated(t: u32) -> Weight;
+    fn swap_by_tokens(t: u32) -> Weight;
+    fn best_trade_pair_for_amount_out(t: u32) -> Weight;
+    fn flash_swap() -> Weight;
+    fn flash_loan() -> Weight;
+    fn ramp_amplification() -> Weight;
+    fn set_protocol_fee() -> Weight;
+    fn pause_pair() -> Weight;
+    fn unpause_pair() -> Weight;
+    fn pause_all() -> Weight;
+    fn unpause_all() -> Weight;
+    fn set_circuit_breaker() -> Weight;
+    fn initialize_concentrated_pool() -> Weight;
+    fn mint_position() -> Weight;
+    fn decrease_position() -> Weight;
+    fn collect_position() -> Weight;
+    fn create_weighted_pool(n: u32) -> Weight;
+    fn join_pool(n: u32) -> Weight;
+    fn join_pool_single() -> Weight;
+    fn exit_pool(n: u32) -> Weight;
+    fn exit_pool_single() -> Weight;
+    fn swap_weighted() -> Weight;
+}
+
+impl WeightInfo for () {
+    fn create_trade_pair() -> Weight {
+        200_000
+    }
+    fn delist_trade_pair() -> Weight {
+        200_000
+    }
+    fn add_liquidity() -> Weight {
+        200_000
+    }
+    fn remove_liquidity() -> Weight {
+        200_000
+    }
+    fn swap_buy() -> Weight {
+        200_000
+    }
+    fn swap_sell() -> Weight {
+        200_000
+    }
+    fn swap_buy_stable() -> Weight {
+        200_000
+    }
+    fn swap_sell_stable() -> Weight {
+        200_000
+    }
+    fn swap_buy_concentrated(_t: u32) -> Weight {
+        200_000
+    }
+    fn swap_sell_concentrated(_t: u32) -> Weight {
+        200_000
+    }
+    fn swap_by_tokens(_t: u32) -> Weight {
+        200_000
+    }
+    fn best_trade_pair_for_amount_out(_t: u32) -> Weight {
+        200_000
+    }
+    fn flash_swap() -> Weight {
+        200_000
+    }
+    fn flash_loan() -> Weight {
+        200_000
+    }
+    fn ramp_amplification() -> Weight {
+        200_000
+    }
+    fn set_protocol_fee() -> Weight {
+        200_000
+    }
+    fn pause_pair() -> Weight {
+        200_000
+    }
+    fn unpause_pair() -> Weight {
+        200_000
+    }
+    fn pause_all() -> Weight {
+        200_000
+    }
+    fn unpause_all() -> Weight {
+        200_000
+    }
+    fn set_circuit_breaker() -> Weight {
+        200_000
+    }
+    fn initialize_concentrated_pool() -> Weight {
+        200_000
+    }
+    fn mint_position() -> Weight {
+        200_000
+    }
+    fn decrease_position() -> Weight {
+        200_000
+    }
+    fn collect_position() -> Weight {
+        200_000
+    }
+    fn create_weighted_pool(_n: u32) -> Weight {
+        200_000
+    }
+    fn join_pool(_n: u32) -> Weight {
+        200_000
+    }
+    fn join_pool_single() -> Weight {
+        200_000
+    }
+    fn exit_pool(_n: u32) -> Weight {
+        200_000
+    }
+    fn exit_pool_single() -> Weight {
+        200_000
+    }
+    fn swap_weighted() -> Weight {
+        200_000
+    }
+}
+
+/// Pricing curve of a trade pair.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum PoolKind {
+    /// Uniswap constant product `x * y = k`.
+    ConstantProduct,
+    /// Curve StableSwap invariant, for pegged assets.
+    StableSwap,
+    /// Uniswap V3 concentrated liquidity, provided by positions over price ranges.
+    Concentrated,
+}
+
+/// Who may create trade pairs.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum ListingPolicy {
+    /// Any account, for any tokens.
+    Permissionless,
+    /// The owner of the base or of the quote token.
+    TokenOwner,
+    /// Only the listing origin, through `force_create_trade_pair`.
+    Governance,
 }
 
 #[derive(Encode, Decode, Clone, PartialEq, Eq)]
@@ -39,7 +327,24 @@ where
     base: T::Hash,
     quote: T::Hash,
     liquidity_token_hash: T::Hash,
-    liquidity_token_issued_amount: T::Balance,
+    liquidity_token_issued_amount: BalanceOf<T>,
+    account: T::AccountId,
+    kind: PoolKind,
+    fee_tier: u32,
+}
+
+/// Balancer pool of two to eight tokens with arbitrary weights.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct WeightedPool<T>
+where
+    T: Trait,
+{
+    pool_hash: T::Hash,
+    tokens: Vec<T::Hash>,
+    weights: Vec<u32>,
+    pool_token_hash: T::Hash,
+    pool_token_issued_amount: BalanceOf<T>,
     account: T::AccountId,
 }
 
@@ -47,12 +352,54 @@ decl_storage! {
     trait Store for Module<T: Trait> as TemplateModule {
         ///	TradePairHash => TradePair
         TradePairs get(fn trade_pair): map hasher(blake2_128_concat) T::Hash => Option<TradePair<T>>;
-        /// (BaseTokenHash, quoteTokenHash) => TradePairHash
-        TradePairsHashByBaseQuote get(fn trade_pair_hash_by_base_quote): map hasher(blake2_128_concat) (T::Hash, T::Hash) => Option<T::Hash>;
+        /// (BaseTokenHash, quoteTokenHash, fee tier) => TradePairHash, in both orientations
+        TradePairsHashByBaseQuote get(fn trade_pair_hash_by_base_quote): map hasher(blake2_128_concat) (T::Hash, T::Hash, u32) => Option<T::Hash>;
+        /// (BaseTokenHash, quoteTokenHash) => TradePairHash of every fee tier, in both orientations
+        TradePairsHashesByBaseQuote get(fn trade_pair_hashes_by_base_quote): map hasher(blake2_128_concat) (T::Hash, T::Hash) => Vec<T::Hash>;
         /// Index => TradePairHash
         TradePairsHashByIndex get(fn trade_pair_hash_by_index): map hasher(blake2_128_concat) u64 => Option<T::Hash>;
         /// Index
         TradePairsIndex get(fn trade_pair_index): u64;
+        /// TradePairHash => whether the pair was delisted, leaving only liquidity removal
+        DelistedTradePairs get(fn is_delisted): map hasher(blake2_128_concat) T::Hash => bool;
+        /// TradePairHash => whether the reserves of the pair are lent out by a flash loan or swap
+        LockedTradePairs get(fn is_locked): map hasher(blake2_128_concat) T::Hash => bool;
+        /// TradePairHash => amplification coefficient of a StableSwap pair
+        Amplifications get(fn amplification): map hasher(blake2_128_concat) T::Hash => Option<Amplification<T::BlockNumber>>;
+        /// TradePairHash => price and liquidity of a concentrated liquidity pair
+        ConcentratedPools get(fn concentrated_pool): map hasher(blake2_128_concat) T::Hash => Option<ConcentratedPool>;
+        /// (TradePairHash, tick index) => initialized tick
+        Ticks get(fn tick): double_map hasher(blake2_128_concat) T::Hash, hasher(blake2_128_concat) i32 => Option<Tick>;
+        /// TradePairHash => sorted indexes of the initialized ticks
+        InitializedTicks get(fn initialized_ticks): map hasher(blake2_128_concat) T::Hash => Vec<i32>;
+        /// PositionId => Position
+        Positions get(fn position): map hasher(blake2_128_concat) u64 => Option<Position<T::AccountId, T::Hash, BalanceOf<T>>>;
+        /// Id of the next position
+        NextPositionId get(fn next_position_id): u64;
+        /// WeightedPoolHash => WeightedPool
+        WeightedPools get(fn weighted_pool): map hasher(blake2_128_concat) T::Hash => Option<WeightedPool<T>>;
+        /// Index => WeightedPoolHash
+        WeightedPoolsHashByIndex get(fn weighted_pool_hash_by_index): map hasher(blake2_128_concat) u64 => Option<T::Hash>;
+        /// Index
+        WeightedPoolsIndex get(fn weighted_pool_index): u64;
+        /// Account receiving the protocol share of swap fees, none when the protocol fee is off
+        ProtocolFeeRecipient get(fn protocol_fee_recipient): Option<T::AccountId>;
+        /// Share of swap fees paid to the protocol
+        ProtocolFeeShare get(fn protocol_fee_share): Permill;
+        /// TradePairHash => product of the reserves after the last liquidity event, while the protocol fee is on
+        KLast get(fn k_last): map hasher(blake2_128_concat) T::Hash => U256;
+        /// TradePairHash => liquidity tokens minted to the protocol fee recipient so far
+        ProtocolFeesAccrued get(fn protocol_fees_accrued): map hasher(blake2_128_concat) T::Hash => BalanceOf<T>;
+        /// Whether trading is paused on every pair and pool
+        AllPaused get(fn is_all_paused): bool;
+        /// TradePairHash or WeightedPoolHash => whether trading is paused on it
+        PausedPairs get(fn is_pair_paused): map hasher(blake2_128_concat) T::Hash => bool;
+        /// TradePairHash => largest price move allowed within a block
+        CircuitBreakers get(fn circuit_breaker): map hasher(blake2_128_concat) T::Hash => Option<Permill>;
+        /// TradePairHash => (block, quote amount, base amount) giving the price before the first swap of the block
+        BlockStartPrices get(fn block_start_price): map hasher(blake2_128_concat) T::Hash => Option<(T::BlockNumber, U256, U256)>;
+        /// Release of the storage layout, for running the migrations it is missing
+        StorageVersion get(fn storage_version) build(|_: &GenesisConfig| Releases::LATEST): Releases;
 
         Nonce: u64;
     }
@@ -63,13 +410,54 @@ decl_event!(
     where
         <T as frame_system::Trait>::AccountId,
         <T as frame_system::Trait>::Hash,
+        <T as frame_system::Trait>::BlockNumber,
         TradePair = TradePair<T>,
+        Balance = BalanceOf<T>,
     {
         TradePairCreated(AccountId, Hash, TradePair),
-        LiquidityAdded(AccountId, Hash),
-        LiquidityRemoved(AccountId, Hash),
-        SwapBuy(AccountId, Hash),
-        SwapSell(AccountId, Hash),
+        TradePairDelisted(Hash),
+        /// Sender, pair, base amount in, quote amount in, liquidity tokens minted, base reserve,
+        /// quote reserve
+        LiquidityAdded(AccountId, Hash, Balance, Balance, Balance, Balance, Balance),
+        /// Sender, pair, base amount out, quote amount out, liquidity tokens burned, base
+        /// reserve, quote reserve
+        LiquidityRemoved(AccountId, Hash, Balance, Balance, Balance, Balance, Balance),
+        /// Sender, pair, base amount in, quote amount out, base reserve, quote reserve
+        SwapBuy(AccountId, Hash, Balance, Balance, Balance, Balance),
+        /// Sender, pair, quote amount in, base amount out, base reserve, quote reserve
+        SwapSell(AccountId, Hash, Balance, Balance, Balance, Balance),
+        /// Pair, base reserve, quote reserve, whenever the reserves of a pair change
+        Sync(Hash, Balance, Balance),
+        FlashSwap(AccountId, Hash),
+        /// Borrower, pair, token, amount, fee
+        FlashLoan(AccountId, Hash, Hash, Balance, Balance),
+        /// Pair, initial amplification, future amplification, block reaching it
+        AmplificationRamping(Hash, u128, u128, BlockNumber),
+        /// Pair, square root price, tick
+        ConcentratedPoolInitialized(Hash, u128, i32),
+        /// Owner, pair, position id, base amount, quote amount
+        PositionMinted(AccountId, Hash, u64, Balance, Balance),
+        /// Owner, position id, base amount, quote amount
+        PositionDecreased(AccountId, u64, Balance, Balance),
+        /// Owner, position id, base amount, quote amount
+        PositionCollected(AccountId, u64, Balance, Balance),
+        WeightedPoolCreated(AccountId, Hash),
+        /// Sender, pool, pool tokens minted
+        PoolJoined(AccountId, Hash, Balance),
+        /// Sender, pool, pool tokens burned
+        PoolExited(AccountId, Hash, Balance),
+        /// Sender, pool, token in, token out, amount in, amount out
+        WeightedPoolSwap(AccountId, Hash, Hash, Hash, Balance, Balance),
+        /// Recipient, share of swap fees
+        ProtocolFeeSet(Option<AccountId>, Permill),
+        /// Pair, recipient, liquidity tokens minted
+        ProtocolFeeMinted(Hash, AccountId, Balance),
+        PairPaused(Hash),
+        PairUnpaused(Hash),
+        AllPaused,
+        AllUnpaused,
+        /// Pair, largest price move allowed within a block
+        CircuitBreakerSet(Hash, Option<Permill>),
     }
 );
 
@@ -87,8 +475,6 @@ decl_error! {
         TradePairExisted,
         /// No matching trade pair found
         NoMatchingTradePair,
-        /// Liquidity base & quote proportion is not same as pool's
-        LiquidityProportionInvalid,
         /// Quote amount is none in init step of adding liquidity
         QuoteAmountIsNone,
         /// Base amount is zero
@@ -102,11 +488,97 @@ decl_error! {
         ///
         LiquidityTokenAmountIsZero,
         ///
-        LiquidityTokenIssuedAmountIsZero,
-        ///
         PoolBaseAmountIsZero,
         ///
         PoolQuoteAmountIsZero,
+        /// Transfers of the base or quote token are paused
+        TokenPaused,
+        /// Flash swap asks for nothing
+        FlashSwapAmountIsZero,
+        /// Flash swap asks for at least the whole pool
+        InsufficientLiquidity,
+        /// Flash swap borrower did not pay back the pool with fee
+        FlashSwapNotRepaid,
+        /// Token is neither the base nor the quote of the trade pair
+        TokenNotInTradePair,
+        /// Flash loan amount is zero
+        FlashLoanAmountIsZero,
+        /// Flash loan borrower did not pay back the pool with fee
+        FlashLoanNotRepaid,
+        /// Trade pair is not a StableSwap pair
+        NotStableSwapPair,
+        /// Amplification coefficient is zero or too large
+        InvalidAmplification,
+        /// Amplification ramp ends in the past
+        InvalidRampBlock,
+        /// StableSwap invariant could not be computed
+        StableSwapMathFailed,
+        /// Operation is not supported by the pool kind of the trade pair
+        UnsupportedPoolKind,
+        /// Concentrated liquidity pair already has a price
+        PoolAlreadyInitialized,
+        /// Concentrated liquidity pair has no price yet
+        PoolNotInitialized,
+        /// Square root price is outside of the tick range
+        InvalidSqrtPrice,
+        /// Lower tick is not below the upper tick, or a tick is out of range
+        InvalidTickRange,
+        /// Position liquidity is zero
+        LiquidityIsZero,
+        /// No matching position found
+        NoMatchingPosition,
+        /// Sender is not the owner of the position
+        NotPositionOwner,
+        /// Position has less liquidity than asked for
+        PositionLiquidityTooLow,
+        /// Concentrated liquidity amounts overflow
+        ConcentratedMathOverflow,
+        /// Weighted pool needs between two and eight distinct tokens
+        InvalidWeightedPoolTokens,
+        /// Weights are zero or too large
+        InvalidWeights,
+        /// Amounts or weights do not match the tokens of the pool
+        AmountsLengthMismatch,
+        /// No matching weighted pool found
+        NoMatchingWeightedPool,
+        /// Token is not one of the tokens of the weighted pool
+        TokenNotInWeightedPool,
+        /// Pool or token amount is zero
+        PoolAmountIsZero,
+        /// Amount in is above the maximum or amount out below the minimum
+        SlippageLimitExceeded,
+        /// Trade takes too large a part of the pool balance
+        TradeTooLarge,
+        /// Weighted pool math overflowed
+        WeightedMathFailed,
+        /// Fee tier is not one of the allowed fee tiers
+        InvalidFeeTier,
+        /// Trade pairs can only be listed by governance
+        ListingRequiresGovernance,
+        /// Trade pair was delisted
+        TradePairDelisted,
+        /// Protocol fee is set without a recipient
+        ProtocolFeeRecipientIsNone,
+        /// Overflow computing the protocol fee
+        ProtocolFeeOverflow,
+        /// Trading is paused
+        TradingPaused,
+        /// Trade moves the price more than the circuit breaker of the pair allows within a block
+        PriceMoveTooLarge,
+        /// Reserves of the trade pair are lent out
+        TradePairLocked,
+        /// Liquidity tokens of the trade pair were minted or burned while its reserves were lent out
+        LiquidityChangedWhileLocked,
+        /// Flash swap data is not a call
+        InvalidFlashSwapCall,
+        /// Reserves of the trade pair overflow
+        PoolAmountOverflow,
+        /// Liquidity amounts overflow
+        LiquidityMathOverflow,
+        /// Concentrated liquidity pair has too many initialized ticks
+        TooManyInitializedTicks,
+        /// Swap crosses too many initialized ticks
+        TooManyTicksCrossed,
     }
 }
 
@@ -116,47 +588,280 @@ decl_module! {
 
         fn deposit_event() = default;
 
-        #[weight = 200_000]
-        pub fn create_trade_pair(origin, base: T::Hash, quote: T::Hash) -> dispatch::DispatchResult {
+        fn on_runtime_upgrade() -> Weight {
+            migrations::migrate::<T>()
+        }
+
+        fn integrity_test() {
+            assert!(
+                T::FeeTiers::get().contains(&T::DefaultFeeTier::get()),
+                "the default fee tier must be one of the fee tiers",
+            );
+        }
+
+        #[weight = T::WeightInfo::create_trade_pair()]
+        pub fn create_trade_pair(origin, base: T::Hash, quote: T::Hash, kind: PoolKind, fee_tier: u32) -> dispatch::DispatchResult {
             let sender = ensure_signed(origin)?;
 
-            Self::do_create_trade_pair(sender, base, quote)
+            Self::do_create_trade_pair(sender, base, quote, kind, fee_tier)
+        }
+
+        /// Create a trade pair whatever the listing policy, on behalf of `creator`.
+        #[weight = T::WeightInfo::create_trade_pair()]
+        pub fn force_create_trade_pair(origin, creator: T::AccountId, base: T::Hash, quote: T::Hash, kind: PoolKind, fee_tier: u32) -> dispatch::DispatchResult {
+            T::ListingOrigin::ensure_origin(origin)?;
+
+            Self::insert_trade_pair(creator, base, quote, kind, fee_tier)
+        }
+
+        /// Stop trading and adding liquidity to a pair. Liquidity providers can still remove
+        /// their liquidity.
+        #[weight = T::WeightInfo::delist_trade_pair()]
+        pub fn delist_trade_pair(origin, hash: T::Hash) -> dispatch::DispatchResult {
+            ensure_root(origin)?;
+
+            Self::do_delist_trade_pair(hash)
         }
 
-        #[weight = 200_000]
-        pub fn add_liquidity(origin, hash: T::Hash, base_amount: T::Balance, o_quote_amount: Option<T::Balance>) -> dispatch::DispatchResult {
+        #[weight = T::WeightInfo::add_liquidity()]
+        #[transactional]
+        pub fn add_liquidity(origin, hash: T::Hash, base_amount: BalanceOf<T>, o_quote_amount: Option<BalanceOf<T>>) -> dispatch::DispatchResult {
             let sender = ensure_signed(origin)?;
 
             Self::do_add_liquidity(sender, hash, base_amount, o_quote_amount)
         }
 
-        #[weight = 200_000]
-        pub fn add_liquidity_by_base_quote(origin, base: T::Hash, quote: T::Hash, base_amount: T::Balance, o_quote_amount: Option<T::Balance>) -> dispatch::DispatchResult {
+        #[weight = T::WeightInfo::add_liquidity()]
+        #[transactional]
+        pub fn add_liquidity_by_base_quote(origin, base: T::Hash, quote: T::Hash, fee_tier: u32, base_amount: BalanceOf<T>, o_quote_amount: Option<BalanceOf<T>>)
+            -> dispatch::DispatchResult {
             let sender = ensure_signed(origin)?;
-            let hash = Self::trade_pair_hash_by_base_quote((base, quote)).ok_or(Error::<T>::NoMatchingTradePair)?;
 
-            Self::do_add_liquidity(sender, hash, base_amount, o_quote_amount)
+            Self::do_add_liquidity_by_base_quote(sender, base, quote, fee_tier, base_amount, o_quote_amount)
         }
 
-        #[weight = 200_000]
-        pub fn remove_liquidity(origin, hash: T::Hash, liquidity_token_amount: T::Balance) -> dispatch::DispatchResult {
+        #[weight = T::WeightInfo::remove_liquidity()]
+        #[transactional]
+        pub fn remove_liquidity(origin, hash: T::Hash, liquidity_token_amount: BalanceOf<T>) -> dispatch::DispatchResult {
             let sender = ensure_signed(origin)?;
 
             Self::do_remove_liquidity(sender, hash, liquidity_token_amount)
         }
 
-        #[weight = 200_000]
-        pub fn swap_buy(origin, hash: T::Hash, base_amount: T::Balance) -> dispatch::DispatchResult {
+        /// Charged for the costliest pool kind, refunded down to the pool kind of the pair.
+        #[weight = Module::<T>::max_swap_weight(true)]
+        #[transactional]
+        pub fn swap_buy(origin, hash: T::Hash, base_amount: BalanceOf<T>) -> dispatch::DispatchResultWithPostInfo {
+            let sender = ensure_signed(origin)?;
+            let tp = Self::trade_pair(hash).ok_or(Error::<T>::NoMatchingTradePair)?;
+
+            Self::do_swap_buy(sender, hash, base_amount)?;
+            Ok(Some(Self::swap_weight(tp.kind, true)).into())
+        }
+
+        /// Charged for the costliest pool kind, refunded down to the pool kind of the pair.
+        #[weight = Module::<T>::max_swap_weight(false)]
+        #[transactional]
+        pub fn swap_sell(origin, hash: T::Hash, quote_amount: BalanceOf<T>) -> dispatch::DispatchResultWithPostInfo {
             let sender = ensure_signed(origin)?;
+            let tp = Self::trade_pair(hash).ok_or(Error::<T>::NoMatchingTradePair)?;
 
-            Self::do_swap_buy(sender, hash, base_amount)
+            Self::do_swap_sell(sender, hash, quote_amount)?;
+            Ok(Some(Self::swap_weight(tp.kind, false)).into())
         }
 
-        #[weight = 200_000]
-        pub fn swap_sell(origin, hash: T::Hash, quote_amount: T::Balance) -> dispatch::DispatchResult {
+        /// Swap `amount_in` of `token_in`, either the base or the quote of the pair, for the
+        /// other token.
+        #[weight = Module::<T>::max_swap_weight(true).max(Module::<T>::max_swap_weight(false))]
+        #[transactional]
+        pub fn swap(origin, hash: T::Hash, token_in: T::Hash, amount_in: BalanceOf<T>) -> dispatch::DispatchResultWithPostInfo {
             let sender = ensure_signed(origin)?;
+            let tp = Self::trade_pair(hash).ok_or(Error::<T>::NoMatchingTradePair)?;
+
+            Self::do_swap(sender, hash, token_in, amount_in)?;
+            Ok(Some(Self::swap_weight(tp.kind, token_in == tp.base)).into())
+        }
+
+        /// Swap `amount_in` of `token_in` for `token_out` in the fee tier of the pair giving the
+        /// most.
+        // at most as many pairs as fee tiers are quoted, each at the cost of the costliest pool kind
+        #[weight = T::WeightInfo::swap_by_tokens(T::FeeTiers::get().len() as u32)]
+        #[transactional]
+        pub fn swap_by_tokens(origin, token_in: T::Hash, token_out: T::Hash, amount_in: BalanceOf<T>) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+            let (hash, _) = Self::best_trade_pair(token_in, token_out, amount_in).ok_or(Error::<T>::NoMatchingTradePair)?;
+
+            Self::do_swap(sender, hash, token_in, amount_in)
+        }
+
+        /// Send the output amounts first, run the flash swap handler and then require the
+        /// pool to be paid back, reverting everything otherwise.
+        #[weight = T::FlashSwapHandler::weight(&data).saturating_add(T::WeightInfo::flash_swap())]
+        #[transactional]
+        pub fn flash_swap(origin, hash: T::Hash, base_amount_out: BalanceOf<T>, quote_amount_out: BalanceOf<T>, data: Vec<u8>)
+            -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+
+            Self::do_flash_swap(sender, hash, base_amount_out, quote_amount_out, data)
+        }
+
+        /// Lend `amount` of `token` from the pair, dispatch `call` as the sender and then
+        /// require the amount plus fee to be back in the pair, reverting everything otherwise.
+        #[weight = call.get_dispatch_info().weight.saturating_add(T::WeightInfo::flash_loan())]
+        #[transactional]
+        pub fn flash_loan(origin, hash: T::Hash, token: T::Hash, amount: BalanceOf<T>, call: Box<<T as Trait>::Call>)
+            -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+
+            Self::do_flash_loan(sender, hash, token, amount, *call)
+        }
+
+        /// Move the amplification coefficient of a StableSwap pair linearly from its current
+        /// value to `future_a`, reached at `future_block`.
+        #[weight = T::WeightInfo::ramp_amplification()]
+        pub fn ramp_amplification(origin, hash: T::Hash, future_a: u128, future_block: T::BlockNumber) -> dispatch::DispatchResult {
+            ensure_root(origin)?;
+
+            Self::do_ramp_amplification(hash, future_a, future_block)
+        }
+
+        /// Pay `share` of the swap fees of constant product pairs to `recipient`, or turn the
+        /// protocol fee off with no recipient.
+        #[weight = T::WeightInfo::set_protocol_fee()]
+        pub fn set_protocol_fee(origin, recipient: Option<T::AccountId>, share: Permill) -> dispatch::DispatchResult {
+            ensure_root(origin)?;
+
+            Self::do_set_protocol_fee(recipient, share)
+        }
+
+        /// Stop trading and adding liquidity to a trade pair or weighted pool.
+        #[weight = T::WeightInfo::pause_pair()]
+        pub fn pause_pair(origin, hash: T::Hash) -> dispatch::DispatchResult {
+            ensure_root(origin)?;
+
+            Self::do_set_pair_paused(hash, true)
+        }
+
+        #[weight = T::WeightInfo::unpause_pair()]
+        pub fn unpause_pair(origin, hash: T::Hash) -> dispatch::DispatchResult {
+            ensure_root(origin)?;
 
-            Self::do_swap_sell(sender, hash, quote_amount)
+            Self::do_set_pair_paused(hash, false)
+        }
+
+        /// Stop trading and adding liquidity everywhere.
+        #[weight = T::WeightInfo::pause_all()]
+        pub fn pause_all(origin) -> dispatch::DispatchResult {
+            ensure_root(origin)?;
+
+            AllPaused::put(true);
+            Self::deposit_event(RawEvent::AllPaused);
+
+            Ok(())
+        }
+
+        #[weight = T::WeightInfo::unpause_all()]
+        pub fn unpause_all(origin) -> dispatch::DispatchResult {
+            ensure_root(origin)?;
+
+            AllPaused::put(false);
+            Self::deposit_event(RawEvent::AllUnpaused);
+
+            Ok(())
+        }
+
+        /// Reject swaps moving the price of a trade pair by more than `max_price_move` from the
+        /// price at the start of the block, or remove the circuit breaker with none.
+        #[weight = T::WeightInfo::set_circuit_breaker()]
+        pub fn set_circuit_breaker(origin, hash: T::Hash, max_price_move: Option<Permill>) -> dispatch::DispatchResult {
+            ensure_root(origin)?;
+
+            Self::do_set_circuit_breaker(hash, max_price_move)
+        }
+
+        /// Set the starting price of a concentrated liquidity pair, as the square root of the
+        /// quote per base price in Q64.64.
+        #[weight = T::WeightInfo::initialize_concentrated_pool()]
+        pub fn initialize_concentrated_pool(origin, hash: T::Hash, sqrt_price: u128) -> dispatch::DispatchResult {
+            ensure_signed(origin)?;
+
+            Self::do_initialize_concentrated_pool(hash, sqrt_price)
+        }
+
+        /// Provide `liquidity` to a concentrated liquidity pair between `tick_lower` and
+        /// `tick_upper`, creating a new position.
+        #[weight = T::WeightInfo::mint_position()]
+        #[transactional]
+        pub fn mint_position(origin, hash: T::Hash, tick_lower: i32, tick_upper: i32, liquidity: u128) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+
+            Self::do_mint_position(sender, hash, tick_lower, tick_upper, liquidity)
+        }
+
+        #[weight = T::WeightInfo::decrease_position()]
+        #[transactional]
+        pub fn decrease_position(origin, position_id: u64, liquidity: u128) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+
+            Self::do_decrease_position(sender, position_id, liquidity)
+        }
+
+        #[weight = T::WeightInfo::collect_position()]
+        #[transactional]
+        pub fn collect_position(origin, position_id: u64) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+
+            Self::do_collect_position(sender, position_id)
+        }
+
+        /// Create a pool of `tokens` with the given relative `weights`, seeded with `amounts`.
+        #[weight = T::WeightInfo::create_weighted_pool(tokens.len() as u32)]
+        #[transactional]
+        pub fn create_weighted_pool(origin, tokens: Vec<T::Hash>, weights: Vec<u32>, amounts: Vec<BalanceOf<T>>) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+
+            Self::do_create_weighted_pool(sender, tokens, weights, amounts)
+        }
+
+        #[weight = T::WeightInfo::join_pool(max_amounts_in.len() as u32)]
+        #[transactional]
+        pub fn join_pool(origin, hash: T::Hash, pool_amount_out: BalanceOf<T>, max_amounts_in: Vec<BalanceOf<T>>) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+
+            Self::do_join_pool(sender, hash, pool_amount_out, max_amounts_in)
+        }
+
+        #[weight = T::WeightInfo::join_pool_single()]
+        #[transactional]
+        pub fn join_pool_single(origin, hash: T::Hash, token_in: T::Hash, amount_in: BalanceOf<T>, min_pool_amount_out: BalanceOf<T>) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+
+            Self::do_join_pool_single(sender, hash, token_in, amount_in, min_pool_amount_out)
+        }
+
+        #[weight = T::WeightInfo::exit_pool(min_amounts_out.len() as u32)]
+        #[transactional]
+        pub fn exit_pool(origin, hash: T::Hash, pool_amount_in: BalanceOf<T>, min_amounts_out: Vec<BalanceOf<T>>) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+
+            Self::do_exit_pool(sender, hash, pool_amount_in, min_amounts_out)
+        }
+
+        #[weight = T::WeightInfo::exit_pool_single()]
+        #[transactional]
+        pub fn exit_pool_single(origin, hash: T::Hash, token_out: T::Hash, pool_amount_in: BalanceOf<T>, min_amount_out: BalanceOf<T>) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+
+            Self::do_exit_pool_single(sender, hash, token_out, pool_amount_in, min_amount_out)
+        }
+
+        #[weight = T::WeightInfo::swap_weighted()]
+        #[transactional]
+        pub fn swap_weighted(origin, hash: T::Hash, token_in: T::Hash, amount_in: BalanceOf<T>, token_out: T::Hash, min_amount_out: BalanceOf<T>)
+            -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+
+            Self::do_swap_weighted(sender, hash, token_in, amount_in, token_out, min_amount_out)
         }
     }
 }
@@ -166,11 +871,47 @@ impl<T: Trait> Module<T> {
         sender: T::AccountId,
         base: T::Hash,
         quote: T::Hash,
+        kind: PoolKind,
+        fee_tier: u32,
+    ) -> dispatch::DispatchResult {
+        match T::ListingPolicy::get() {
+            ListingPolicy::Permissionless => {}
+            ListingPolicy::TokenOwner => {
+                let base_owner = T::Currency::owner(base).ok_or(Error::<T>::TokenOwnerNotFound)?;
+                let quote_owner =
+                    T::Currency::owner(quote).ok_or(Error::<T>::TokenOwnerNotFound)?;
+                ensure!(
+                    sender == base_owner || sender == quote_owner,
+                    Error::<T>::SenderNotEqualToBaseOrQuoteOwner
+                );
+            }
+            ListingPolicy::Governance => return Err(Error::<T>::ListingRequiresGovernance.into()),
+        }
+
+        Self::insert_trade_pair(sender, base, quote, kind, fee_tier)
+    }
+
+    fn insert_trade_pair(
+        sender: T::AccountId,
+        base: T::Hash,
+        quote: T::Hash,
+        kind: PoolKind,
+        fee_tier: u32,
     ) -> dispatch::DispatchResult {
         ensure!(base != quote, Error::<T>::BaseEqualQuote);
+        ensure!(
+            fee_tier < FEE_TIER_DENOMINATOR && T::FeeTiers::get().contains(&fee_tier),
+            Error::<T>::InvalidFeeTier
+        );
+        // one pair per fee tier whatever the orientation, so liquidity is not fragmented
+        ensure!(
+            !TradePairsHashByBaseQuote::<T>::contains_key((base, quote, fee_tier))
+                && !TradePairsHashByBaseQuote::<T>::contains_key((quote, base, fee_tier)),
+            Error::<T>::TradePairExisted
+        );
 
-        let base_token = <token::Module<T>>::token(base).ok_or(Error::<T>::TokenNotFound)?;
-        let quote_token = <token::Module<T>>::token(quote).ok_or(Error::<T>::TokenNotFound)?;
+        let base_symbol = T::Currency::symbol(base).ok_or(Error::<T>::TokenNotFound)?;
+        let quote_symbol = T::Currency::symbol(quote).ok_or(Error::<T>::TokenNotFound)?;
 
         let nonce = Nonce::get();
 
@@ -189,14 +930,14 @@ impl<T: Trait> Module<T> {
 
         let mut lt_name = Vec::new();
         lt_name.extend(b"LT_".to_vec());
-        lt_name.extend(base_token.symbol.clone());
+        lt_name.extend(base_symbol);
         lt_name.extend(b"_".to_vec());
-        lt_name.extend(quote_token.symbol.clone());
+        lt_name.extend(quote_symbol);
 
-        let liquidity_token_hash = <token::Module<T>>::do_issue(
-            account.clone(),
+        let liquidity_token_hash = T::Currency::issue(
+            &account,
             lt_name,
-            T::Balance::max_value(),
+            BalanceOf::<T>::max_value(),
             TokenType::Liquidity,
         )?;
 
@@ -207,11 +948,30 @@ impl<T: Trait> Module<T> {
             account,
             liquidity_token_hash,
             liquidity_token_issued_amount: Zero::zero(),
+            kind,
+            fee_tier,
         };
 
+        if kind == PoolKind::StableSwap {
+            let now = <frame_system::Module<T>>::block_number();
+            let amp = T::InitialAmplification::get();
+            Amplifications::<T>::insert(
+                hash,
+                Amplification {
+                    initial_a: amp,
+                    future_a: amp,
+                    initial_block: now,
+                    future_block: now,
+                },
+            );
+        }
+
         Nonce::mutate(|n| *n += 1);
         TradePairs::insert(hash, tp.clone());
-        TradePairsHashByBaseQuote::<T>::insert((base, quote), hash);
+        TradePairsHashByBaseQuote::<T>::insert((base, quote, fee_tier), hash);
+        TradePairsHashByBaseQuote::<T>::insert((quote, base, fee_tier), hash);
+        TradePairsHashesByBaseQuote::<T>::append((base, quote), hash);
+        TradePairsHashesByBaseQuote::<T>::append((quote, base), hash);
 
         let index = Self::trade_pair_index();
         TradePairsHashByIndex::<T>::insert(index, hash);
@@ -222,24 +982,85 @@ impl<T: Trait> Module<T> {
         Ok(())
     }
 
+    fn do_delist_trade_pair(hash: T::Hash) -> dispatch::DispatchResult {
+        let tp = Self::trade_pair(hash).ok_or(Error::<T>::NoMatchingTradePair)?;
+        ensure!(!Self::is_delisted(hash), Error::<T>::TradePairDelisted);
+
+        TradePairsHashByBaseQuote::<T>::remove((tp.base, tp.quote, tp.fee_tier));
+        TradePairsHashByBaseQuote::<T>::remove((tp.quote, tp.base, tp.fee_tier));
+        TradePairsHashesByBaseQuote::<T>::mutate((tp.base, tp.quote), |hashes| {
+            hashes.retain(|h| *h != hash)
+        });
+        TradePairsHashesByBaseQuote::<T>::mutate((tp.quote, tp.base), |hashes| {
+            hashes.retain(|h| *h != hash)
+        });
+        DelistedTradePairs::<T>::insert(hash, true);
+
+        Self::deposit_event(RawEvent::TradePairDelisted(hash));
+
+        Ok(())
+    }
+
     fn derivative_account_id(base: T::Hash, quote: T::Hash, hash: T::Hash) -> T::AccountId {
         let entropy = (b"substrate/uniswap", base, quote, hash).using_encoded(blake2_256);
         T::AccountId::decode(&mut &entropy[..]).unwrap_or_default()
     }
 
+    fn ensure_not_paused(hash: T::Hash) -> dispatch::DispatchResult {
+        ensure!(
+            !Self::is_all_paused() && !Self::is_pair_paused(hash),
+            Error::<T>::TradingPaused
+        );
+
+        Ok(())
+    }
+
+    /// Reject anything reading the reserves of a pair while they are lent out, as they no
+    /// longer price its liquidity.
+    fn ensure_not_locked(hash: T::Hash) -> dispatch::DispatchResult {
+        ensure!(!Self::is_locked(hash), Error::<T>::TradePairLocked);
+
+        Ok(())
+    }
+
+    fn ensure_tradable(tp: &TradePair<T>) -> dispatch::DispatchResult {
+        ensure!(
+            !Self::is_delisted(tp.tp_hash),
+            Error::<T>::TradePairDelisted
+        );
+        Self::ensure_not_locked(tp.tp_hash)?;
+        Self::ensure_not_paused(tp.tp_hash)?;
+        ensure!(
+            !T::Currency::is_paused(tp.base) && !T::Currency::is_paused(tp.quote),
+            Error::<T>::TokenPaused
+        );
+
+        Ok(())
+    }
+
     fn do_add_liquidity(
         sender: T::AccountId,
         hash: T::Hash,
-        base_amount: T::Balance,
-        o_quote_amount: Option<T::Balance>,
+        base_amount: BalanceOf<T>,
+        o_quote_amount: Option<BalanceOf<T>>,
     ) -> dispatch::DispatchResult {
         let mut tp = Self::trade_pair(hash).ok_or(Error::<T>::NoMatchingTradePair)?;
+        ensure!(
+            tp.kind != PoolKind::Concentrated,
+            Error::<T>::UnsupportedPoolKind
+        );
+        ensure!(!Self::is_delisted(hash), Error::<T>::TradePairDelisted);
+        Self::ensure_not_locked(hash)?;
+        Self::ensure_not_paused(hash)?;
 
         ensure!(base_amount > Zero::zero(), Error::<T>::BaseAmountIsZero);
-        <token::Module<T>>::ensure_free_balance(sender.clone(), tp.base, base_amount)?;
+        T::Currency::ensure_can_withdraw(tp.base, &sender, base_amount)?;
 
-        let pool_base_amount = <token::Module<T>>::balance_of((tp.account.clone(), tp.base));
-        let pool_quote_amount = <token::Module<T>>::balance_of((tp.account.clone(), tp.quote));
+        let pool_base_amount = T::Currency::total_balance(tp.base, &tp.account);
+        let pool_quote_amount = T::Currency::total_balance(tp.quote, &tp.account);
+
+        let protocol_fee = Self::protocol_fee(&tp, pool_base_amount, pool_quote_amount)?;
+        let total_supply = Self::total_supply(&tp, &protocol_fee);
 
         let quote_amount;
         let liquidity_minted;
@@ -250,9 +1071,8 @@ impl<T: Trait> Module<T> {
             quote_amount = o_quote_amount.unwrap();
             liquidity_minted = base_amount;
         } else {
-            // todo: overflow fix
-            quote_amount = pool_quote_amount * base_amount / pool_base_amount;
-            liquidity_minted = tp.liquidity_token_issued_amount * base_amount / pool_base_amount;
+            quote_amount = Self::mul_div(base_amount, pool_quote_amount, pool_base_amount)?;
+            liquidity_minted = Self::mul_div(base_amount, total_supply, pool_base_amount)?;
         }
 
         ensure!(quote_amount > Zero::zero(), Error::<T>::QuoteAmountIsZero);
@@ -260,51 +1080,79 @@ impl<T: Trait> Module<T> {
             liquidity_minted > Zero::zero(),
             Error::<T>::LiquidityMintedIsZero
         );
-        // ensure!(pool_base_amount * quote_amount == pool_quote_amount * base_amount, Error::<T>::LiquidityProportionInvalid);
 
-        <token::Module<T>>::ensure_free_balance(sender.clone(), tp.quote, quote_amount)?;
-        <token::Module<T>>::ensure_free_balance(
-            tp.account.clone(),
-            tp.liquidity_token_hash,
-            liquidity_minted,
-        )?;
+        T::Currency::ensure_can_withdraw(tp.quote, &sender, quote_amount)?;
+        T::Currency::ensure_can_withdraw(tp.liquidity_token_hash, &tp.account, liquidity_minted)?;
 
-        <token::Module<T>>::do_transfer(
-            sender.clone(),
-            tp.account.clone(),
-            tp.base,
-            base_amount,
-            None,
-        )?;
-        <token::Module<T>>::do_transfer(
-            sender.clone(),
-            tp.account.clone(),
-            tp.quote,
-            quote_amount,
-            None,
-        )?;
-        <token::Module<T>>::do_transfer(
-            tp.account.clone(),
-            sender.clone(),
+        Self::mint_protocol_fee(&mut tp, protocol_fee.clone())?;
+
+        T::Currency::transfer(tp.base, &sender, &tp.account, base_amount)?;
+        T::Currency::transfer(tp.quote, &sender, &tp.account, quote_amount)?;
+        T::Currency::transfer(
             tp.liquidity_token_hash,
+            &tp.account,
+            &sender,
             liquidity_minted,
-            None,
         )?;
 
         tp.liquidity_token_issued_amount = tp.liquidity_token_issued_amount + liquidity_minted;
+        let (reserve_base, reserve_quote) = Self::sync(&tp);
         <TradePairs<T>>::insert(hash, tp);
+        Self::update_k_last(hash, protocol_fee.is_some(), reserve_base, reserve_quote);
 
-        Self::deposit_event(RawEvent::LiquidityAdded(sender, hash));
+        Self::deposit_event(RawEvent::LiquidityAdded(
+            sender,
+            hash,
+            base_amount,
+            quote_amount,
+            liquidity_minted,
+            reserve_base,
+            reserve_quote,
+        ));
 
         Ok(())
     }
 
+    /// Add liquidity to the pair of `base` and `quote`, with the amounts given in this order
+    /// even if the pair was created the other way around.
+    fn do_add_liquidity_by_base_quote(
+        sender: T::AccountId,
+        base: T::Hash,
+        quote: T::Hash,
+        fee_tier: u32,
+        base_amount: BalanceOf<T>,
+        o_quote_amount: Option<BalanceOf<T>>,
+    ) -> dispatch::DispatchResult {
+        let hash = Self::trade_pair_hash_by_base_quote((base, quote, fee_tier))
+            .ok_or(Error::<T>::NoMatchingTradePair)?;
+        let tp = Self::trade_pair(hash).ok_or(Error::<T>::NoMatchingTradePair)?;
+        if tp.base == base {
+            return Self::do_add_liquidity(sender, hash, base_amount, o_quote_amount);
+        }
+
+        // `base_amount` is an amount of the quote of the pair
+        let pool_base_amount = T::Currency::total_balance(tp.base, &tp.account);
+        let pool_quote_amount = T::Currency::total_balance(tp.quote, &tp.account);
+        if pool_quote_amount == Zero::zero() || tp.liquidity_token_issued_amount == Zero::zero() {
+            let pair_base_amount = o_quote_amount.ok_or(Error::<T>::QuoteAmountIsNone)?;
+            Self::do_add_liquidity(sender, hash, pair_base_amount, Some(base_amount))
+        } else {
+            let pair_base_amount = Self::mul_div(base_amount, pool_base_amount, pool_quote_amount)?;
+            Self::do_add_liquidity(sender, hash, pair_base_amount, None)
+        }
+    }
+
     fn do_remove_liquidity(
         sender: T::AccountId,
         hash: T::Hash,
-        liquidity_token_amount: T::Balance,
+        liquidity_token_amount: BalanceOf<T>,
     ) -> dispatch::DispatchResult {
         let mut tp = Self::trade_pair(hash).ok_or(Error::<T>::NoMatchingTradePair)?;
+        ensure!(
+            tp.kind != PoolKind::Concentrated,
+            Error::<T>::UnsupportedPoolKind
+        );
+        Self::ensure_not_locked(hash)?;
 
         ensure!(
             liquidity_token_amount <= tp.liquidity_token_issued_amount,
@@ -315,13 +1163,9 @@ impl<T: Trait> Module<T> {
             liquidity_token_amount > Zero::zero(),
             Error::<T>::LiquidityTokenAmountIsZero
         );
-        ensure!(
-            tp.liquidity_token_issued_amount > Zero::zero(),
-            Error::<T>::LiquidityTokenIssuedAmountIsZero
-        );
 
-        let pool_base_amount = <token::Module<T>>::balance_of((tp.account.clone(), tp.base));
-        let pool_quote_amount = <token::Module<T>>::balance_of((tp.account.clone(), tp.quote));
+        let pool_base_amount = T::Currency::total_balance(tp.base, &tp.account);
+        let pool_quote_amount = T::Currency::total_balance(tp.quote, &tp.account);
         ensure!(
             pool_base_amount > Zero::zero(),
             Error::<T>::PoolBaseAmountIsZero
@@ -331,61 +1175,204 @@ impl<T: Trait> Module<T> {
             Error::<T>::PoolQuoteAmountIsZero
         );
 
-        let base_amount =
-            pool_base_amount * liquidity_token_amount / tp.liquidity_token_issued_amount;
-        let quote_amount =
-            pool_quote_amount * liquidity_token_amount / tp.liquidity_token_issued_amount;
+        let protocol_fee = Self::protocol_fee(&tp, pool_base_amount, pool_quote_amount)?;
+        let total_supply = Self::total_supply(&tp, &protocol_fee);
+
+        let base_amount = Self::mul_div(liquidity_token_amount, pool_base_amount, total_supply)?;
+        let quote_amount = Self::mul_div(liquidity_token_amount, pool_quote_amount, total_supply)?;
         ensure!(quote_amount > Zero::zero(), Error::<T>::QuoteAmountIsZero);
         ensure!(base_amount > Zero::zero(), Error::<T>::BaseAmountIsZero);
 
-        <token::Module<T>>::ensure_free_balance(tp.account.clone(), tp.base, base_amount)?;
-        <token::Module<T>>::ensure_free_balance(tp.account.clone(), tp.quote, quote_amount)?;
-        <token::Module<T>>::ensure_free_balance(
-            sender.clone(),
-            tp.liquidity_token_hash,
-            liquidity_token_amount,
-        )?;
+        T::Currency::ensure_can_withdraw(tp.base, &tp.account, base_amount)?;
+        T::Currency::ensure_can_withdraw(tp.quote, &tp.account, quote_amount)?;
+        T::Currency::ensure_can_withdraw(tp.liquidity_token_hash, &sender, liquidity_token_amount)?;
 
-        <token::Module<T>>::do_transfer(
-            tp.account.clone(),
-            sender.clone(),
-            tp.base,
-            base_amount,
-            None,
-        )?;
-        <token::Module<T>>::do_transfer(
-            tp.account.clone(),
-            sender.clone(),
-            tp.quote,
-            quote_amount,
-            None,
-        )?;
-        <token::Module<T>>::do_transfer(
-            sender.clone(),
-            tp.account.clone(),
+        Self::mint_protocol_fee(&mut tp, protocol_fee.clone())?;
+
+        T::Currency::transfer(tp.base, &tp.account, &sender, base_amount)?;
+        T::Currency::transfer(tp.quote, &tp.account, &sender, quote_amount)?;
+        T::Currency::transfer(
             tp.liquidity_token_hash,
+            &sender,
+            &tp.account,
             liquidity_token_amount,
-            None,
         )?;
 
         tp.liquidity_token_issued_amount =
             tp.liquidity_token_issued_amount - liquidity_token_amount;
+        let (reserve_base, reserve_quote) = Self::sync(&tp);
         <TradePairs<T>>::insert(hash, tp);
+        Self::update_k_last(hash, protocol_fee.is_some(), reserve_base, reserve_quote);
+
+        Self::deposit_event(RawEvent::LiquidityRemoved(
+            sender,
+            hash,
+            base_amount,
+            quote_amount,
+            liquidity_token_amount,
+            reserve_base,
+            reserve_quote,
+        ));
+
+        Ok(())
+    }
 
-        Self::deposit_event(RawEvent::LiquidityRemoved(sender, hash));
+    fn do_set_protocol_fee(
+        recipient: Option<T::AccountId>,
+        share: Permill,
+    ) -> dispatch::DispatchResult {
+        ensure!(
+            recipient.is_some() || share.is_zero(),
+            Error::<T>::ProtocolFeeRecipientIsNone
+        );
+
+        ProtocolFeeRecipient::<T>::set(recipient.clone());
+        ProtocolFeeShare::put(share);
+
+        Self::deposit_event(RawEvent::ProtocolFeeSet(recipient, share));
+
+        Ok(())
+    }
+
+    /// The protocol fee recipient of a pair and the liquidity tokens to mint to it for the swap
+    /// fees earned since the last liquidity event, as Uniswap V2 does, or none when the protocol
+    /// fee is off. Only constant product pairs pay the protocol fee.
+    fn protocol_fee(
+        tp: &TradePair<T>,
+        pool_base_amount: BalanceOf<T>,
+        pool_quote_amount: BalanceOf<T>,
+    ) -> Result<Option<(T::AccountId, BalanceOf<T>)>, dispatch::DispatchError> {
+        let share = Self::protocol_fee_share();
+        let recipient = match Self::protocol_fee_recipient() {
+            Some(recipient) if tp.kind == PoolKind::ConstantProduct && !share.is_zero() => {
+                recipient
+            }
+            _ => return Ok(None),
+        };
+
+        let k_last = Self::k_last(tp.tp_hash);
+        if k_last.is_zero() || tp.liquidity_token_issued_amount.is_zero() {
+            return Ok(Some((recipient, Zero::zero())));
+        }
+
+        let root_k =
+            integer_sqrt(Self::to_u256(pool_base_amount) * Self::to_u256(pool_quote_amount));
+        let root_k_last = integer_sqrt(k_last);
+        if root_k <= root_k_last {
+            return Ok(Some((recipient, Zero::zero())));
+        }
+
+        // supply * (root_k - root_k_last) * share / ((1 - share) * root_k + share * root_k_last)
+        let share_parts = U256::from(share.deconstruct());
+        let numerator = Self::to_u256(tp.liquidity_token_issued_amount)
+            .checked_mul(root_k - root_k_last)
+            .and_then(|n| n.checked_mul(share_parts))
+            .ok_or(Error::<T>::ProtocolFeeOverflow)?;
+        let denominator = (U256::from(Permill::ACCURACY) - share_parts)
+            .checked_mul(root_k)
+            .and_then(|d| d.checked_add(share_parts.checked_mul(root_k_last)?))
+            .ok_or(Error::<T>::ProtocolFeeOverflow)?;
+        let liquidity = (numerator / denominator).low_u128().saturated_into();
+
+        Ok(Some((recipient, liquidity)))
+    }
+
+    /// Liquidity tokens issued by a pair once the protocol fee is minted.
+    fn total_supply(
+        tp: &TradePair<T>,
+        protocol_fee: &Option<(T::AccountId, BalanceOf<T>)>,
+    ) -> BalanceOf<T> {
+        match protocol_fee {
+            Some((_, liquidity)) => tp.liquidity_token_issued_amount + *liquidity,
+            None => tp.liquidity_token_issued_amount,
+        }
+    }
+
+    fn mint_protocol_fee(
+        tp: &mut TradePair<T>,
+        protocol_fee: Option<(T::AccountId, BalanceOf<T>)>,
+    ) -> dispatch::DispatchResult {
+        if let Some((recipient, liquidity)) = protocol_fee {
+            if liquidity > Zero::zero() {
+                T::Currency::transfer(tp.liquidity_token_hash, &tp.account, &recipient, liquidity)?;
+                tp.liquidity_token_issued_amount = tp.liquidity_token_issued_amount + liquidity;
+                ProtocolFeesAccrued::<T>::mutate(tp.tp_hash, |accrued| {
+                    *accrued = *accrued + liquidity
+                });
+
+                Self::deposit_event(RawEvent::ProtocolFeeMinted(
+                    tp.tp_hash, recipient, liquidity,
+                ));
+            }
+        }
 
         Ok(())
     }
 
+    fn update_k_last(
+        hash: T::Hash,
+        fee_on: bool,
+        pool_base_amount: BalanceOf<T>,
+        pool_quote_amount: BalanceOf<T>,
+    ) {
+        if fee_on {
+            KLast::<T>::insert(
+                hash,
+                Self::to_u256(pool_base_amount) * Self::to_u256(pool_quote_amount),
+            );
+        } else if KLast::<T>::contains_key(hash) {
+            KLast::<T>::remove(hash);
+        }
+    }
+
+    /// The base and quote amounts that the liquidity tokens minted to the protocol so far are
+    /// worth at the current reserves of a pair.
+    pub fn protocol_revenue(hash: T::Hash) -> (BalanceOf<T>, BalanceOf<T>) {
+        let accrued = Self::protocol_fees_accrued(hash);
+        match Self::trade_pair(hash) {
+            Some(tp) if !accrued.is_zero() && !tp.liquidity_token_issued_amount.is_zero() => {
+                let pool_base_amount = T::Currency::total_balance(tp.base, &tp.account);
+                let pool_quote_amount = T::Currency::total_balance(tp.quote, &tp.account);
+                // accrued never exceeds the issued amount, so the shares fit back in a balance
+                let share = |amount: BalanceOf<T>| {
+                    (Self::to_u256(amount) * Self::to_u256(accrued)
+                        / Self::to_u256(tp.liquidity_token_issued_amount))
+                    .low_u128()
+                    .saturated_into()
+                };
+                (share(pool_base_amount), share(pool_quote_amount))
+            }
+            _ => (Zero::zero(), Zero::zero()),
+        }
+    }
+
+    fn do_swap(
+        sender: T::AccountId,
+        hash: T::Hash,
+        token_in: T::Hash,
+        amount_in: BalanceOf<T>,
+    ) -> dispatch::DispatchResult {
+        let tp = Self::trade_pair(hash).ok_or(Error::<T>::NoMatchingTradePair)?;
+
+        if token_in == tp.base {
+            Self::do_swap_buy(sender, hash, amount_in)
+        } else if token_in == tp.quote {
+            Self::do_swap_sell(sender, hash, amount_in)
+        } else {
+            Err(Error::<T>::TokenNotInTradePair.into())
+        }
+    }
+
     fn do_swap_buy(
         sender: T::AccountId,
         hash: T::Hash,
-        base_amount: T::Balance,
+        base_amount: BalanceOf<T>,
     ) -> dispatch::DispatchResult {
         let tp = Self::trade_pair(hash).ok_or(Error::<T>::NoMatchingTradePair)?;
+        Self::ensure_tradable(&tp)?;
 
-        let pool_base_amount = <token::Module<T>>::balance_of((tp.account.clone(), tp.base));
-        let pool_quote_amount = <token::Module<T>>::balance_of((tp.account.clone(), tp.quote));
+        let pool_base_amount = T::Currency::total_balance(tp.base, &tp.account);
+        let pool_quote_amount = T::Currency::total_balance(tp.quote, &tp.account);
         ensure!(
             pool_base_amount > Zero::zero(),
             Error::<T>::PoolBaseAmountIsZero
@@ -395,33 +1382,42 @@ impl<T: Trait> Module<T> {
             Error::<T>::PoolQuoteAmountIsZero
         );
 
-        // todo: add fee support
-        let quote_amount = (pool_quote_amount * (pool_base_amount + base_amount)
-            - pool_quote_amount * pool_base_amount)
-            / (pool_base_amount + base_amount);
+        ensure!(base_amount > Zero::zero(), Error::<T>::BaseAmountIsZero);
 
+        let (quote_amount, concentrated_swap) = Self::compute_swap(&tp, true, base_amount)?;
         ensure!(quote_amount > Zero::zero(), Error::<T>::QuoteAmountIsZero);
-        ensure!(base_amount > Zero::zero(), Error::<T>::BaseAmountIsZero);
 
-        <token::Module<T>>::ensure_free_balance(sender.clone(), tp.base, base_amount)?;
-        <token::Module<T>>::ensure_free_balance(tp.account.clone(), tp.quote, quote_amount)?;
+        T::Currency::ensure_can_withdraw(tp.base, &sender, base_amount)?;
+        T::Currency::ensure_can_withdraw(tp.quote, &tp.account, quote_amount)?;
+
+        Self::check_circuit_breaker(
+            &tp,
+            (pool_base_amount, pool_quote_amount),
+            (
+                pool_base_amount
+                    .checked_add(&base_amount)
+                    .ok_or(Error::<T>::PoolAmountOverflow)?,
+                pool_quote_amount - quote_amount,
+            ),
+            &concentrated_swap,
+        )?;
 
-        <token::Module<T>>::do_transfer(
-            sender.clone(),
-            tp.account.clone(),
-            tp.base,
+        T::Currency::transfer(tp.base, &sender, &tp.account, base_amount)?;
+        T::Currency::transfer(tp.quote, &tp.account, &sender, quote_amount)?;
+
+        if let Some(swap) = concentrated_swap {
+            Self::apply_concentrated_swap(hash, swap);
+        }
+
+        let (reserve_base, reserve_quote) = Self::sync(&tp);
+        Self::deposit_event(RawEvent::SwapBuy(
+            sender,
+            hash,
             base_amount,
-            None,
-        )?;
-        <token::Module<T>>::do_transfer(
-            tp.account.clone(),
-            sender.clone(),
-            tp.quote,
             quote_amount,
-            None,
-        )?;
-
-        Self::deposit_event(RawEvent::SwapBuy(sender, hash));
+            reserve_base,
+            reserve_quote,
+        ));
 
         Ok(())
     }
@@ -429,12 +1425,13 @@ impl<T: Trait> Module<T> {
     fn do_swap_sell(
         sender: T::AccountId,
         hash: T::Hash,
-        quote_amount: T::Balance,
+        quote_amount: BalanceOf<T>,
     ) -> dispatch::DispatchResult {
         let tp = Self::trade_pair(hash).ok_or(Error::<T>::NoMatchingTradePair)?;
+        Self::ensure_tradable(&tp)?;
 
-        let pool_base_amount = <token::Module<T>>::balance_of((tp.account.clone(), tp.base));
-        let pool_quote_amount = <token::Module<T>>::balance_of((tp.account.clone(), tp.quote));
+        let pool_base_amount = T::Currency::total_balance(tp.base, &tp.account);
+        let pool_quote_amount = T::Currency::total_balance(tp.quote, &tp.account);
         ensure!(
             pool_base_amount > Zero::zero(),
             Error::<T>::PoolBaseAmountIsZero
@@ -444,33 +1441,569 @@ impl<T: Trait> Module<T> {
             Error::<T>::PoolQuoteAmountIsZero
         );
 
-        let base_amount = (pool_base_amount * (pool_quote_amount + quote_amount)
-            - pool_quote_amount * pool_base_amount)
-            / (pool_quote_amount + quote_amount);
-
         ensure!(quote_amount > Zero::zero(), Error::<T>::QuoteAmountIsZero);
+
+        let (base_amount, concentrated_swap) = Self::compute_swap(&tp, false, quote_amount)?;
         ensure!(base_amount > Zero::zero(), Error::<T>::BaseAmountIsZero);
 
-        <token::Module<T>>::ensure_free_balance(tp.account.clone(), tp.base, base_amount)?;
-        <token::Module<T>>::ensure_free_balance(sender.clone(), tp.quote, quote_amount)?;
+        T::Currency::ensure_can_withdraw(tp.base, &tp.account, base_amount)?;
+        T::Currency::ensure_can_withdraw(tp.quote, &sender, quote_amount)?;
+
+        Self::check_circuit_breaker(
+            &tp,
+            (pool_base_amount, pool_quote_amount),
+            (
+                pool_base_amount - base_amount,
+                pool_quote_amount
+                    .checked_add(&quote_amount)
+                    .ok_or(Error::<T>::PoolAmountOverflow)?,
+            ),
+            &concentrated_swap,
+        )?;
 
-        <token::Module<T>>::do_transfer(
-            tp.account.clone(),
-            sender.clone(),
-            tp.base,
+        T::Currency::transfer(tp.base, &tp.account, &sender, base_amount)?;
+        T::Currency::transfer(tp.quote, &sender, &tp.account, quote_amount)?;
+
+        if let Some(swap) = concentrated_swap {
+            Self::apply_concentrated_swap(hash, swap);
+        }
+
+        let (reserve_base, reserve_quote) = Self::sync(&tp);
+        Self::deposit_event(RawEvent::SwapSell(
+            sender,
+            hash,
+            quote_amount,
             base_amount,
-            None,
+            reserve_base,
+            reserve_quote,
+        ));
+
+        Ok(())
+    }
+
+    /// Announce the reserves of a pair with a `Sync` event once they changed, and return them.
+    fn sync(tp: &TradePair<T>) -> (BalanceOf<T>, BalanceOf<T>) {
+        let reserve_base = T::Currency::total_balance(tp.base, &tp.account);
+        let reserve_quote = T::Currency::total_balance(tp.quote, &tp.account);
+        Self::deposit_event(RawEvent::Sync(tp.tp_hash, reserve_base, reserve_quote));
+
+        (reserve_base, reserve_quote)
+    }
+
+    fn do_set_pair_paused(hash: T::Hash, paused: bool) -> dispatch::DispatchResult {
+        ensure!(
+            TradePairs::<T>::contains_key(hash) || WeightedPools::<T>::contains_key(hash),
+            Error::<T>::NoMatchingTradePair
+        );
+
+        if paused {
+            PausedPairs::<T>::insert(hash, true);
+            Self::deposit_event(RawEvent::PairPaused(hash));
+        } else {
+            PausedPairs::<T>::remove(hash);
+            Self::deposit_event(RawEvent::PairUnpaused(hash));
+        }
+
+        Ok(())
+    }
+
+    fn do_set_circuit_breaker(
+        hash: T::Hash,
+        max_price_move: Option<Permill>,
+    ) -> dispatch::DispatchResult {
+        ensure!(
+            TradePairs::<T>::contains_key(hash),
+            Error::<T>::NoMatchingTradePair
+        );
+
+        match max_price_move {
+            Some(max_price_move) => CircuitBreakers::<T>::insert(hash, max_price_move),
+            None => {
+                CircuitBreakers::<T>::remove(hash);
+                BlockStartPrices::<T>::remove(hash);
+            }
+        }
+
+        Self::deposit_event(RawEvent::CircuitBreakerSet(hash, max_price_move));
+
+        Ok(())
+    }
+
+    /// Reject a swap taking the reserves of a pair from `reserves_before` to `reserves_after`,
+    /// both as (base, quote), when it moves the price further from the price at the start of
+    /// the block than the circuit breaker of the pair allows.
+    fn check_circuit_breaker(
+        tp: &TradePair<T>,
+        reserves_before: (BalanceOf<T>, BalanceOf<T>),
+        reserves_after: (BalanceOf<T>, BalanceOf<T>),
+        concentrated_swap: &Option<concentrated::ConcentratedSwap>,
+    ) -> dispatch::DispatchResult {
+        let max_price_move = match Self::circuit_breaker(tp.tp_hash) {
+            Some(max_price_move) => max_price_move,
+            None => return Ok(()),
+        };
+
+        // prices are quote per base, as (numerator, denominator)
+        let (price_before, price_after) = match concentrated_swap {
+            Some(swap) => {
+                let pool =
+                    Self::concentrated_pool(tp.tp_hash).ok_or(Error::<T>::PoolNotInitialized)?;
+                (
+                    Self::sqrt_price_to_price(pool.sqrt_price),
+                    Self::sqrt_price_to_price(swap.sqrt_price()),
+                )
+            }
+            None => (
+                (
+                    Self::to_u256(reserves_before.1),
+                    Self::to_u256(reserves_before.0),
+                ),
+                (
+                    Self::to_u256(reserves_after.1),
+                    Self::to_u256(reserves_after.0),
+                ),
+            ),
+        };
+
+        let now = <frame_system::Module<T>>::block_number();
+        let (start_numerator, start_denominator) = match Self::block_start_price(tp.tp_hash) {
+            Some((block, numerator, denominator)) if block == now => (numerator, denominator),
+            _ => {
+                BlockStartPrices::<T>::insert(tp.tp_hash, (now, price_before.0, price_before.1));
+                price_before
+            }
+        };
+
+        // |after - start| <= max_price_move * start, cross multiplied by both denominators
+        let after = price_after.0.saturating_mul(start_denominator);
+        let start = start_numerator.saturating_mul(price_after.1);
+        let price_move = if after > start {
+            after - start
+        } else {
+            start - after
+        };
+        ensure!(
+            price_move.saturating_mul(U256::from(Permill::ACCURACY))
+                <= start.saturating_mul(U256::from(max_price_move.deconstruct())),
+            Error::<T>::PriceMoveTooLarge
+        );
+
+        Ok(())
+    }
+
+    /// Quote per base price of a Q64.64 square root price, as (numerator, denominator).
+    fn sqrt_price_to_price(sqrt_price: u128) -> (U256, U256) {
+        (
+            (U256::from(sqrt_price) * U256::from(sqrt_price)) >> 64,
+            U256::one() << 64,
+        )
+    }
+
+    fn do_flash_swap(
+        sender: T::AccountId,
+        hash: T::Hash,
+        base_amount_out: BalanceOf<T>,
+        quote_amount_out: BalanceOf<T>,
+        data: Vec<u8>,
+    ) -> dispatch::DispatchResult {
+        let tp = Self::trade_pair(hash).ok_or(Error::<T>::NoMatchingTradePair)?;
+        Self::ensure_tradable(&tp)?;
+        ensure!(
+            tp.kind != PoolKind::Concentrated,
+            Error::<T>::UnsupportedPoolKind
+        );
+
+        ensure!(
+            base_amount_out > Zero::zero() || quote_amount_out > Zero::zero(),
+            Error::<T>::FlashSwapAmountIsZero
+        );
+
+        let pool_base_amount = T::Currency::total_balance(tp.base, &tp.account);
+        let pool_quote_amount = T::Currency::total_balance(tp.quote, &tp.account);
+        ensure!(
+            base_amount_out < pool_base_amount && quote_amount_out < pool_quote_amount,
+            Error::<T>::InsufficientLiquidity
+        );
+
+        // the lock is rolled back along with everything else if the swap fails
+        LockedTradePairs::<T>::insert(hash, true);
+        if base_amount_out > Zero::zero() {
+            T::Currency::transfer(tp.base, &tp.account, &sender, base_amount_out)?;
+        }
+        if quote_amount_out > Zero::zero() {
+            T::Currency::transfer(tp.quote, &tp.account, &sender, quote_amount_out)?;
+        }
+
+        T::FlashSwapHandler::on_flash_swap(
+            &sender,
+            hash,
+            &tp.account,
+            base_amount_out,
+            quote_amount_out,
+            &data,
         )?;
-        <token::Module<T>>::do_transfer(
-            sender.clone(),
-            tp.account.clone(),
-            tp.quote,
-            quote_amount,
-            None,
+
+        let new_pool_base_amount = T::Currency::total_balance(tp.base, &tp.account);
+        let new_pool_quote_amount = T::Currency::total_balance(tp.quote, &tp.account);
+        let base_amount_in =
+            new_pool_base_amount.saturating_sub(pool_base_amount - base_amount_out);
+        let quote_amount_in =
+            new_pool_quote_amount.saturating_sub(pool_quote_amount - quote_amount_out);
+        ensure!(
+            base_amount_in > Zero::zero() || quote_amount_in > Zero::zero(),
+            Error::<T>::FlashSwapNotRepaid
+        );
+
+        // balances net of the fee on the input amounts, scaled by the fee tier denominator
+        let denominator = U256::from(FEE_TIER_DENOMINATOR);
+        let fee = U256::from(tp.fee_tier);
+        let adjusted_base =
+            Self::to_u256(new_pool_base_amount) * denominator - Self::to_u256(base_amount_in) * fee;
+        let adjusted_quote = Self::to_u256(new_pool_quote_amount) * denominator
+            - Self::to_u256(quote_amount_in) * fee;
+
+        match tp.kind {
+            PoolKind::ConstantProduct => {
+                // both sides take up to 512 bits with reserves near the top of the balance range
+                ensure!(
+                    adjusted_base.full_mul(adjusted_quote)
+                        >= (Self::to_u256(pool_base_amount) * Self::to_u256(pool_quote_amount))
+                            .full_mul(denominator * denominator),
+                    Error::<T>::FlashSwapNotRepaid
+                );
+            }
+            PoolKind::StableSwap => {
+                let amp = Self::current_amplification(hash);
+                let d = stable::get_d(
+                    pool_base_amount.saturated_into(),
+                    pool_quote_amount.saturated_into(),
+                    amp,
+                )
+                .ok_or(Error::<T>::StableSwapMathFailed)?;
+                let new_d = stable::get_d(
+                    (adjusted_base / denominator).low_u128(),
+                    (adjusted_quote / denominator).low_u128(),
+                    amp,
+                )
+                .ok_or(Error::<T>::StableSwapMathFailed)?;
+                ensure!(new_d >= d, Error::<T>::FlashSwapNotRepaid);
+            }
+            // concentrated liquidity pairs are rejected above
+            PoolKind::Concentrated => {}
+        }
+        ensure!(
+            Self::trade_pair(hash).map(|tp| tp.liquidity_token_issued_amount)
+                == Some(tp.liquidity_token_issued_amount),
+            Error::<T>::LiquidityChangedWhileLocked
+        );
+        LockedTradePairs::<T>::remove(hash);
+
+        Self::check_circuit_breaker(
+            &tp,
+            (pool_base_amount, pool_quote_amount),
+            (new_pool_base_amount, new_pool_quote_amount),
+            &None,
         )?;
+        Self::sync(&tp);
+        Self::deposit_event(RawEvent::FlashSwap(sender, hash));
+
+        Ok(())
+    }
+
+    fn do_flash_loan(
+        sender: T::AccountId,
+        hash: T::Hash,
+        token: T::Hash,
+        amount: BalanceOf<T>,
+        call: <T as Trait>::Call,
+    ) -> dispatch::DispatchResult {
+        let tp = Self::trade_pair(hash).ok_or(Error::<T>::NoMatchingTradePair)?;
+        Self::ensure_tradable(&tp)?;
+
+        ensure!(
+            token == tp.base || token == tp.quote,
+            Error::<T>::TokenNotInTradePair
+        );
+        ensure!(amount > Zero::zero(), Error::<T>::FlashLoanAmountIsZero);
+
+        let pool_amount = T::Currency::total_balance(token, &tp.account);
+        ensure!(amount < pool_amount, Error::<T>::InsufficientLiquidity);
+
+        let fee = T::FlashLoanFee::get().mul_ceil(amount);
+
+        // the lock is rolled back along with everything else if the loan fails
+        LockedTradePairs::<T>::insert(hash, true);
+        T::Currency::transfer(token, &tp.account, &sender, amount)?;
+
+        call.dispatch(frame_system::RawOrigin::Signed(sender.clone()).into())
+            .map(|_| ())
+            .map_err(|e| e.error)?;
+
+        let new_pool_amount = T::Currency::total_balance(token, &tp.account);
+        ensure!(
+            new_pool_amount >= pool_amount.saturating_add(fee),
+            Error::<T>::FlashLoanNotRepaid
+        );
+        ensure!(
+            Self::trade_pair(hash).map(|tp| tp.liquidity_token_issued_amount)
+                == Some(tp.liquidity_token_issued_amount),
+            Error::<T>::LiquidityChangedWhileLocked
+        );
+        LockedTradePairs::<T>::remove(hash);
 
-        Self::deposit_event(RawEvent::SwapSell(sender, hash));
+        Self::sync(&tp);
+        Self::deposit_event(RawEvent::FlashLoan(sender, hash, token, amount, fee));
 
         Ok(())
     }
+
+    /// The trade pairs of `token_in` and `token_out` quoted for a swap, as many as there are
+    /// fee tiers so that the weight of quoting them is bounded, even with the pairs of other
+    /// fee tiers left by upgrades.
+    fn quoted_trade_pair_hashes(
+        token_in: T::Hash,
+        token_out: T::Hash,
+    ) -> impl Iterator<Item = T::Hash> {
+        Self::trade_pair_hashes_by_base_quote((token_in, token_out))
+            .into_iter()
+            .take(T::FeeTiers::get().len())
+    }
+
+    /// Weight of a swap in a pair of `kind`, buying if `base_in`, crossing as many ticks as a
+    /// swap may in a concentrated liquidity pair.
+    pub fn swap_weight(kind: PoolKind, base_in: bool) -> Weight {
+        match (kind, base_in) {
+            (PoolKind::ConstantProduct, true) => T::WeightInfo::swap_buy(),
+            (PoolKind::ConstantProduct, false) => T::WeightInfo::swap_sell(),
+            (PoolKind::StableSwap, true) => T::WeightInfo::swap_buy_stable(),
+            (PoolKind::StableSwap, false) => T::WeightInfo::swap_sell_stable(),
+            (PoolKind::Concentrated, true) => {
+                T::WeightInfo::swap_buy_concentrated(concentrated::MAX_TICKS_CROSSED)
+            }
+            (PoolKind::Concentrated, false) => {
+                T::WeightInfo::swap_sell_concentrated(concentrated::MAX_TICKS_CROSSED)
+            }
+        }
+    }
+
+    /// Weight of a swap in the costliest pool kind, buying if `base_in`.
+    pub fn max_swap_weight(base_in: bool) -> Weight {
+        [
+            PoolKind::ConstantProduct,
+            PoolKind::StableSwap,
+            PoolKind::Concentrated,
+        ]
+        .iter()
+        .map(|kind| Self::swap_weight(*kind, base_in))
+        .max()
+        .unwrap_or_default()
+    }
+
+    /// The trade pair of `token_in` and `token_out`, over every fee tier, sending out the most
+    /// for `amount_in` of `token_in`, with that amount.
+    pub fn best_trade_pair(
+        token_in: T::Hash,
+        token_out: T::Hash,
+        amount_in: BalanceOf<T>,
+    ) -> Option<(T::Hash, BalanceOf<T>)> {
+        Self::quoted_trade_pair_hashes(token_in, token_out)
+            .filter_map(|hash| {
+                let tp = Self::trade_pair(hash)?;
+                let (amount_out, _) =
+                    Self::compute_swap(&tp, tp.base == token_in, amount_in).ok()?;
+                Some((hash, amount_out))
+            })
+            .max_by_key(|(_, amount_out)| *amount_out)
+    }
+
+    /// The trade pair of `token_in` and `token_out`, over every fee tier, taking the least of
+    /// `token_in` for at least `amount_out` of `token_out`, with that amount, if it is at most
+    /// `max_amount_in`.
+    pub fn best_trade_pair_for_amount_out(
+        token_in: T::Hash,
+        token_out: T::Hash,
+        amount_out: BalanceOf<T>,
+        max_amount_in: BalanceOf<T>,
+    ) -> Option<(T::Hash, BalanceOf<T>)> {
+        Self::quoted_trade_pair_hashes(token_in, token_out)
+            .filter_map(|hash| {
+                let tp = Self::trade_pair(hash)?;
+                let amount_in =
+                    Self::compute_amount_in(&tp, tp.base == token_in, amount_out, max_amount_in)?;
+                Some((hash, amount_in))
+            })
+            .min_by_key(|(_, amount_in)| *amount_in)
+    }
+
+    /// The least amount of base, or of quote if `base_in` is false, sending at least
+    /// `amount_out` out of the pair, if it is at most `max_amount_in`. Found by bisection, as
+    /// the amount sent out grows with the amount in for every pool kind. The bisection stops
+    /// after `AMOUNT_IN_SEARCH_STEPS`, with an amount that is enough if not the least when
+    /// `max_amount_in` takes more bits.
+    fn compute_amount_in(
+        tp: &TradePair<T>,
+        base_in: bool,
+        amount_out: BalanceOf<T>,
+        max_amount_in: BalanceOf<T>,
+    ) -> Option<BalanceOf<T>> {
+        let enough = |amount_in: BalanceOf<T>| {
+            Self::compute_swap(tp, base_in, amount_in)
+                .map(|(sent, _)| sent >= amount_out)
+                .unwrap_or(false)
+        };
+        if amount_out.is_zero() || !enough(max_amount_in) {
+            return None;
+        }
+
+        // nothing is sent out for nothing, so `low` is never enough and `high` always is
+        let mut low: BalanceOf<T> = Zero::zero();
+        let mut high = max_amount_in;
+        let mut steps = 0;
+        while high - low > One::one() && steps < AMOUNT_IN_SEARCH_STEPS {
+            steps += 1;
+            let middle = low + (high - low) / BalanceOf::<T>::from(2u32);
+            if enough(middle) {
+                high = middle;
+            } else {
+                low = middle;
+            }
+        }
+
+        Some(high)
+    }
+
+    /// The amount sent out of the pair for `amount_in` of base, or of quote if `base_in` is
+    /// false, along with the new state of a concentrated liquidity pair.
+    fn compute_swap(
+        tp: &TradePair<T>,
+        base_in: bool,
+        amount_in: BalanceOf<T>,
+    ) -> Result<(BalanceOf<T>, Option<concentrated::ConcentratedSwap>), dispatch::DispatchError>
+    {
+        if tp.kind == PoolKind::Concentrated {
+            let swap = Self::compute_concentrated_swap(
+                tp.tp_hash,
+                base_in,
+                amount_in.saturated_into(),
+                u128::from(tp.fee_tier) * 100,
+            )?;
+            return Ok((Self::to_balance(swap.amount_out)?, Some(swap)));
+        }
+
+        let (token_in, token_out) = if base_in {
+            (tp.base, tp.quote)
+        } else {
+            (tp.quote, tp.base)
+        };
+        let reserve_in = T::Currency::total_balance(token_in, &tp.account);
+        let reserve_out = T::Currency::total_balance(token_out, &tp.account);
+        ensure!(
+            !reserve_in.is_zero() && !reserve_out.is_zero(),
+            Error::<T>::InsufficientLiquidity
+        );
+
+        Ok((
+            Self::get_amount_out(tp, reserve_in, reserve_out, amount_in)?,
+            None,
+        ))
+    }
+
+    /// The amount sent out of the pool for `amount_in` added to `reserve_in`, net of the fee.
+    fn get_amount_out(
+        tp: &TradePair<T>,
+        reserve_in: BalanceOf<T>,
+        reserve_out: BalanceOf<T>,
+        amount_in: BalanceOf<T>,
+    ) -> Result<BalanceOf<T>, dispatch::DispatchError> {
+        let fee = Permill::from_parts(tp.fee_tier * 100).mul_ceil(amount_in);
+        let amount_in = amount_in - fee;
+
+        match tp.kind {
+            // in 256 bits, as the amount in is given by the user; the result is below
+            // `reserve_out`, so it fits back in a balance
+            PoolKind::ConstantProduct => Ok((Self::to_u256(reserve_out)
+                * Self::to_u256(amount_in)
+                / (Self::to_u256(reserve_in) + Self::to_u256(amount_in)))
+            .low_u128()
+            .saturated_into()),
+            PoolKind::StableSwap => stable::get_amount_out(
+                Self::current_amplification(tp.tp_hash),
+                reserve_in.saturated_into(),
+                reserve_out.saturated_into(),
+                amount_in.saturated_into(),
+            )
+            .map(|amount| amount.saturated_into())
+            .ok_or_else(|| Error::<T>::StableSwapMathFailed.into()),
+            PoolKind::Concentrated => Err(Error::<T>::UnsupportedPoolKind.into()),
+        }
+    }
+
+    /// The amplification coefficient of a StableSwap pair at the current block.
+    pub fn current_amplification(hash: T::Hash) -> u128 {
+        let now = <frame_system::Module<T>>::block_number();
+        Self::amplification(hash)
+            .map(|amplification| amplification.at(now))
+            .unwrap_or_else(T::InitialAmplification::get)
+    }
+
+    fn do_ramp_amplification(
+        hash: T::Hash,
+        future_a: u128,
+        future_block: T::BlockNumber,
+    ) -> dispatch::DispatchResult {
+        let tp = Self::trade_pair(hash).ok_or(Error::<T>::NoMatchingTradePair)?;
+        ensure!(
+            tp.kind == PoolKind::StableSwap,
+            Error::<T>::NotStableSwapPair
+        );
+        ensure!(
+            future_a > 0 && future_a <= stable::MAX_AMPLIFICATION,
+            Error::<T>::InvalidAmplification
+        );
+
+        let now = <frame_system::Module<T>>::block_number();
+        ensure!(future_block >= now, Error::<T>::InvalidRampBlock);
+
+        let initial_a = Self::current_amplification(hash);
+        Amplifications::<T>::insert(
+            hash,
+            Amplification {
+                initial_a,
+                future_a,
+                initial_block: now,
+                future_block,
+            },
+        );
+
+        Self::deposit_event(RawEvent::AmplificationRamping(
+            hash,
+            initial_a,
+            future_a,
+            future_block,
+        ));
+
+        Ok(())
+    }
+
+    fn to_u256(amount: BalanceOf<T>) -> U256 {
+        U256::from(amount.saturated_into::<u128>())
+    }
+
+    /// `amount * numerator / denominator` in 256 bits, failing if the result is not a balance.
+    fn mul_div(
+        amount: BalanceOf<T>,
+        numerator: BalanceOf<T>,
+        denominator: BalanceOf<T>,
+    ) -> Result<BalanceOf<T>, dispatch::DispatchError> {
+        let result = Self::to_u256(amount) * Self::to_u256(numerator) / Self::to_u256(denominator);
+        ensure!(
+            result <= U256::from(u128::max_value()),
+            Error::<T>::LiquidityMathOverflow
+        );
+
+        result
+            .low_u128()
+            .try_into()
+            .map_err(|_| Error::<T>::LiquidityMathOverflow.into())
+    }
 }
@@ -0,0 +1,131 @@
+//! Storage migrations of the swap module.
+//!
+//! The release of the storage layout is kept in `StorageVersion`. On a runtime upgrade, every
+//! migration of the registry newer than that release runs in order, and the release is bumped
+//! after each one.
+
+use codec::{Decode, Encode};
+use frame_support::{
+    storage::{IterableStorageMap, StoragePrefixedMap},
+    traits::Get,
+    weights::Weight,
+    StorageMap, StorageValue,
+};
+use sp_runtime::RuntimeDebug;
+use sp_std::{cmp::Reverse, prelude::*};
+
+use crate::{
+    DelistedTradePairs, PoolKind, StorageVersion, TradePair, TradePairs, TradePairsHashByBaseQuote,
+    TradePairsHashesByBaseQuote, Trait,
+};
+
+/// Releases of the storage layout, oldest first.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, RuntimeDebug)]
+pub enum Releases {
+    /// Trade pairs without a pool kind or a fee tier, indexed by base and quote. Storage written
+    /// before the release was tracked decodes as this one.
+    V1,
+    /// Trade pairs with a pool kind and a fee tier, indexed by fee tier in both orientations.
+    V2,
+}
+
+impl Releases {
+    /// Release of the storage layout written by this code.
+    pub const LATEST: Releases = Releases::V2;
+}
+
+impl Default for Releases {
+    fn default() -> Self {
+        Releases::V1
+    }
+}
+
+/// Migrations in order, each upgrading the storage from the release before to its release.
+pub fn migrations<T: Trait>() -> Vec<(Releases, fn() -> Weight)> {
+    vec![(Releases::V2, v2::migrate::<T>)]
+}
+
+/// Run the migrations the storage is missing and return their weight.
+pub fn migrate<T: Trait>() -> Weight {
+    let mut release = StorageVersion::get();
+    let mut weight = T::DbWeight::get().reads(1);
+
+    for (next, migration) in migrations::<T>() {
+        if release < next {
+            weight = weight.saturating_add(migration());
+            release = next;
+            StorageVersion::put(release);
+            weight = weight.saturating_add(T::DbWeight::get().writes(1));
+        }
+    }
+
+    weight
+}
+
+/// Storage layout of release V1.
+pub mod v1 {
+    use codec::{Decode, Encode};
+
+    use crate::{BalanceOf, Trait};
+
+    #[derive(Encode, Decode, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(Debug))]
+    pub struct TradePair<T>
+    where
+        T: Trait,
+    {
+        pub tp_hash: T::Hash,
+        pub base: T::Hash,
+        pub quote: T::Hash,
+        pub liquidity_token_hash: T::Hash,
+        pub liquidity_token_issued_amount: BalanceOf<T>,
+        pub account: T::AccountId,
+    }
+}
+
+/// Release V2: give the pairs of release V1 a pool kind and a fee tier, and index them again.
+pub mod v2 {
+    use super::*;
+
+    /// Pairs of release V1 were constant product pairs charging no fee, so they become pairs of
+    /// the default fee tier. A pair and the pair of the same tokens in the other orientation
+    /// used to coexist, while a fee tier holds one pair of two tokens whatever the orientation:
+    /// the pair with the most liquidity is indexed in both orientations, and the other one is
+    /// delisted so that its providers can only remove their liquidity.
+    pub fn migrate<T: Trait>() -> Weight {
+        let fee_tier = T::DefaultFeeTier::get();
+        TradePairs::<T>::translate::<v1::TradePair<T>, _>(|_, tp| {
+            Some(TradePair {
+                tp_hash: tp.tp_hash,
+                base: tp.base,
+                quote: tp.quote,
+                liquidity_token_hash: tp.liquidity_token_hash,
+                liquidity_token_issued_amount: tp.liquidity_token_issued_amount,
+                account: tp.account,
+                kind: PoolKind::ConstantProduct,
+                fee_tier,
+            })
+        });
+
+        // the V1 index is keyed by (base, quote) only
+        TradePairsHashByBaseQuote::<T>::remove_all();
+
+        // the sort is stable, so pairs with as much liquidity keep their storage order
+        let mut pairs: Vec<TradePair<T>> = TradePairs::<T>::iter().map(|(_, tp)| tp).collect();
+        pairs.sort_by_key(|tp| Reverse(tp.liquidity_token_issued_amount));
+        for tp in pairs.iter() {
+            if TradePairsHashByBaseQuote::<T>::contains_key((tp.base, tp.quote, fee_tier)) {
+                DelistedTradePairs::<T>::insert(tp.tp_hash, true);
+                continue;
+            }
+
+            TradePairsHashByBaseQuote::<T>::insert((tp.base, tp.quote, fee_tier), tp.tp_hash);
+            TradePairsHashByBaseQuote::<T>::insert((tp.quote, tp.base, fee_tier), tp.tp_hash);
+            TradePairsHashesByBaseQuote::<T>::append((tp.base, tp.quote), tp.tp_hash);
+            TradePairsHashesByBaseQuote::<T>::append((tp.quote, tp.base), tp.tp_hash);
+        }
+
+        let count = pairs.len() as Weight;
+        T::DbWeight::get().reads_writes(3 * count + 1, 5 * count + 1)
+    }
+}
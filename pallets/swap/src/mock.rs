@@ -1,10 +1,17 @@
-use crate::{Module, Trait};
-use frame_support::{impl_outer_origin, parameter_types, weights::Weight};
+use crate as swap;
+use crate::{DispatchCall, FlashSwapHandler, ListingPolicy, Module, Trait};
+use codec::Encode;
+use frame_support::{
+	dispatch, impl_outer_dispatch, impl_outer_event, impl_outer_origin, parameter_types,
+	traits::Get, weights::Weight, Blake2_128Concat, StorageHasher,
+};
 use sp_core::H256;
+use sp_io::hashing::twox_128;
+use std::cell::RefCell;
 use sp_runtime::{
 	testing::Header,
-	traits::{BlakeTwo256, IdentityLookup},
-	Perbill,
+	traits::{BlakeTwo256, ConvertInto, IdentityLookup},
+	ModuleId, Perbill, Permill,
 };
 
 use frame_system as system;
@@ -14,9 +21,26 @@ impl_outer_origin! {
 	pub enum Origin for Test {}
 }
 
+impl_outer_dispatch! {
+	pub enum Call for Test where origin: Origin {
+		token::TokenModule,
+		swap::SwapModule,
+		system::System,
+	}
+}
+
+impl_outer_event! {
+	pub enum TestEvent for Test {
+		system<T>,
+		balances<T>,
+		token<T>,
+		swap<T>,
+	}
+}
+
 // Configure a mock runtime to test the pallet.
 
-#[derive(Clone, Eq, PartialEq)]
+#[derive(Clone, Eq, PartialEq, Debug)]
 pub struct Test;
 parameter_types! {
 	pub const BlockHashCount: u64 = 250;
@@ -28,7 +52,7 @@ parameter_types! {
 impl system::Trait for Test {
 	type BaseCallFilter = ();
 	type Origin = Origin;
-	type Call = ();
+	type Call = Call;
 	type Index = u64;
 	type BlockNumber = u64;
 	type Hash = H256;
@@ -36,7 +60,7 @@ impl system::Trait for Test {
 	type AccountId = u64;
 	type Lookup = IdentityLookup<Self::AccountId>;
 	type Header = Header;
-	type Event = ();
+	type Event = TestEvent;
 	type BlockHashCount = BlockHashCount;
 	type MaximumBlockWeight = MaximumBlockWeight;
 	type DbWeight = ();
@@ -62,19 +86,100 @@ impl balances::Trait for Test {
 	type Balance = u128;
 	type MaxLocks = MaxLocks;
 	type DustRemoval = ();
-	type Event = ();
+	type Event = TestEvent;
 	type ExistentialDeposit = ExistentialDeposit;
 	type AccountStore = system::Module<Test>;
 	type WeightInfo = ();
 }
 
+thread_local! {
+	/// (base, quote) amounts paid back to the pool by `TestFlashSwapHandler` given no data
+	pub static FLASH_SWAP_REPAYMENT: RefCell<(u128, u128)> = RefCell::new((0, 0));
+}
+
+pub struct TestFlashSwapHandler;
+impl FlashSwapHandler<u64, H256, u128> for TestFlashSwapHandler {
+	fn on_flash_swap(
+		borrower: &u64,
+		tp_hash: H256,
+		pair_account: &u64,
+		base_amount: u128,
+		quote_amount: u128,
+		data: &[u8],
+	) -> dispatch::DispatchResult {
+		if !data.is_empty() {
+			return DispatchCall::<Test>::on_flash_swap(
+				borrower,
+				tp_hash,
+				pair_account,
+				base_amount,
+				quote_amount,
+				data,
+			);
+		}
+
+		let tp = SwapModule::trade_pair(tp_hash).unwrap();
+		let (base_amount, quote_amount) = FLASH_SWAP_REPAYMENT.with(|v| *v.borrow());
+		if base_amount > 0 {
+			TokenModule::do_transfer(*borrower, *pair_account, tp.base, base_amount, None)?;
+		}
+		if quote_amount > 0 {
+			TokenModule::do_transfer(*borrower, *pair_account, tp.quote, quote_amount, None)?;
+		}
+		Ok(())
+	}
+}
+
+pub fn set_flash_swap_repayment(base_amount: u128, quote_amount: u128) {
+	FLASH_SWAP_REPAYMENT.with(|v| *v.borrow_mut() = (base_amount, quote_amount));
+}
+
+thread_local! {
+	pub static LISTING_POLICY: RefCell<ListingPolicy> = RefCell::new(ListingPolicy::Permissionless);
+}
+
+pub struct TestListingPolicy;
+impl Get<ListingPolicy> for TestListingPolicy {
+	fn get() -> ListingPolicy {
+		LISTING_POLICY.with(|v| *v.borrow())
+	}
+}
+
+pub fn set_listing_policy(policy: ListingPolicy) {
+	LISTING_POLICY.with(|v| *v.borrow_mut() = policy);
+}
+
+parameter_types! {
+	pub const FlashLoanFee: Permill = Permill::from_percent(1);
+	pub const InitialAmplification: u128 = 100;
+	pub FeeTiers: Vec<u32> = vec![0, 5, 30, 100];
+	pub const DefaultFeeTier: u32 = 30;
+}
+
 impl Trait for Test {
-	type Event = ();
+	type Event = TestEvent;
+	type Currency = token::Module<Test>;
+	type FlashSwapHandler = TestFlashSwapHandler;
+	type Call = Call;
+	type FlashLoanFee = FlashLoanFee;
+	type InitialAmplification = InitialAmplification;
+	type FeeTiers = FeeTiers;
+	type DefaultFeeTier = DefaultFeeTier;
+	type ListingPolicy = TestListingPolicy;
+	type ListingOrigin = system::EnsureRoot<u64>;
 	type Price = u128;
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub const TokenModuleId: ModuleId = ModuleId(*b"py/token");
 }
 
 impl token::Trait for Test {
-	type Event = ();
+	type Event = TestEvent;
+	type ModuleId = TokenModuleId;
+	type BlockNumberToBalance = ConvertInto;
+	type WeightInfo = ();
 }
 
 pub type SwapModule = Module<Test>;
@@ -84,8 +189,57 @@ pub type Balances = balances::Module<Test>;
 
 // Build genesis storage according to the mock runtime.
 pub fn new_test_ext() -> sp_io::TestExternalities {
-	system::GenesisConfig::default()
+	let mut storage = system::GenesisConfig::default()
+		.build_storage::<Test>()
+		.unwrap();
+	token::GenesisConfig::default()
+		.assimilate_storage::<Test>(&mut storage)
+		.unwrap();
+	swap::GenesisConfig::default()
+		.assimilate_storage::<Test>(&mut storage)
+		.unwrap();
+	storage.into()
+}
+
+/// Storage of a chain upgraded from an earlier runtime: the system genesis and the raw
+/// `snapshot` entries, without any release recorded by the pallets.
+pub fn new_test_ext_from_snapshot(snapshot: Vec<(Vec<u8>, Vec<u8>)>) -> sp_io::TestExternalities {
+	let mut storage = system::GenesisConfig::default()
 		.build_storage::<Test>()
-		.unwrap()
-		.into()
+		.unwrap();
+	storage.top.extend(snapshot);
+	storage.into()
+}
+
+/// Raw key of `key` in the `item` map of the swap module, hashed with `blake2_128_concat`.
+pub fn map_key<K: Encode>(item: &[u8], key: &K) -> Vec<u8> {
+	let mut raw = twox_128(b"TemplateModule").to_vec();
+	raw.extend_from_slice(&twox_128(item));
+	raw.extend(Blake2_128Concat::hash(&key.encode()));
+	raw
+}
+
+/// Events deposited so far by the swap module, oldest first.
+pub fn swap_events() -> Vec<swap::Event<Test>> {
+	System::events()
+		.into_iter()
+		.filter_map(|record| match record.event {
+			TestEvent::swap(event) => Some(event),
+			_ => None,
+		})
+		.collect()
+}
+
+pub fn assert_last_event<E: Into<TestEvent>>(event: E) {
+	let record = System::events().pop().expect("an event was deposited");
+	assert_eq!(record.event, event.into());
+}
+
+pub fn assert_has_event<E: Into<TestEvent>>(event: E) {
+	let event = event.into();
+	assert!(
+		System::events().iter().any(|record| record.event == event),
+		"{:?} was not deposited",
+		event
+	);
 }
@@ -0,0 +1,147 @@
+//! Curve StableSwap invariant for two-token pools.
+//!
+//! With `Ann = A * n^n`, reserves `x_i` summing to `S` and `n = 2`, the invariant `D` satisfies
+//! `Ann * S + D = Ann * D + D^(n + 1) / (n^n * prod(x_i))`.
+
+use codec::{Decode, Encode};
+use sp_core::U256;
+use sp_runtime::{
+    traits::{AtLeast32BitUnsigned, Saturating},
+    RuntimeDebug, SaturatedConversion,
+};
+
+const N_COINS: u64 = 2;
+const MAX_ITERATIONS: usize = 255;
+
+/// Upper bound of the amplification coefficient.
+pub const MAX_AMPLIFICATION: u128 = 1_000_000;
+
+/// Amplification coefficient of a StableSwap pair, moving linearly from `initial_a` at
+/// `initial_block` to `future_a` at `future_block`.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub struct Amplification<BlockNumber> {
+    pub initial_a: u128,
+    pub future_a: u128,
+    pub initial_block: BlockNumber,
+    pub future_block: BlockNumber,
+}
+
+impl<BlockNumber: AtLeast32BitUnsigned + Copy> Amplification<BlockNumber> {
+    /// Amplification coefficient at block `now`.
+    pub fn at(&self, now: BlockNumber) -> u128 {
+        if now >= self.future_block {
+            return self.future_a;
+        }
+
+        let elapsed: u128 = now.saturating_sub(self.initial_block).saturated_into();
+        let duration: u128 = (self.future_block - self.initial_block).saturated_into();
+
+        if self.future_a > self.initial_a {
+            self.initial_a + (self.future_a - self.initial_a) * elapsed / duration
+        } else {
+            self.initial_a - (self.initial_a - self.future_a) * elapsed / duration
+        }
+    }
+}
+
+fn abs_diff(a: U256, b: U256) -> U256 {
+    if a > b {
+        a - b
+    } else {
+        b - a
+    }
+}
+
+/// The invariant `D` of reserves `x` and `y`, or `None` if it can't be computed.
+pub fn get_d(x: u128, y: u128, amp: u128) -> Option<U256> {
+    let n = U256::from(N_COINS);
+    let x = U256::from(x);
+    let y = U256::from(y);
+    let s = x + y;
+    if s.is_zero() {
+        return Some(U256::zero());
+    }
+
+    let ann = U256::from(amp).checked_mul(n * n)?;
+    let mut d = s;
+    for _ in 0..MAX_ITERATIONS {
+        // d_p = D^3 / (n^n * x * y)
+        let d_p = d
+            .checked_mul(d)?
+            .checked_div(x.checked_mul(n)?)?
+            .checked_mul(d)?
+            .checked_div(y.checked_mul(n)?)?;
+        let d_prev = d;
+
+        let numerator = ann
+            .checked_mul(s)?
+            .checked_add(d_p.checked_mul(n)?)?
+            .checked_mul(d)?;
+        let denominator = ann
+            .checked_sub(U256::one())?
+            .checked_mul(d)?
+            .checked_add((n + U256::one()).checked_mul(d_p)?)?;
+        d = numerator.checked_div(denominator)?;
+
+        if abs_diff(d, d_prev) <= U256::one() {
+            return Some(d);
+        }
+    }
+
+    None
+}
+
+/// The reserve of the other token keeping the invariant `d` once one reserve becomes `x`.
+pub fn get_y(x: u128, d: U256, amp: u128) -> Option<U256> {
+    let n = U256::from(N_COINS);
+    let x = U256::from(x);
+
+    let ann = U256::from(amp).checked_mul(n * n)?;
+    // c = D^3 / (n^n * x * Ann)
+    let c = d
+        .checked_mul(d)?
+        .checked_div(x.checked_mul(n)?)?
+        .checked_mul(d)?
+        .checked_div(ann.checked_mul(n)?)?;
+    let b = x.checked_add(d.checked_div(ann)?)?;
+
+    let mut y = d;
+    for _ in 0..MAX_ITERATIONS {
+        let y_prev = y;
+
+        // y = (y^2 + c) / (2 * y + b - D)
+        let numerator = y.checked_mul(y)?.checked_add(c)?;
+        let denominator = y
+            .checked_mul(U256::from(2u64))?
+            .checked_add(b)?
+            .checked_sub(d)?;
+        y = numerator.checked_div(denominator)?;
+
+        if abs_diff(y, y_prev) <= U256::one() {
+            return Some(y);
+        }
+    }
+
+    None
+}
+
+/// The amount of the other token sent out for `amount_in` added to `reserve_in`, rounded down
+/// in favour of the pool.
+pub fn get_amount_out(
+    amp: u128,
+    reserve_in: u128,
+    reserve_out: u128,
+    amount_in: u128,
+) -> Option<u128> {
+    let d = get_d(reserve_in, reserve_out, amp)?;
+    let y = get_y(reserve_in.checked_add(amount_in)?, d, amp)?;
+
+    let amount_out = U256::from(reserve_out)
+        .checked_sub(y)?
+        .checked_sub(U256::one())?;
+    if amount_out > U256::from(u128::max_value()) {
+        return None;
+    }
+
+    Some(amount_out.low_u128())
+}
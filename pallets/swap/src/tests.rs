@@ -1,8 +1,16 @@
-use crate::mock::*;
+use crate::{
+    concentrated::{MAX_INITIALIZED_TICKS, MAX_TICKS_CROSSED},
+    migrations,
+    mock::*,
+    Error, ListingPolicy, PoolKind, RawEvent, Releases,
+};
+use codec::Encode;
 use frame_support::{
-    assert_ok,
-    traits::{OnFinalize, OnInitialize},
+    assert_err, assert_noop, assert_ok,
+    traits::{Currency, OnFinalize, OnInitialize, OnRuntimeUpgrade},
 };
+use sp_core::H256;
+use sp_runtime::Permill;
 use token::*;
 
 fn run_to_block(n: u64) {
@@ -83,10 +91,12 @@ fn swap_tests() {
         assert_ok!(SwapModule::do_create_trade_pair(
             alice,
             token1.token_hash,
-            token2.token_hash
+            token2.token_hash,
+            PoolKind::ConstantProduct,
+            0
         ));
         let tp_hash =
-            SwapModule::trade_pair_hash_by_base_quote((token1.token_hash, token2.token_hash))
+            SwapModule::trade_pair_hash_by_base_quote((token1.token_hash, token2.token_hash, 0))
                 .unwrap();
         let mut tp = SwapModule::trade_pair(tp_hash).unwrap();
 
@@ -306,3 +316,2933 @@ fn swap_tests() {
         assert_eq!(TokenModule::balance_of((tp.account, token2.token_hash)), 0);
     });
 }
+
+#[test]
+fn wrapped_native_trade_pair_tests() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        let alice = 10u64;
+        let _ = Balances::deposit_creating(&alice, 100000);
+
+        let token_hash = issue_token(b"8888", 10000000);
+
+        assert_ok!(TokenModule::do_wrap(alice, 1000));
+        let native_hash = TokenModule::wrapped_native_token_hash().unwrap();
+
+        let tp_hash = create_pair_of(native_hash, token_hash, PoolKind::ConstantProduct, 0);
+        let tp = SwapModule::trade_pair(tp_hash).unwrap();
+
+        assert_ok!(SwapModule::do_add_liquidity(
+            alice,
+            tp.tp_hash,
+            1000,
+            Some(1000 * 300)
+        ));
+        assert_eq!(TokenModule::balance_of((tp.account, native_hash)), 1000);
+        assert_eq!(TokenModule::balance_of((alice, native_hash)), 0);
+        assert_eq!(Balances::free_balance(&alice), 99000);
+        assert_eq!(Balances::free_balance(&TokenModule::account_id()), 1000);
+    });
+}
+
+#[test]
+fn paused_token_tests() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        let alice = 10u64;
+
+        let token1_hash = issue_token(b"6666", 21000000);
+        let token2_hash = issue_token(b"8888", 10000000);
+
+        let tp_hash = create_liquid_pair_of(
+            token1_hash,
+            token2_hash,
+            PoolKind::ConstantProduct,
+            0,
+            100,
+            100 * 300,
+        );
+
+        assert_ok!(TokenModule::pause_token(Origin::signed(alice), token2_hash));
+        assert_err!(
+            SwapModule::do_swap_buy(alice, tp_hash, 13),
+            Error::<Test>::TokenPaused
+        );
+        assert_err!(
+            SwapModule::do_swap_sell(alice, tp_hash, 539),
+            Error::<Test>::TokenPaused
+        );
+
+        assert_ok!(TokenModule::unpause_token(
+            Origin::signed(alice),
+            token2_hash
+        ));
+        assert_ok!(SwapModule::do_swap_buy(alice, tp_hash, 13));
+    });
+}
+
+#[test]
+fn flash_swap_tests() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        let alice = 10u64;
+
+        let token1_hash = issue_token(b"6666", 21000000);
+        let token2_hash = issue_token(b"8888", 10000000);
+
+        let tp_hash = create_liquid_pair_of(
+            token1_hash,
+            token2_hash,
+            PoolKind::ConstantProduct,
+            30,
+            1000,
+            1000 * 300,
+        );
+        let tp = SwapModule::trade_pair(tp_hash).unwrap();
+
+        assert_err!(
+            SwapModule::flash_swap(Origin::signed(alice), tp_hash, 0, 0, vec![]),
+            Error::<Test>::FlashSwapAmountIsZero
+        );
+        assert_err!(
+            SwapModule::flash_swap(Origin::signed(alice), tp_hash, 1000, 0, vec![]),
+            Error::<Test>::InsufficientLiquidity
+        );
+
+        // paying back the borrowed amount without fee is not enough
+        set_flash_swap_repayment(100, 0);
+        assert_err!(
+            SwapModule::flash_swap(Origin::signed(alice), tp_hash, 100, 0, vec![]),
+            Error::<Test>::FlashSwapNotRepaid
+        );
+        // nothing was left half-applied
+        assert_eq!(TokenModule::balance_of((tp.account, token1_hash)), 1000);
+        assert_eq!(TokenModule::balance_of((alice, token1_hash)), 20999000);
+
+        // paying with the other token has to restore k plus fee
+        set_flash_swap_repayment(0, 33433);
+        assert_err!(
+            SwapModule::flash_swap(Origin::signed(alice), tp_hash, 100, 0, vec![]),
+            Error::<Test>::FlashSwapNotRepaid
+        );
+        assert_eq!(TokenModule::balance_of((tp.account, token1_hash)), 1000);
+        assert_eq!(TokenModule::balance_of((tp.account, token2_hash)), 300000);
+
+        set_flash_swap_repayment(0, 33434);
+        assert_ok!(SwapModule::flash_swap(
+            Origin::signed(alice),
+            tp_hash,
+            100,
+            0,
+            vec![]
+        ));
+        assert_eq!(TokenModule::balance_of((tp.account, token1_hash)), 900);
+        assert_eq!(TokenModule::balance_of((tp.account, token2_hash)), 333434);
+        assert_eq!(TokenModule::balance_of((alice, token1_hash)), 20999100);
+        assert_eq!(TokenModule::balance_of((alice, token2_hash)), 9666566);
+
+        // borrowing and paying back the same token with fee
+        set_flash_swap_repayment(91, 0);
+        assert_ok!(SwapModule::flash_swap(
+            Origin::signed(alice),
+            tp_hash,
+            90,
+            0,
+            vec![]
+        ));
+        assert_eq!(TokenModule::balance_of((tp.account, token1_hash)), 901);
+    });
+}
+
+#[test]
+fn flash_swap_with_large_reserves() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        let alice = 10u64;
+        let reserve = 1u128 << 115;
+
+        let token1_hash = issue_token(b"6666", 1 << 120);
+        let token2_hash = issue_token(b"8888", 1 << 120);
+        let tp_hash = create_liquid_pair_of(
+            token1_hash,
+            token2_hash,
+            PoolKind::ConstantProduct,
+            30,
+            reserve,
+            reserve,
+        );
+        let tp = SwapModule::trade_pair(tp_hash).unwrap();
+
+        // the products of the reserves scaled by the fee denominator exceed 256 bits
+        set_flash_swap_repayment(1003, 0);
+        assert_err!(
+            SwapModule::flash_swap(Origin::signed(alice), tp_hash, 1000, 0, vec![]),
+            Error::<Test>::FlashSwapNotRepaid
+        );
+        set_flash_swap_repayment(1004, 0);
+        assert_ok!(SwapModule::flash_swap(
+            Origin::signed(alice),
+            tp_hash,
+            1000,
+            0,
+            vec![]
+        ));
+        assert_eq!(
+            TokenModule::balance_of((tp.account, token1_hash)),
+            reserve + 4
+        );
+    });
+}
+
+#[test]
+fn flash_swap_dispatches_borrower_call() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        let alice = 10u64;
+
+        let token1_hash = issue_token(b"6666", 100000);
+        let token2_hash = issue_token(b"8888", 100000);
+        let tp_hash = create_liquid_pair_of(
+            token1_hash,
+            token2_hash,
+            PoolKind::ConstantProduct,
+            30,
+            10000,
+            10000,
+        );
+        let tp = SwapModule::trade_pair(tp_hash).unwrap();
+
+        assert_noop!(
+            SwapModule::flash_swap(Origin::signed(alice), tp_hash, 1000, 0, vec![0xff]),
+            Error::<Test>::InvalidFlashSwapCall
+        );
+
+        // the pair is locked while its reserves are out
+        let add_liquidity = Call::SwapModule(crate::Call::add_liquidity(tp_hash, 1100, None));
+        assert_noop!(
+            SwapModule::flash_swap(
+                Origin::signed(alice),
+                tp_hash,
+                1000,
+                0,
+                add_liquidity.encode()
+            ),
+            Error::<Test>::TradePairLocked
+        );
+
+        let repay = Call::TokenModule(token::Call::transfer(token1_hash, tp.account, 1004, None));
+        assert_ok!(SwapModule::flash_swap(
+            Origin::signed(alice),
+            tp_hash,
+            1000,
+            0,
+            repay.encode()
+        ));
+        assert_eq!(TokenModule::balance_of((tp.account, token1_hash)), 10004);
+        assert!(!SwapModule::is_locked(tp_hash));
+    });
+}
+
+#[test]
+fn flash_swap_trips_circuit_breaker() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        let alice = 10u64;
+
+        let token1_hash = issue_token(b"6666", 100000);
+        let token2_hash = issue_token(b"8888", 100000);
+        let tp_hash = create_liquid_pair_of(
+            token1_hash,
+            token2_hash,
+            PoolKind::ConstantProduct,
+            30,
+            10000,
+            10000,
+        );
+        assert_ok!(SwapModule::set_circuit_breaker(
+            Origin::root(),
+            tp_hash,
+            Some(Permill::from_percent(10))
+        ));
+
+        // paying 2000 base back in quote leaves (8000, 13000), a 62% price move
+        set_flash_swap_repayment(0, 3000);
+        assert_noop!(
+            SwapModule::flash_swap(Origin::signed(alice), tp_hash, 2000, 0, vec![]),
+            Error::<Test>::PriceMoveTooLarge
+        );
+
+        set_flash_swap_repayment(101, 0);
+        assert_ok!(SwapModule::flash_swap(
+            Origin::signed(alice),
+            tp_hash,
+            100,
+            0,
+            vec![]
+        ));
+    });
+}
+
+#[test]
+fn flash_loan_tests() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        let alice = 10u64;
+
+        let token1_hash = issue_token(b"6666", 21000000);
+        let token2_hash = issue_token(b"8888", 10000000);
+        let token3_hash = issue_token(b"9999", 10000000);
+
+        let tp_hash = create_liquid_pair_of(
+            token1_hash,
+            token2_hash,
+            PoolKind::ConstantProduct,
+            0,
+            1000,
+            1000 * 300,
+        );
+        let tp = SwapModule::trade_pair(tp_hash).unwrap();
+
+        let repay = |amount: u128| {
+            Box::new(Call::TokenModule(token::Call::transfer(
+                token1_hash,
+                tp.account,
+                amount,
+                None,
+            )))
+        };
+
+        assert_err!(
+            SwapModule::flash_loan(Origin::signed(alice), tp_hash, token3_hash, 500, repay(505)),
+            Error::<Test>::TokenNotInTradePair
+        );
+        assert_err!(
+            SwapModule::flash_loan(Origin::signed(alice), tp_hash, token1_hash, 0, repay(505)),
+            Error::<Test>::FlashLoanAmountIsZero
+        );
+        assert_err!(
+            SwapModule::flash_loan(
+                Origin::signed(alice),
+                tp_hash,
+                token1_hash,
+                1000,
+                repay(1010)
+            ),
+            Error::<Test>::InsufficientLiquidity
+        );
+
+        // 1% fee is missing
+        assert_err!(
+            SwapModule::flash_loan(Origin::signed(alice), tp_hash, token1_hash, 500, repay(504)),
+            Error::<Test>::FlashLoanNotRepaid
+        );
+        assert_eq!(TokenModule::balance_of((tp.account, token1_hash)), 1000);
+        assert_eq!(TokenModule::balance_of((alice, token1_hash)), 20999000);
+
+        // a failing inner call reverts the loan
+        assert_err!(
+            SwapModule::flash_loan(
+                Origin::signed(alice),
+                tp_hash,
+                token1_hash,
+                500,
+                repay(30000000)
+            ),
+            token::Error::<Test>::BalanceNotEnough
+        );
+        assert_eq!(TokenModule::balance_of((tp.account, token1_hash)), 1000);
+        assert_eq!(TokenModule::balance_of((alice, token1_hash)), 20999000);
+
+        assert_ok!(SwapModule::flash_loan(
+            Origin::signed(alice),
+            tp_hash,
+            token1_hash,
+            500,
+            repay(505)
+        ));
+        assert_eq!(TokenModule::balance_of((tp.account, token1_hash)), 1005);
+        assert_eq!(TokenModule::balance_of((alice, token1_hash)), 20998995);
+
+        // the fee stays in the pool for the liquidity providers
+        assert_ok!(SwapModule::do_remove_liquidity(alice, tp_hash, 1000));
+        assert_eq!(TokenModule::balance_of((alice, token1_hash)), 21000000);
+    });
+}
+
+#[test]
+fn flash_loan_cannot_reenter_the_pair() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        let alice = 10u64;
+        let bob = 20u64;
+
+        let token1_hash = issue_token(b"6666", 100000);
+        let token2_hash = issue_token(b"8888", 100000);
+        assert_ok!(TokenModule::do_transfer(
+            alice,
+            bob,
+            token1_hash,
+            1000,
+            None
+        ));
+        assert_ok!(TokenModule::do_transfer(
+            alice,
+            bob,
+            token2_hash,
+            10000,
+            None
+        ));
+        let tp_hash = create_liquid_pair_of(
+            token1_hash,
+            token2_hash,
+            PoolKind::ConstantProduct,
+            0,
+            1000,
+            1000,
+        );
+
+        // borrowing 900 base leaves (100, 1000), where adding 910 base mints 9100 liquidity
+        // tokens and pays the loan back with its fee at the same time
+        let add_liquidity = Box::new(Call::SwapModule(crate::Call::add_liquidity(
+            tp_hash, 910, None,
+        )));
+        assert_noop!(
+            SwapModule::flash_loan(
+                Origin::signed(bob),
+                tp_hash,
+                token1_hash,
+                900,
+                add_liquidity
+            ),
+            Error::<Test>::TradePairLocked
+        );
+
+        for call in vec![
+            crate::Call::remove_liquidity(tp_hash, 1),
+            crate::Call::swap_buy(tp_hash, 10),
+            crate::Call::swap(tp_hash, token2_hash, 10),
+            crate::Call::flash_swap(tp_hash, 10, 0, vec![]),
+            crate::Call::flash_loan(
+                tp_hash,
+                token2_hash,
+                10,
+                Box::new(Call::SwapModule(crate::Call::swap_sell(tp_hash, 10))),
+            ),
+        ] {
+            assert_noop!(
+                SwapModule::flash_loan(
+                    Origin::signed(bob),
+                    tp_hash,
+                    token1_hash,
+                    900,
+                    Box::new(Call::SwapModule(call))
+                ),
+                Error::<Test>::TradePairLocked
+            );
+        }
+        assert!(!SwapModule::is_locked(tp_hash));
+    });
+}
+
+#[test]
+fn positions_cannot_change_while_pair_is_locked() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (base, _, tp_hash) = create_concentrated_position();
+
+        for call in vec![
+            crate::Call::decrease_position(0, 1),
+            crate::Call::collect_position(0),
+        ] {
+            assert_noop!(
+                SwapModule::flash_loan(
+                    Origin::signed(10),
+                    tp_hash,
+                    base,
+                    1000,
+                    Box::new(Call::SwapModule(call))
+                ),
+                Error::<Test>::TradePairLocked
+            );
+        }
+    });
+}
+
+#[test]
+fn flash_loan_cannot_mint_position_with_loaned_tokens() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (base, _, tp_hash) = create_concentrated_position();
+
+        // minting sends the loaned base back to the pair, which would pass for repaying the
+        // loan while bob keeps the position
+        let mint_position = Box::new(Call::SwapModule(crate::Call::mint_position(
+            tp_hash, -1000, 1000, 1000000000,
+        )));
+        assert_noop!(
+            SwapModule::flash_loan(Origin::signed(20), tp_hash, base, 40000000, mint_position),
+            Error::<Test>::TradePairLocked
+        );
+        assert!(SwapModule::position(1).is_none());
+    });
+}
+
+#[test]
+fn stable_swap_tests() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        let alice = 10u64;
+        let bob = 20u64;
+
+        let token1_hash = issue_token(b"USDA", 10000000);
+        let token2_hash = issue_token(b"USDB", 10000000);
+
+        let tp_hash = create_pair_of(token1_hash, token2_hash, PoolKind::StableSwap, 0);
+        let tp = SwapModule::trade_pair(tp_hash).unwrap();
+        assert_eq!(tp.kind, PoolKind::StableSwap);
+        assert_eq!(SwapModule::current_amplification(tp_hash), 100);
+
+        assert_ok!(SwapModule::do_add_liquidity(
+            alice,
+            tp_hash,
+            1000000,
+            Some(1000000)
+        ));
+        assert_eq!(
+            TokenModule::balance_of((alice, tp.liquidity_token_hash)),
+            1000000
+        );
+
+        // a constant product pool would give 9900
+        assert_ok!(SwapModule::do_swap_buy(alice, tp_hash, 10000));
+        assert_eq!(TokenModule::balance_of((tp.account, token1_hash)), 1010000);
+        assert_eq!(TokenModule::balance_of((tp.account, token2_hash)), 990001);
+        assert_eq!(TokenModule::balance_of((alice, token2_hash)), 9009999);
+
+        assert_err!(
+            SwapModule::ramp_amplification(Origin::signed(alice), tp_hash, 1, 11),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        assert_err!(
+            SwapModule::ramp_amplification(Origin::root(), tp_hash, 0, 11),
+            Error::<Test>::InvalidAmplification
+        );
+        assert_err!(
+            SwapModule::ramp_amplification(Origin::root(), tp_hash, 1, 0),
+            Error::<Test>::InvalidRampBlock
+        );
+
+        assert_ok!(SwapModule::ramp_amplification(
+            Origin::root(),
+            tp_hash,
+            1,
+            11
+        ));
+        run_to_block(6);
+        assert_eq!(SwapModule::current_amplification(tp_hash), 51);
+        run_to_block(11);
+        assert_eq!(SwapModule::current_amplification(tp_hash), 1);
+
+        assert_ok!(TokenModule::do_transfer(
+            alice,
+            bob,
+            token2_hash,
+            10000,
+            None
+        ));
+        assert_ok!(SwapModule::do_swap_sell(bob, tp_hash, 10000));
+        assert_eq!(TokenModule::balance_of((bob, token1_hash)), 10033);
+        assert_eq!(TokenModule::balance_of((bob, token2_hash)), 0);
+        assert_eq!(TokenModule::balance_of((tp.account, token1_hash)), 999967);
+        assert_eq!(TokenModule::balance_of((tp.account, token2_hash)), 1000001);
+
+        // constant product pairs have no amplification to ramp
+        let token3_hash = issue_token(b"8888", 10000000);
+        let cp_hash = create_pair_of(token1_hash, token3_hash, PoolKind::ConstantProduct, 0);
+        assert_err!(
+            SwapModule::ramp_amplification(Origin::root(), cp_hash, 1, 20),
+            Error::<Test>::NotStableSwapPair
+        );
+    });
+}
+
+#[test]
+fn concentrated_liquidity_tests() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        let alice = 10u64;
+        let bob = 20u64;
+
+        let (token1_hash, token2_hash, tp_hash) = create_pair(PoolKind::Concentrated, 30);
+        let tp = SwapModule::trade_pair(tp_hash).unwrap();
+
+        assert_err!(
+            SwapModule::do_add_liquidity(alice, tp_hash, 1000, Some(1000)),
+            Error::<Test>::UnsupportedPoolKind
+        );
+        assert_err!(
+            SwapModule::mint_position(Origin::signed(alice), tp_hash, -1000, 1000, 1000000000),
+            Error::<Test>::PoolNotInitialized
+        );
+
+        // price 1
+        assert_ok!(SwapModule::initialize_concentrated_pool(
+            Origin::signed(alice),
+            tp_hash,
+            1 << 64
+        ));
+        assert_err!(
+            SwapModule::initialize_concentrated_pool(Origin::signed(alice), tp_hash, 1 << 64),
+            Error::<Test>::PoolAlreadyInitialized
+        );
+        assert_eq!(SwapModule::concentrated_pool(tp_hash).unwrap().tick, 0);
+
+        assert_err!(
+            SwapModule::mint_position(Origin::signed(alice), tp_hash, 60, -60, 1000000000),
+            Error::<Test>::InvalidTickRange
+        );
+        assert_err!(
+            SwapModule::mint_position(Origin::signed(alice), tp_hash, -60, 60, 0),
+            Error::<Test>::LiquidityIsZero
+        );
+
+        assert_ok!(SwapModule::mint_position(
+            Origin::signed(alice),
+            tp_hash,
+            -1000,
+            1000,
+            1000000000
+        ));
+        assert_eq!(TokenModule::balance_of((tp.account, token1_hash)), 48768198);
+        assert_eq!(TokenModule::balance_of((tp.account, token2_hash)), 48768198);
+
+        assert_ok!(SwapModule::mint_position(
+            Origin::signed(bob),
+            tp_hash,
+            -60,
+            60,
+            1000000000
+        ));
+        assert_eq!(TokenModule::balance_of((bob, token1_hash)), 97004645);
+        assert_eq!(TokenModule::balance_of((bob, token2_hash)), 97004645);
+        assert_eq!(
+            SwapModule::initialized_ticks(tp_hash),
+            vec![-1000, -60, 60, 1000]
+        );
+        assert_eq!(
+            SwapModule::concentrated_pool(tp_hash).unwrap().liquidity,
+            2000000000
+        );
+        assert_eq!(SwapModule::position(1).unwrap().owner, bob);
+
+        // crosses the lower tick of bob's position
+        assert_ok!(SwapModule::do_swap_buy(alice, tp_hash, 10000000));
+        assert_eq!(
+            TokenModule::balance_of((alice, token2_hash)),
+            9900000000 - 48768198 + 9912813
+        );
+        let pool = SwapModule::concentrated_pool(tp_hash).unwrap();
+        assert_eq!(pool.tick, -139);
+        assert_eq!(pool.liquidity, 1000000000);
+
+        assert_err!(
+            SwapModule::decrease_position(Origin::signed(alice), 1, 1000000000),
+            Error::<Test>::NotPositionOwner
+        );
+        assert_err!(
+            SwapModule::decrease_position(Origin::signed(bob), 1, 1000000001),
+            Error::<Test>::PositionLiquidityTooLow
+        );
+
+        // bob's range is all base now, plus his share of the fees earned in range
+        assert_ok!(SwapModule::decrease_position(
+            Origin::signed(bob),
+            1,
+            1000000000
+        ));
+        let position = SwapModule::position(1).unwrap();
+        assert_eq!(position.liquidity, 0);
+        assert_eq!(position.tokens_owed_base, 6008748);
+        assert_eq!(position.tokens_owed_quote, 0);
+        assert_eq!(SwapModule::initialized_ticks(tp_hash), vec![-1000, 1000]);
+
+        assert_ok!(SwapModule::collect_position(Origin::signed(bob), 1));
+        assert_eq!(
+            TokenModule::balance_of((bob, token1_hash)),
+            97004645 + 6008748
+        );
+        assert_eq!(TokenModule::balance_of((bob, token2_hash)), 97004645);
+        assert_eq!(SwapModule::position(1), None);
+
+        // alice keeps her liquidity and collects the rest of the fees
+        assert_ok!(SwapModule::collect_position(Origin::signed(alice), 0));
+        assert_eq!(
+            TokenModule::balance_of((alice, token1_hash)),
+            9900000000 - 48768198 - 10000000 + 20960
+        );
+        assert_eq!(SwapModule::position(0).unwrap().tokens_owed_base, 0);
+        assert_eq!(SwapModule::position(0).unwrap().liquidity, 1000000000);
+    });
+}
+
+#[test]
+fn weighted_pool_tests() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        let alice = 10u64;
+        let bob = 20u64;
+
+        let tokens = issue_tokens(3);
+        let (a, b, c) = (tokens[0], tokens[1], tokens[2]);
+
+        assert_err!(
+            SwapModule::create_weighted_pool(
+                Origin::signed(alice),
+                vec![a],
+                vec![1],
+                vec![1000000]
+            ),
+            Error::<Test>::InvalidWeightedPoolTokens
+        );
+        assert_err!(
+            SwapModule::create_weighted_pool(
+                Origin::signed(alice),
+                vec![a, b, a],
+                vec![2, 1, 1],
+                vec![1000000, 500000, 2000000]
+            ),
+            Error::<Test>::InvalidWeightedPoolTokens
+        );
+        assert_err!(
+            SwapModule::create_weighted_pool(
+                Origin::signed(alice),
+                vec![a, b, c],
+                vec![2, 0, 1],
+                vec![1000000, 500000, 2000000]
+            ),
+            Error::<Test>::InvalidWeights
+        );
+        assert_err!(
+            SwapModule::create_weighted_pool(
+                Origin::signed(alice),
+                vec![a, b, c],
+                vec![2, 1, 1],
+                vec![1000000, 500000]
+            ),
+            Error::<Test>::AmountsLengthMismatch
+        );
+
+        // 50% a, 25% b, 25% c
+        assert_ok!(SwapModule::create_weighted_pool(
+            Origin::signed(alice),
+            vec![a, b, c],
+            vec![2, 1, 1],
+            vec![1000000, 500000, 2000000]
+        ));
+        let pool_hash = SwapModule::weighted_pool_hash_by_index(0).unwrap();
+        let pool = SwapModule::weighted_pool(pool_hash).unwrap();
+        assert_eq!(
+            TokenModule::balance_of((alice, pool.pool_token_hash)),
+            1000000000
+        );
+        assert_eq!(TokenModule::balance_of((pool.account, b)), 500000);
+
+        // spot price of a in b is 1
+        assert_err!(
+            SwapModule::swap_weighted(Origin::signed(bob), pool_hash, a, 10000, b, 9824),
+            Error::<Test>::SlippageLimitExceeded
+        );
+        assert_err!(
+            SwapModule::swap_weighted(Origin::signed(bob), pool_hash, a, 500001, b, 0),
+            Error::<Test>::TradeTooLarge
+        );
+        assert_ok!(SwapModule::swap_weighted(
+            Origin::signed(bob),
+            pool_hash,
+            a,
+            10000,
+            b,
+            9823
+        ));
+        assert_eq!(TokenModule::balance_of((bob, a)), 99990000);
+        assert_eq!(TokenModule::balance_of((bob, b)), 100009823);
+
+        // 1% of the pool
+        assert_err!(
+            SwapModule::join_pool(
+                Origin::signed(bob),
+                pool_hash,
+                10000000,
+                vec![10100, 4901, 20000]
+            ),
+            Error::<Test>::SlippageLimitExceeded
+        );
+        assert_ok!(SwapModule::join_pool(
+            Origin::signed(bob),
+            pool_hash,
+            10000000,
+            vec![10100, 4902, 20000]
+        ));
+        assert_eq!(TokenModule::balance_of((bob, a)), 99979900);
+        assert_eq!(TokenModule::balance_of((bob, b)), 100004921);
+        assert_eq!(TokenModule::balance_of((bob, c)), 99980000);
+        assert_eq!(
+            TokenModule::balance_of((bob, pool.pool_token_hash)),
+            10000000
+        );
+
+        assert_ok!(SwapModule::join_pool_single(
+            Origin::signed(bob),
+            pool_hash,
+            c,
+            100000,
+            12247302
+        ));
+        assert_eq!(TokenModule::balance_of((bob, c)), 99880000);
+        assert_eq!(
+            TokenModule::balance_of((bob, pool.pool_token_hash)),
+            22247302
+        );
+
+        assert_ok!(SwapModule::exit_pool_single(
+            Origin::signed(bob),
+            pool_hash,
+            a,
+            10000000,
+            19830
+        ));
+        assert_eq!(TokenModule::balance_of((bob, a)), 99999730);
+
+        assert_ok!(SwapModule::exit_pool(
+            Origin::signed(bob),
+            pool_hash,
+            12247302,
+            vec![12102, 5990, 25650]
+        ));
+        assert_eq!(TokenModule::balance_of((bob, a)), 100011832);
+        assert_eq!(TokenModule::balance_of((bob, b)), 100010911);
+        assert_eq!(TokenModule::balance_of((bob, c)), 99905650);
+        assert_eq!(TokenModule::balance_of((bob, pool.pool_token_hash)), 0);
+        assert_eq!(
+            SwapModule::weighted_pool(pool_hash)
+                .unwrap()
+                .pool_token_issued_amount,
+            1000000000
+        );
+    });
+}
+
+#[test]
+fn fee_tier_tests() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        let alice = 10u64;
+        let bob = 20u64;
+
+        let token1_hash = issue_token(b"6666", 10000000);
+        let token2_hash = issue_token(b"8888", 10000000);
+
+        assert_err!(
+            SwapModule::create_trade_pair(
+                Origin::signed(alice),
+                token1_hash,
+                token2_hash,
+                PoolKind::ConstantProduct,
+                7
+            ),
+            Error::<Test>::InvalidFeeTier
+        );
+        assert_ok!(SwapModule::create_trade_pair(
+            Origin::signed(alice),
+            token1_hash,
+            token2_hash,
+            PoolKind::ConstantProduct,
+            5
+        ));
+        assert_err!(
+            SwapModule::create_trade_pair(
+                Origin::signed(alice),
+                token1_hash,
+                token2_hash,
+                PoolKind::ConstantProduct,
+                5
+            ),
+            Error::<Test>::TradePairExisted
+        );
+        assert_ok!(SwapModule::create_trade_pair(
+            Origin::signed(alice),
+            token1_hash,
+            token2_hash,
+            PoolKind::ConstantProduct,
+            100
+        ));
+        let cheap_hash =
+            SwapModule::trade_pair_hash_by_base_quote((token1_hash, token2_hash, 5)).unwrap();
+        let deep_hash =
+            SwapModule::trade_pair_hash_by_base_quote((token1_hash, token2_hash, 100)).unwrap();
+        assert_eq!(
+            SwapModule::trade_pair_hashes_by_base_quote((token1_hash, token2_hash)),
+            vec![cheap_hash, deep_hash]
+        );
+        let deep_tp = SwapModule::trade_pair(deep_hash).unwrap();
+
+        assert_err!(
+            SwapModule::add_liquidity_by_base_quote(
+                Origin::signed(alice),
+                token1_hash,
+                token2_hash,
+                30,
+                10000,
+                Some(10000)
+            ),
+            Error::<Test>::NoMatchingTradePair
+        );
+        assert_ok!(SwapModule::add_liquidity_by_base_quote(
+            Origin::signed(alice),
+            token1_hash,
+            token2_hash,
+            5,
+            10000,
+            Some(10000)
+        ));
+        assert_ok!(SwapModule::add_liquidity_by_base_quote(
+            Origin::signed(alice),
+            token1_hash,
+            token2_hash,
+            100,
+            100000,
+            Some(100000)
+        ));
+
+        // the 0.05% pair gives 908 and the deeper 1% pair gives 980
+        assert_eq!(
+            SwapModule::best_trade_pair(token1_hash, token2_hash, 1000),
+            Some((deep_hash, 980))
+        );
+        assert_ok!(TokenModule::do_transfer(
+            alice,
+            bob,
+            token1_hash,
+            1000,
+            None
+        ));
+        assert_ok!(SwapModule::swap_by_tokens(
+            Origin::signed(bob),
+            token1_hash,
+            token2_hash,
+            1000
+        ));
+        assert_eq!(TokenModule::balance_of((bob, token2_hash)), 980);
+        assert_eq!(
+            TokenModule::balance_of((deep_tp.account, token1_hash)),
+            101000
+        );
+        assert_eq!(
+            TokenModule::balance_of((deep_tp.account, token2_hash)),
+            99020
+        );
+
+        assert_ok!(SwapModule::swap_by_tokens(
+            Origin::signed(bob),
+            token2_hash,
+            token1_hash,
+            980
+        ));
+        assert_eq!(TokenModule::balance_of((bob, token1_hash)), 979);
+        assert_eq!(TokenModule::balance_of((bob, token2_hash)), 0);
+    });
+}
+
+#[test]
+fn canonical_ordering_tests() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        let alice = 10u64;
+        let bob = 20u64;
+
+        let token1_hash = issue_token(b"6666", 10000000);
+        let token2_hash = issue_token(b"8888", 10000000);
+
+        assert_ok!(SwapModule::create_trade_pair(
+            Origin::signed(alice),
+            token1_hash,
+            token2_hash,
+            PoolKind::ConstantProduct,
+            0
+        ));
+        assert_err!(
+            SwapModule::create_trade_pair(
+                Origin::signed(alice),
+                token2_hash,
+                token1_hash,
+                PoolKind::ConstantProduct,
+                0
+            ),
+            Error::<Test>::TradePairExisted
+        );
+        let tp_hash =
+            SwapModule::trade_pair_hash_by_base_quote((token1_hash, token2_hash, 0)).unwrap();
+        assert_eq!(
+            SwapModule::trade_pair_hash_by_base_quote((token2_hash, token1_hash, 0)),
+            Some(tp_hash)
+        );
+        let tp = SwapModule::trade_pair(tp_hash).unwrap();
+        assert_eq!(tp.base, token1_hash);
+
+        // amounts follow the orientation of the call, not of the pair
+        assert_ok!(SwapModule::add_liquidity_by_base_quote(
+            Origin::signed(alice),
+            token2_hash,
+            token1_hash,
+            0,
+            20000,
+            Some(10000)
+        ));
+        assert_eq!(TokenModule::balance_of((tp.account, token1_hash)), 10000);
+        assert_eq!(TokenModule::balance_of((tp.account, token2_hash)), 20000);
+        assert_ok!(SwapModule::add_liquidity_by_base_quote(
+            Origin::signed(alice),
+            token2_hash,
+            token1_hash,
+            0,
+            2000,
+            None
+        ));
+        assert_eq!(TokenModule::balance_of((tp.account, token1_hash)), 11000);
+        assert_eq!(TokenModule::balance_of((tp.account, token2_hash)), 22000);
+        assert_eq!(
+            TokenModule::balance_of((alice, tp.liquidity_token_hash)),
+            11000
+        );
+
+        assert_ok!(TokenModule::do_transfer(
+            alice,
+            bob,
+            token2_hash,
+            2200,
+            None
+        ));
+        let token3_hash = issue_token(b"9999", 10000000);
+        assert_err!(
+            SwapModule::swap(Origin::signed(bob), tp_hash, token3_hash, 2200),
+            Error::<Test>::TokenNotInTradePair
+        );
+
+        assert_ok!(SwapModule::swap(
+            Origin::signed(bob),
+            tp_hash,
+            token2_hash,
+            2200
+        ));
+        assert_eq!(TokenModule::balance_of((bob, token1_hash)), 1000);
+        assert_eq!(TokenModule::balance_of((bob, token2_hash)), 0);
+
+        assert_ok!(SwapModule::swap(
+            Origin::signed(bob),
+            tp_hash,
+            token1_hash,
+            1000
+        ));
+        assert_eq!(TokenModule::balance_of((bob, token1_hash)), 0);
+        assert_eq!(TokenModule::balance_of((bob, token2_hash)), 2200);
+
+        assert_ok!(SwapModule::swap_by_tokens(
+            Origin::signed(bob),
+            token2_hash,
+            token1_hash,
+            2200
+        ));
+        assert_eq!(TokenModule::balance_of((bob, token1_hash)), 1000);
+        assert_eq!(TokenModule::balance_of((bob, token2_hash)), 0);
+    });
+}
+
+#[test]
+fn listing_policy_tests() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        let alice = 10u64;
+        let bob = 20u64;
+        let charlie = 30u64;
+
+        let token1_hash = issue_token(b"6666", 10000000);
+        assert_ok!(TokenModule::do_issue(
+            bob,
+            b"8888".to_vec(),
+            10000000,
+            TokenType::Normal
+        ));
+        let token2_hash = TokenModule::token_hash_by_index(1).unwrap();
+
+        set_listing_policy(ListingPolicy::TokenOwner);
+        assert_err!(
+            SwapModule::create_trade_pair(
+                Origin::signed(charlie),
+                token1_hash,
+                token2_hash,
+                PoolKind::ConstantProduct,
+                0
+            ),
+            Error::<Test>::SenderNotEqualToBaseOrQuoteOwner
+        );
+        assert_err!(
+            SwapModule::create_trade_pair(
+                Origin::signed(alice),
+                token1_hash,
+                sp_core::H256::from_low_u64_be(3),
+                PoolKind::ConstantProduct,
+                0
+            ),
+            Error::<Test>::TokenOwnerNotFound
+        );
+        // the owner of either token can list the pair
+        assert_ok!(SwapModule::create_trade_pair(
+            Origin::signed(bob),
+            token1_hash,
+            token2_hash,
+            PoolKind::ConstantProduct,
+            0
+        ));
+
+        set_listing_policy(ListingPolicy::Governance);
+        assert_err!(
+            SwapModule::create_trade_pair(
+                Origin::signed(alice),
+                token1_hash,
+                token2_hash,
+                PoolKind::ConstantProduct,
+                30
+            ),
+            Error::<Test>::ListingRequiresGovernance
+        );
+        assert_err!(
+            SwapModule::force_create_trade_pair(
+                Origin::signed(alice),
+                alice,
+                token1_hash,
+                token2_hash,
+                PoolKind::ConstantProduct,
+                30
+            ),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        assert_ok!(SwapModule::force_create_trade_pair(
+            Origin::root(),
+            bob,
+            token1_hash,
+            token2_hash,
+            PoolKind::ConstantProduct,
+            30
+        ));
+        let forced_hash =
+            SwapModule::trade_pair_hash_by_base_quote((token1_hash, token2_hash, 30)).unwrap();
+        assert_last_event(RawEvent::TradePairCreated(
+            bob,
+            forced_hash,
+            SwapModule::trade_pair(forced_hash).unwrap(),
+        ));
+        assert_eq!(SwapModule::trade_pair(forced_hash).unwrap().fee_tier, 30);
+
+        set_listing_policy(ListingPolicy::Permissionless);
+        let tp_hash =
+            SwapModule::trade_pair_hash_by_base_quote((token1_hash, token2_hash, 0)).unwrap();
+        let tp = SwapModule::trade_pair(tp_hash).unwrap();
+        assert_ok!(TokenModule::do_transfer(
+            bob,
+            alice,
+            token2_hash,
+            100000,
+            None
+        ));
+        assert_ok!(SwapModule::add_liquidity(
+            Origin::signed(alice),
+            tp_hash,
+            10000,
+            Some(20000)
+        ));
+
+        assert_err!(
+            SwapModule::delist_trade_pair(Origin::signed(alice), tp_hash),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        assert_ok!(SwapModule::delist_trade_pair(Origin::root(), tp_hash));
+        assert_err!(
+            SwapModule::delist_trade_pair(Origin::root(), tp_hash),
+            Error::<Test>::TradePairDelisted
+        );
+        assert!(SwapModule::is_delisted(tp_hash));
+        assert_eq!(
+            SwapModule::trade_pair_hash_by_base_quote((token1_hash, token2_hash, 0)),
+            None
+        );
+        assert_eq!(
+            SwapModule::trade_pair_hash_by_base_quote((token2_hash, token1_hash, 0)),
+            None
+        );
+        assert_eq!(
+            SwapModule::trade_pair_hashes_by_base_quote((token2_hash, token1_hash)),
+            vec![forced_hash]
+        );
+
+        assert_err!(
+            SwapModule::swap(Origin::signed(alice), tp_hash, token1_hash, 1000),
+            Error::<Test>::TradePairDelisted
+        );
+        assert_err!(
+            SwapModule::add_liquidity(Origin::signed(alice), tp_hash, 1000, None),
+            Error::<Test>::TradePairDelisted
+        );
+        // liquidity providers can still leave the pair
+        assert_ok!(SwapModule::remove_liquidity(
+            Origin::signed(alice),
+            tp_hash,
+            5000
+        ));
+        assert_eq!(TokenModule::balance_of((tp.account, token1_hash)), 5000);
+        assert_eq!(TokenModule::balance_of((tp.account, token2_hash)), 10000);
+
+        // the token pair can be listed again in the same fee tier
+        assert_ok!(SwapModule::create_trade_pair(
+            Origin::signed(charlie),
+            token2_hash,
+            token1_hash,
+            PoolKind::ConstantProduct,
+            0
+        ));
+        let relisted_hash =
+            SwapModule::trade_pair_hash_by_base_quote((token1_hash, token2_hash, 0)).unwrap();
+        assert_ne!(relisted_hash, tp_hash);
+        assert!(!SwapModule::is_delisted(relisted_hash));
+    });
+}
+
+#[test]
+fn protocol_fee_tests() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        let alice = 10u64;
+        let bob = 20u64;
+        let treasury = 99u64;
+
+        let token1_hash = issue_token(b"6666", 10000000);
+        let token2_hash = issue_token(b"8888", 10000000);
+
+        let tp_hash = create_pair_of(token1_hash, token2_hash, PoolKind::ConstantProduct, 30);
+        let tp = SwapModule::trade_pair(tp_hash).unwrap();
+
+        assert_err!(
+            SwapModule::set_protocol_fee(
+                Origin::signed(alice),
+                Some(treasury),
+                Permill::from_parts(166_667)
+            ),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        assert_err!(
+            SwapModule::set_protocol_fee(Origin::root(), None, Permill::from_parts(166_667)),
+            Error::<Test>::ProtocolFeeRecipientIsNone
+        );
+        // a sixth of the swap fees
+        assert_ok!(SwapModule::set_protocol_fee(
+            Origin::root(),
+            Some(treasury),
+            Permill::from_parts(166_667)
+        ));
+        assert_eq!(SwapModule::protocol_fee_recipient(), Some(treasury));
+
+        assert_ok!(SwapModule::add_liquidity(
+            Origin::signed(alice),
+            tp_hash,
+            1000000,
+            Some(1000000)
+        ));
+        assert_eq!(
+            TokenModule::balance_of((treasury, tp.liquidity_token_hash)),
+            0
+        );
+        assert_eq!(SwapModule::k_last(tp_hash), 1000000000000u128.into());
+
+        assert_ok!(TokenModule::do_transfer(
+            alice,
+            bob,
+            token1_hash,
+            100000,
+            None
+        ));
+        assert_ok!(SwapModule::swap(
+            Origin::signed(bob),
+            tp_hash,
+            token1_hash,
+            100000
+        ));
+        assert_eq!(TokenModule::balance_of((bob, token2_hash)), 90661);
+        // fees are minted lazily, on the next liquidity event
+        assert_eq!(
+            TokenModule::balance_of((treasury, tp.liquidity_token_hash)),
+            0
+        );
+
+        assert_ok!(SwapModule::add_liquidity(
+            Origin::signed(alice),
+            tp_hash,
+            100000,
+            None
+        ));
+        assert_eq!(
+            TokenModule::balance_of((treasury, tp.liquidity_token_hash)),
+            22
+        );
+        assert_eq!(SwapModule::protocol_fees_accrued(tp_hash), 22);
+        assert_eq!(
+            TokenModule::balance_of((alice, tp.liquidity_token_hash)),
+            1000000 + 90911
+        );
+        assert_eq!(TokenModule::balance_of((tp.account, token1_hash)), 1200000);
+        assert_eq!(TokenModule::balance_of((tp.account, token2_hash)), 992006);
+        assert_eq!(
+            SwapModule::trade_pair(tp_hash)
+                .unwrap()
+                .liquidity_token_issued_amount,
+            1090933
+        );
+        assert_eq!(SwapModule::k_last(tp_hash), 1190407200000u128.into());
+
+        // no swap since the last liquidity event, so nothing more is minted
+        assert_ok!(SwapModule::remove_liquidity(
+            Origin::signed(alice),
+            tp_hash,
+            100000
+        ));
+        assert_eq!(SwapModule::protocol_fees_accrued(tp_hash), 22);
+        assert_eq!(
+            TokenModule::balance_of((tp.account, token1_hash)),
+            1200000 - 109997
+        );
+        assert_eq!(
+            TokenModule::balance_of((tp.account, token2_hash)),
+            992006 - 90931
+        );
+        assert_eq!(SwapModule::protocol_revenue(tp_hash), (24, 20));
+
+        // turning the fee off stops tracking the pair
+        assert_ok!(SwapModule::set_protocol_fee(
+            Origin::root(),
+            None,
+            Permill::zero()
+        ));
+        assert_ok!(SwapModule::remove_liquidity(
+            Origin::signed(alice),
+            tp_hash,
+            100000
+        ));
+        assert!(SwapModule::k_last(tp_hash).is_zero());
+    });
+}
+
+#[test]
+fn protocol_revenue_with_large_reserves() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        let reserve = 1u128 << 100;
+
+        let token1_hash = issue_token(b"6666", 1 << 120);
+        let token2_hash = issue_token(b"8888", 1 << 120);
+        let tp_hash = create_liquid_pair_of(
+            token1_hash,
+            token2_hash,
+            PoolKind::ConstantProduct,
+            30,
+            reserve,
+            reserve,
+        );
+        assert_eq!(
+            SwapModule::trade_pair(tp_hash)
+                .unwrap()
+                .liquidity_token_issued_amount,
+            reserve
+        );
+
+        // the reserves times the accrued liquidity take 140 bits
+        crate::ProtocolFeesAccrued::<Test>::insert(tp_hash, 1u128 << 40);
+        assert_eq!(
+            SwapModule::protocol_revenue(tp_hash),
+            (1u128 << 40, 1u128 << 40)
+        );
+    });
+}
+
+#[test]
+fn pause_and_circuit_breaker_tests() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        let alice = 10u64;
+        let bob = 20u64;
+
+        let token1_hash = issue_token(b"6666", 10000000);
+        let token2_hash = issue_token(b"8888", 10000000);
+
+        let tp_hash = create_pair_of(token1_hash, token2_hash, PoolKind::ConstantProduct, 0);
+        assert_ok!(SwapModule::add_liquidity(
+            Origin::signed(alice),
+            tp_hash,
+            100000,
+            Some(200000)
+        ));
+        assert_ok!(TokenModule::do_transfer(
+            alice,
+            bob,
+            token1_hash,
+            100000,
+            None
+        ));
+
+        assert_err!(
+            SwapModule::pause_pair(Origin::signed(alice), tp_hash),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        assert_err!(
+            SwapModule::pause_pair(Origin::root(), token1_hash),
+            Error::<Test>::NoMatchingTradePair
+        );
+        assert_ok!(SwapModule::pause_pair(Origin::root(), tp_hash));
+        assert!(SwapModule::is_pair_paused(tp_hash));
+        assert_err!(
+            SwapModule::swap(Origin::signed(bob), tp_hash, token1_hash, 1000),
+            Error::<Test>::TradingPaused
+        );
+        assert_err!(
+            SwapModule::add_liquidity(Origin::signed(alice), tp_hash, 1000, None),
+            Error::<Test>::TradingPaused
+        );
+        // liquidity can still be removed from a paused pair
+        assert_ok!(SwapModule::remove_liquidity(
+            Origin::signed(alice),
+            tp_hash,
+            1000
+        ));
+        assert_ok!(SwapModule::unpause_pair(Origin::root(), tp_hash));
+        assert_ok!(SwapModule::add_liquidity(
+            Origin::signed(alice),
+            tp_hash,
+            1000,
+            None
+        ));
+
+        assert_ok!(SwapModule::pause_all(Origin::root()));
+        assert_err!(
+            SwapModule::swap(Origin::signed(bob), tp_hash, token1_hash, 1000),
+            Error::<Test>::TradingPaused
+        );
+        assert_ok!(SwapModule::unpause_all(Origin::root()));
+        assert!(!SwapModule::is_all_paused());
+
+        // trades may move the price by up to 10% within a block
+        assert_ok!(SwapModule::set_circuit_breaker(
+            Origin::root(),
+            tp_hash,
+            Some(Permill::from_percent(10))
+        ));
+        assert_ok!(SwapModule::swap(
+            Origin::signed(bob),
+            tp_hash,
+            token1_hash,
+            2000
+        ));
+        assert_eq!(TokenModule::balance_of((bob, token2_hash)), 3921);
+        assert_ok!(SwapModule::swap(
+            Origin::signed(bob),
+            tp_hash,
+            token1_hash,
+            3000
+        ));
+        assert_eq!(TokenModule::balance_of((bob, token2_hash)), 3921 + 5602);
+        assert_err!(
+            SwapModule::swap(Origin::signed(bob), tp_hash, token1_hash, 1000),
+            Error::<Test>::PriceMoveTooLarge
+        );
+
+        // the reference price is reset by the first swap of the next block
+        run_to_block(2);
+        assert_ok!(SwapModule::swap(
+            Origin::signed(bob),
+            tp_hash,
+            token1_hash,
+            1000
+        ));
+        assert_eq!(
+            TokenModule::balance_of((bob, token2_hash)),
+            3921 + 5602 + 1796
+        );
+
+        assert_ok!(SwapModule::set_circuit_breaker(
+            Origin::root(),
+            tp_hash,
+            None
+        ));
+        assert_ok!(SwapModule::swap(
+            Origin::signed(bob),
+            tp_hash,
+            token1_hash,
+            50000
+        ));
+    });
+}
+
+/// Issue a token of `total_supply` from alice (10).
+fn issue_token(symbol: &[u8], total_supply: u128) -> H256 {
+    TokenModule::do_issue(10, symbol.to_vec(), total_supply, TokenType::Normal).unwrap()
+}
+
+/// Issue `n` tokens from alice (10) and give bob (20) some of each.
+fn issue_tokens(n: usize) -> Vec<H256> {
+    let symbols = [b"AAAA", b"BBBB", b"CCCC"];
+    (0..n)
+        .map(|i| {
+            let hash = issue_token(symbols[i], 10000000000);
+            assert_ok!(TokenModule::do_transfer(10, 20, hash, 100000000, None));
+            hash
+        })
+        .collect()
+}
+
+/// Create a trade pair of `base` and `quote` as alice, returning its hash.
+fn create_pair_of(base: H256, quote: H256, kind: PoolKind, fee_tier: u32) -> H256 {
+    assert_ok!(SwapModule::create_trade_pair(
+        Origin::signed(10),
+        base,
+        quote,
+        kind,
+        fee_tier
+    ));
+    let tp_hash = SwapModule::trade_pair_hash_by_base_quote((base, quote, fee_tier)).unwrap();
+    assert_last_event(RawEvent::TradePairCreated(
+        10,
+        tp_hash,
+        SwapModule::trade_pair(tp_hash).unwrap(),
+    ));
+
+    tp_hash
+}
+
+/// Create a trade pair of `base` and `quote` holding `base_amount` and `quote_amount` from
+/// alice, returning its hash.
+fn create_liquid_pair_of(
+    base: H256,
+    quote: H256,
+    kind: PoolKind,
+    fee_tier: u32,
+    base_amount: u128,
+    quote_amount: u128,
+) -> H256 {
+    let tp_hash = create_pair_of(base, quote, kind, fee_tier);
+    assert_ok!(SwapModule::add_liquidity(
+        Origin::signed(10),
+        tp_hash,
+        base_amount,
+        Some(quote_amount)
+    ));
+    assert_has_event(RawEvent::Sync(tp_hash, base_amount, quote_amount));
+
+    tp_hash
+}
+
+/// Create a trade pair of two new tokens as alice, returning (base, quote, pair hash).
+fn create_pair(kind: PoolKind, fee_tier: u32) -> (H256, H256, H256) {
+    let tokens = issue_tokens(2);
+    let (base, quote) = (tokens[0], tokens[1]);
+
+    (base, quote, create_pair_of(base, quote, kind, fee_tier))
+}
+
+/// Create a trade pair holding 1000000 base and 2000000 quote from alice.
+fn create_liquid_pair(kind: PoolKind, fee_tier: u32) -> (H256, H256, H256) {
+    let tokens = issue_tokens(2);
+    let (base, quote) = (tokens[0], tokens[1]);
+
+    let tp_hash = create_liquid_pair_of(base, quote, kind, fee_tier, 1000000, 2000000);
+    assert_last_event(RawEvent::LiquidityAdded(
+        10, tp_hash, 1000000, 2000000, 1000000, 1000000, 2000000,
+    ));
+
+    (base, quote, tp_hash)
+}
+
+/// Create a concentrated liquidity pair initialized at a price of one.
+fn create_concentrated_pool() -> (H256, H256, H256) {
+    let (base, quote, tp_hash) = create_pair(PoolKind::Concentrated, 30);
+    assert_ok!(SwapModule::initialize_concentrated_pool(
+        Origin::signed(10),
+        tp_hash,
+        1 << 64
+    ));
+
+    (base, quote, tp_hash)
+}
+
+/// Create an initialized concentrated liquidity pair at a price of one, where alice holds
+/// position 0 around that price.
+fn create_concentrated_position() -> (H256, H256, H256) {
+    let (base, quote, tp_hash) = create_concentrated_pool();
+    assert_ok!(SwapModule::mint_position(
+        Origin::signed(10),
+        tp_hash,
+        -1000,
+        1000,
+        1000000000
+    ));
+    assert_last_event(RawEvent::PositionMinted(10, tp_hash, 0, 48768198, 48768198));
+
+    (base, quote, tp_hash)
+}
+
+/// Create a weighted pool of two new tokens with equal weights, seeded by alice.
+fn create_weighted_pool() -> (H256, H256, H256) {
+    let tokens = issue_tokens(2);
+
+    assert_ok!(SwapModule::create_weighted_pool(
+        Origin::signed(10),
+        tokens.clone(),
+        vec![1, 1],
+        vec![2000000000, 1000000000]
+    ));
+    let pool_hash = SwapModule::weighted_pool_hash_by_index(0).unwrap();
+    assert_last_event(RawEvent::WeightedPoolCreated(10, pool_hash));
+
+    (tokens[0], tokens[1], pool_hash)
+}
+
+#[test]
+fn swap_and_remove_liquidity_emit_events() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_liquid_pair(PoolKind::ConstantProduct, 30);
+
+        assert_ok!(SwapModule::swap_buy(Origin::signed(20), tp_hash, 10000));
+        assert_has_event(RawEvent::Sync(tp_hash, 1010000, 1980257));
+        assert_last_event(RawEvent::SwapBuy(
+            20, tp_hash, 10000, 19743, 1010000, 1980257,
+        ));
+
+        assert_ok!(SwapModule::swap_sell(Origin::signed(20), tp_hash, 10000));
+        assert_last_event(RawEvent::SwapSell(
+            20, tp_hash, 10000, 5059, 1004941, 1990257,
+        ));
+
+        assert_ok!(SwapModule::remove_liquidity(
+            Origin::signed(10),
+            tp_hash,
+            500000
+        ));
+        assert_last_event(RawEvent::LiquidityRemoved(
+            10, tp_hash, 502470, 995128, 500000, 502471, 995129,
+        ));
+
+        let syncs = swap_events()
+            .into_iter()
+            .filter(|event| matches!(event, RawEvent::Sync(..)))
+            .count();
+        assert_eq!(syncs, 4);
+    });
+}
+
+#[test]
+fn swap_with_huge_amount_is_rejected() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (base, quote, tp_hash) = create_liquid_pair(PoolKind::ConstantProduct, 30);
+
+        // the reserves times the amount in exceed a balance
+        let amount = u128::max_value();
+        assert!(SwapModule::best_trade_pair(base, quote, amount).is_some());
+        assert_noop!(
+            SwapModule::swap_buy(Origin::signed(20), tp_hash, amount),
+            token::Error::<Test>::BalanceNotEnough
+        );
+        assert_noop!(
+            SwapModule::swap_sell(Origin::signed(20), tp_hash, amount),
+            token::Error::<Test>::BalanceNotEnough
+        );
+        assert_noop!(
+            SwapModule::swap_by_tokens(Origin::signed(20), quote, base, amount),
+            token::Error::<Test>::BalanceNotEnough
+        );
+    });
+}
+
+#[test]
+fn liquidity_math_overflow() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (base, quote, tp_hash) = create_pair(PoolKind::ConstantProduct, 30);
+        assert_ok!(SwapModule::add_liquidity(
+            Origin::signed(10),
+            tp_hash,
+            2000000,
+            Some(1000000)
+        ));
+
+        // twice the amount of quote given is more base than a balance holds
+        assert_noop!(
+            SwapModule::add_liquidity_by_base_quote(
+                Origin::signed(20),
+                quote,
+                base,
+                30,
+                u128::max_value(),
+                None
+            ),
+            Error::<Test>::LiquidityMathOverflow
+        );
+
+        // twice the base given is more quote than a balance holds
+        let base = issue_token(b"DDDD", u128::max_value());
+        let quote = issue_tokens(1)[0];
+        let tp_hash =
+            create_liquid_pair_of(base, quote, PoolKind::ConstantProduct, 30, 1000000, 2000000);
+        assert_noop!(
+            SwapModule::add_liquidity(Origin::signed(10), tp_hash, 1 << 127, None),
+            Error::<Test>::LiquidityMathOverflow
+        );
+    });
+}
+
+#[test]
+fn swap_weight_is_refunded_to_the_pool_kind() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (base, _, tp_hash) = create_liquid_pair(PoolKind::StableSwap, 30);
+
+        let post_info = SwapModule::swap_buy(Origin::signed(20), tp_hash, 1000).unwrap();
+        assert_eq!(
+            post_info.actual_weight,
+            Some(SwapModule::swap_weight(PoolKind::StableSwap, true))
+        );
+        let post_info = SwapModule::swap(Origin::signed(20), tp_hash, base, 1000).unwrap();
+        assert_eq!(
+            post_info.actual_weight,
+            Some(SwapModule::swap_weight(PoolKind::StableSwap, true))
+        );
+    });
+}
+
+#[test]
+fn swap_crosses_bounded_ticks() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_concentrated_position();
+        for i in 1..=MAX_TICKS_CROSSED as i32 + 1 {
+            assert_ok!(SwapModule::mint_position(
+                Origin::signed(10),
+                tp_hash,
+                -10 * i,
+                10 * i,
+                1000
+            ));
+        }
+
+        // moving the price below tick -330 crosses one lower tick too many
+        assert_noop!(
+            SwapModule::swap_buy(Origin::signed(10), tp_hash, 20000000),
+            Error::<Test>::TooManyTicksCrossed
+        );
+        assert_ok!(SwapModule::swap_buy(Origin::signed(10), tp_hash, 1000000));
+    });
+}
+
+#[test]
+fn initialized_ticks_are_bounded() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_concentrated_position();
+        let mut tick = 2000;
+        while SwapModule::initialized_ticks(tp_hash).len() < MAX_INITIALIZED_TICKS as usize {
+            assert_ok!(SwapModule::mint_position(
+                Origin::signed(10),
+                tp_hash,
+                tick,
+                tick + 1,
+                1
+            ));
+            tick += 2;
+        }
+
+        assert_noop!(
+            SwapModule::mint_position(Origin::signed(10), tp_hash, tick, tick + 1, 1),
+            Error::<Test>::TooManyInitializedTicks
+        );
+        // positions between initialized ticks can still be minted
+        assert_ok!(SwapModule::mint_position(
+            Origin::signed(10),
+            tp_hash,
+            -1000,
+            1000,
+            1
+        ));
+    });
+}
+
+#[test]
+fn base_equal_quote() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let tokens = issue_tokens(1);
+
+        assert_err!(
+            SwapModule::create_trade_pair(
+                Origin::signed(10),
+                tokens[0],
+                tokens[0],
+                PoolKind::ConstantProduct,
+                30
+            ),
+            Error::<Test>::BaseEqualQuote
+        );
+        assert!(swap_events().is_empty());
+    });
+}
+
+#[test]
+fn token_owner_not_found() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let tokens = issue_tokens(1);
+
+        set_listing_policy(ListingPolicy::TokenOwner);
+        assert_err!(
+            SwapModule::create_trade_pair(
+                Origin::signed(10),
+                tokens[0],
+                H256::from_low_u64_be(1),
+                PoolKind::ConstantProduct,
+                30
+            ),
+            Error::<Test>::TokenOwnerNotFound
+        );
+        set_listing_policy(ListingPolicy::Permissionless);
+    });
+}
+
+#[test]
+fn token_not_found() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let tokens = issue_tokens(1);
+
+        assert_err!(
+            SwapModule::create_trade_pair(
+                Origin::signed(10),
+                tokens[0],
+                H256::from_low_u64_be(1),
+                PoolKind::ConstantProduct,
+                30
+            ),
+            Error::<Test>::TokenNotFound
+        );
+    });
+}
+
+#[test]
+fn sender_not_equal_to_base_or_quote_owner() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let tokens = issue_tokens(2);
+
+        set_listing_policy(ListingPolicy::TokenOwner);
+        assert_err!(
+            SwapModule::create_trade_pair(
+                Origin::signed(20),
+                tokens[0],
+                tokens[1],
+                PoolKind::ConstantProduct,
+                30
+            ),
+            Error::<Test>::SenderNotEqualToBaseOrQuoteOwner
+        );
+        set_listing_policy(ListingPolicy::Permissionless);
+    });
+}
+
+#[test]
+fn trade_pair_existed() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (base, quote, _) = create_pair(PoolKind::ConstantProduct, 30);
+
+        assert_err!(
+            SwapModule::create_trade_pair(
+                Origin::signed(10),
+                quote,
+                base,
+                PoolKind::ConstantProduct,
+                30
+            ),
+            Error::<Test>::TradePairExisted
+        );
+    });
+}
+
+#[test]
+fn no_matching_trade_pair() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let unknown = H256::from_low_u64_be(1);
+
+        assert_err!(
+            SwapModule::add_liquidity(Origin::signed(10), unknown, 1000, Some(1000)),
+            Error::<Test>::NoMatchingTradePair
+        );
+        assert_err!(
+            SwapModule::swap_buy(Origin::signed(10), unknown, 1000),
+            Error::<Test>::NoMatchingTradePair
+        );
+    });
+}
+
+#[test]
+fn quote_amount_is_none() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_pair(PoolKind::ConstantProduct, 30);
+
+        assert_err!(
+            SwapModule::add_liquidity(Origin::signed(10), tp_hash, 1000, None),
+            Error::<Test>::QuoteAmountIsNone
+        );
+    });
+}
+
+#[test]
+fn base_amount_is_zero() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_liquid_pair(PoolKind::ConstantProduct, 30);
+
+        assert_err!(
+            SwapModule::add_liquidity(Origin::signed(10), tp_hash, 0, None),
+            Error::<Test>::BaseAmountIsZero
+        );
+        assert_err!(
+            SwapModule::swap_buy(Origin::signed(10), tp_hash, 0),
+            Error::<Test>::BaseAmountIsZero
+        );
+    });
+}
+
+#[test]
+fn quote_amount_is_zero() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_pair(PoolKind::ConstantProduct, 30);
+
+        assert_err!(
+            SwapModule::add_liquidity(Origin::signed(10), tp_hash, 1000, Some(0)),
+            Error::<Test>::QuoteAmountIsZero
+        );
+    });
+}
+
+#[test]
+fn liquidity_minted_is_zero() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_pair(PoolKind::ConstantProduct, 30);
+        assert_ok!(SwapModule::add_liquidity(
+            Origin::signed(10),
+            tp_hash,
+            100,
+            Some(10000)
+        ));
+        // the pool holds 200 base for 100 liquidity tokens now
+        assert_ok!(SwapModule::swap_buy(Origin::signed(20), tp_hash, 100));
+
+        assert_err!(
+            SwapModule::add_liquidity(Origin::signed(10), tp_hash, 1, None),
+            Error::<Test>::LiquidityMintedIsZero
+        );
+    });
+}
+
+#[test]
+fn liquidity_token_amount_overflow() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_liquid_pair(PoolKind::ConstantProduct, 30);
+
+        assert_err!(
+            SwapModule::remove_liquidity(Origin::signed(10), tp_hash, 1000001),
+            Error::<Test>::LiquidityTokenAmountOverflow
+        );
+    });
+}
+
+#[test]
+fn liquidity_token_amount_is_zero() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_liquid_pair(PoolKind::ConstantProduct, 30);
+
+        assert_err!(
+            SwapModule::remove_liquidity(Origin::signed(10), tp_hash, 0),
+            Error::<Test>::LiquidityTokenAmountIsZero
+        );
+    });
+}
+
+#[test]
+fn pool_base_amount_is_zero() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_pair(PoolKind::ConstantProduct, 30);
+
+        assert_err!(
+            SwapModule::swap_buy(Origin::signed(10), tp_hash, 1000),
+            Error::<Test>::PoolBaseAmountIsZero
+        );
+    });
+}
+
+#[test]
+fn pool_quote_amount_is_zero() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (base, _, tp_hash) = create_pair(PoolKind::ConstantProduct, 30);
+        let tp = SwapModule::trade_pair(tp_hash).unwrap();
+        // base sent to the pair directly, without any quote
+        assert_ok!(TokenModule::do_transfer(10, tp.account, base, 1000, None));
+
+        assert_err!(
+            SwapModule::swap_buy(Origin::signed(10), tp_hash, 1000),
+            Error::<Test>::PoolQuoteAmountIsZero
+        );
+    });
+}
+
+#[test]
+fn token_paused() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (base, _, tp_hash) = create_liquid_pair(PoolKind::ConstantProduct, 30);
+
+        assert_ok!(TokenModule::pause_token(Origin::signed(10), base));
+        assert_err!(
+            SwapModule::swap_buy(Origin::signed(20), tp_hash, 1000),
+            Error::<Test>::TokenPaused
+        );
+    });
+}
+
+#[test]
+fn flash_swap_amount_is_zero() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_liquid_pair(PoolKind::ConstantProduct, 30);
+
+        assert_err!(
+            SwapModule::flash_swap(Origin::signed(10), tp_hash, 0, 0, vec![]),
+            Error::<Test>::FlashSwapAmountIsZero
+        );
+    });
+}
+
+#[test]
+fn insufficient_liquidity() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_liquid_pair(PoolKind::ConstantProduct, 30);
+
+        assert_err!(
+            SwapModule::flash_swap(Origin::signed(10), tp_hash, 1000000, 0, vec![]),
+            Error::<Test>::InsufficientLiquidity
+        );
+    });
+}
+
+#[test]
+fn flash_swap_not_repaid() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (base, _, tp_hash) = create_liquid_pair(PoolKind::ConstantProduct, 30);
+        let tp = SwapModule::trade_pair(tp_hash).unwrap();
+
+        set_flash_swap_repayment(0, 0);
+        assert_err!(
+            SwapModule::flash_swap(Origin::signed(10), tp_hash, 100, 0, vec![]),
+            Error::<Test>::FlashSwapNotRepaid
+        );
+        assert_eq!(TokenModule::balance_of((tp.account, base)), 1000000);
+    });
+}
+
+#[test]
+fn token_not_in_trade_pair() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_liquid_pair(PoolKind::ConstantProduct, 30);
+        let other = issue_tokens(1)[0];
+
+        assert_err!(
+            SwapModule::swap(Origin::signed(10), tp_hash, other, 1000),
+            Error::<Test>::TokenNotInTradePair
+        );
+    });
+}
+
+#[test]
+fn flash_loan_amount_is_zero() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (base, _, tp_hash) = create_liquid_pair(PoolKind::ConstantProduct, 30);
+        let tp = SwapModule::trade_pair(tp_hash).unwrap();
+        let repay = Box::new(Call::TokenModule(token::Call::transfer(
+            base, tp.account, 0, None,
+        )));
+
+        assert_err!(
+            SwapModule::flash_loan(Origin::signed(10), tp_hash, base, 0, repay),
+            Error::<Test>::FlashLoanAmountIsZero
+        );
+    });
+}
+
+#[test]
+fn flash_loan_not_repaid() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (base, _, tp_hash) = create_liquid_pair(PoolKind::ConstantProduct, 30);
+        let tp = SwapModule::trade_pair(tp_hash).unwrap();
+        // the borrowed amount without the fee
+        let repay = Box::new(Call::TokenModule(token::Call::transfer(
+            base, tp.account, 1000, None,
+        )));
+
+        assert_err!(
+            SwapModule::flash_loan(Origin::signed(10), tp_hash, base, 1000, repay),
+            Error::<Test>::FlashLoanNotRepaid
+        );
+        assert_eq!(TokenModule::balance_of((tp.account, base)), 1000000);
+    });
+}
+
+#[test]
+fn not_stable_swap_pair() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_pair(PoolKind::ConstantProduct, 30);
+
+        assert_err!(
+            SwapModule::ramp_amplification(Origin::root(), tp_hash, 200, 10),
+            Error::<Test>::NotStableSwapPair
+        );
+    });
+}
+
+#[test]
+fn invalid_amplification() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_pair(PoolKind::StableSwap, 0);
+
+        assert_err!(
+            SwapModule::ramp_amplification(Origin::root(), tp_hash, 0, 10),
+            Error::<Test>::InvalidAmplification
+        );
+    });
+}
+
+#[test]
+fn invalid_ramp_block() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_pair(PoolKind::StableSwap, 0);
+        run_to_block(5);
+
+        assert_err!(
+            SwapModule::ramp_amplification(Origin::root(), tp_hash, 200, 4),
+            Error::<Test>::InvalidRampBlock
+        );
+    });
+}
+
+#[test]
+fn stable_swap_math_failed() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_liquid_pair(PoolKind::StableSwap, 0);
+
+        // the new base reserve does not fit in a balance
+        assert_err!(
+            SwapModule::swap_buy(Origin::signed(10), tp_hash, u128::max_value()),
+            Error::<Test>::StableSwapMathFailed
+        );
+    });
+}
+
+#[test]
+fn unsupported_pool_kind() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_pair(PoolKind::Concentrated, 30);
+
+        assert_err!(
+            SwapModule::add_liquidity(Origin::signed(10), tp_hash, 1000, Some(1000)),
+            Error::<Test>::UnsupportedPoolKind
+        );
+    });
+}
+
+#[test]
+fn pool_already_initialized() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_pair(PoolKind::Concentrated, 30);
+
+        assert_ok!(SwapModule::initialize_concentrated_pool(
+            Origin::signed(10),
+            tp_hash,
+            1 << 64
+        ));
+        assert_last_event(RawEvent::ConcentratedPoolInitialized(tp_hash, 1 << 64, 0));
+        assert_err!(
+            SwapModule::initialize_concentrated_pool(Origin::signed(10), tp_hash, 1 << 64),
+            Error::<Test>::PoolAlreadyInitialized
+        );
+    });
+}
+
+#[test]
+fn pool_not_initialized() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_pair(PoolKind::Concentrated, 30);
+
+        assert_err!(
+            SwapModule::mint_position(Origin::signed(10), tp_hash, -60, 60, 1000),
+            Error::<Test>::PoolNotInitialized
+        );
+    });
+}
+
+#[test]
+fn invalid_sqrt_price() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_pair(PoolKind::Concentrated, 30);
+
+        assert_err!(
+            SwapModule::initialize_concentrated_pool(Origin::signed(10), tp_hash, 0),
+            Error::<Test>::InvalidSqrtPrice
+        );
+    });
+}
+
+#[test]
+fn invalid_tick_range() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_concentrated_pool();
+
+        assert_err!(
+            SwapModule::mint_position(Origin::signed(10), tp_hash, 60, -60, 1000),
+            Error::<Test>::InvalidTickRange
+        );
+    });
+}
+
+#[test]
+fn liquidity_is_zero() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_concentrated_pool();
+
+        assert_err!(
+            SwapModule::mint_position(Origin::signed(10), tp_hash, -60, 60, 0),
+            Error::<Test>::LiquidityIsZero
+        );
+    });
+}
+
+#[test]
+fn no_matching_position() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        assert_err!(
+            SwapModule::decrease_position(Origin::signed(10), 7, 1),
+            Error::<Test>::NoMatchingPosition
+        );
+        assert_err!(
+            SwapModule::collect_position(Origin::signed(10), 7),
+            Error::<Test>::NoMatchingPosition
+        );
+    });
+}
+
+#[test]
+fn not_position_owner() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_concentrated_position();
+
+        assert_err!(
+            SwapModule::decrease_position(Origin::signed(20), 0, 1),
+            Error::<Test>::NotPositionOwner
+        );
+        assert_err!(
+            SwapModule::collect_position(Origin::signed(20), 0),
+            Error::<Test>::NotPositionOwner
+        );
+    });
+}
+
+#[test]
+fn position_liquidity_too_low() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_concentrated_position();
+
+        assert_err!(
+            SwapModule::decrease_position(Origin::signed(10), 0, 1000000001),
+            Error::<Test>::PositionLiquidityTooLow
+        );
+    });
+}
+
+#[test]
+fn concentrated_math_overflow() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_pair(PoolKind::Concentrated, 30);
+        // a very low price makes the base amount of a wide position huge
+        assert_ok!(SwapModule::initialize_concentrated_pool(
+            Origin::signed(10),
+            tp_hash,
+            1 << 40
+        ));
+
+        assert_err!(
+            SwapModule::mint_position(
+                Origin::signed(10),
+                tp_hash,
+                crate::concentrated::MIN_TICK,
+                0,
+                u128::max_value()
+            ),
+            Error::<Test>::ConcentratedMathOverflow
+        );
+    });
+}
+
+#[test]
+fn invalid_weighted_pool_tokens() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let tokens = issue_tokens(1);
+
+        assert_err!(
+            SwapModule::create_weighted_pool(Origin::signed(10), tokens, vec![1], vec![1000]),
+            Error::<Test>::InvalidWeightedPoolTokens
+        );
+    });
+}
+
+#[test]
+fn invalid_weights() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let tokens = issue_tokens(2);
+
+        assert_err!(
+            SwapModule::create_weighted_pool(
+                Origin::signed(10),
+                tokens,
+                vec![1, 0],
+                vec![1000, 1000]
+            ),
+            Error::<Test>::InvalidWeights
+        );
+    });
+}
+
+#[test]
+fn amounts_length_mismatch() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, pool_hash) = create_weighted_pool();
+
+        assert_err!(
+            SwapModule::join_pool(Origin::signed(20), pool_hash, 1000, vec![1000]),
+            Error::<Test>::AmountsLengthMismatch
+        );
+    });
+}
+
+#[test]
+fn no_matching_weighted_pool() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        assert_err!(
+            SwapModule::join_pool(
+                Origin::signed(20),
+                H256::from_low_u64_be(1),
+                1000,
+                vec![1000, 1000]
+            ),
+            Error::<Test>::NoMatchingWeightedPool
+        );
+    });
+}
+
+#[test]
+fn token_not_in_weighted_pool() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, pool_hash) = create_weighted_pool();
+        let other = issue_tokens(1)[0];
+
+        assert_err!(
+            SwapModule::join_pool_single(Origin::signed(20), pool_hash, other, 1000, 0),
+            Error::<Test>::TokenNotInWeightedPool
+        );
+    });
+}
+
+#[test]
+fn pool_amount_is_zero() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, pool_hash) = create_weighted_pool();
+
+        assert_err!(
+            SwapModule::join_pool(Origin::signed(20), pool_hash, 0, vec![1000, 1000]),
+            Error::<Test>::PoolAmountIsZero
+        );
+    });
+}
+
+#[test]
+fn slippage_limit_exceeded() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, pool_hash) = create_weighted_pool();
+
+        // 1% of the pool costs 20000000 and 10000000
+        assert_err!(
+            SwapModule::join_pool(
+                Origin::signed(20),
+                pool_hash,
+                10000000,
+                vec![19999999, 10000000]
+            ),
+            Error::<Test>::SlippageLimitExceeded
+        );
+        assert_ok!(SwapModule::join_pool(
+            Origin::signed(20),
+            pool_hash,
+            10000000,
+            vec![20000000, 10000000]
+        ));
+        assert_last_event(RawEvent::PoolJoined(20, pool_hash, 10000000));
+    });
+}
+
+#[test]
+fn trade_too_large() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (a, b, pool_hash) = create_weighted_pool();
+
+        assert_err!(
+            SwapModule::swap_weighted(Origin::signed(10), pool_hash, a, 1000000001, b, 0),
+            Error::<Test>::TradeTooLarge
+        );
+    });
+}
+
+#[test]
+fn weighted_math_failed() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, pool_hash) = create_weighted_pool();
+
+        // the amount of the first token needed does not fit in a balance
+        assert_err!(
+            SwapModule::join_pool(
+                Origin::signed(20),
+                pool_hash,
+                u128::max_value(),
+                vec![u128::max_value(), u128::max_value()]
+            ),
+            Error::<Test>::WeightedMathFailed
+        );
+    });
+}
+
+#[test]
+fn invalid_fee_tier() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let tokens = issue_tokens(2);
+
+        assert_err!(
+            SwapModule::create_trade_pair(
+                Origin::signed(10),
+                tokens[0],
+                tokens[1],
+                PoolKind::ConstantProduct,
+                7
+            ),
+            Error::<Test>::InvalidFeeTier
+        );
+    });
+}
+
+#[test]
+fn listing_requires_governance() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let tokens = issue_tokens(2);
+
+        set_listing_policy(ListingPolicy::Governance);
+        assert_err!(
+            SwapModule::create_trade_pair(
+                Origin::signed(10),
+                tokens[0],
+                tokens[1],
+                PoolKind::ConstantProduct,
+                30
+            ),
+            Error::<Test>::ListingRequiresGovernance
+        );
+        assert_ok!(SwapModule::force_create_trade_pair(
+            Origin::root(),
+            10,
+            tokens[0],
+            tokens[1],
+            PoolKind::ConstantProduct,
+            30
+        ));
+        set_listing_policy(ListingPolicy::Permissionless);
+    });
+}
+
+#[test]
+fn trade_pair_delisted() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_liquid_pair(PoolKind::ConstantProduct, 30);
+
+        assert_ok!(SwapModule::delist_trade_pair(Origin::root(), tp_hash));
+        assert_last_event(RawEvent::TradePairDelisted(tp_hash));
+        assert_err!(
+            SwapModule::swap_buy(Origin::signed(20), tp_hash, 1000),
+            Error::<Test>::TradePairDelisted
+        );
+        assert_err!(
+            SwapModule::delist_trade_pair(Origin::root(), tp_hash),
+            Error::<Test>::TradePairDelisted
+        );
+    });
+}
+
+#[test]
+fn protocol_fee_recipient_is_none() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        assert_err!(
+            SwapModule::set_protocol_fee(Origin::root(), None, Permill::from_percent(10)),
+            Error::<Test>::ProtocolFeeRecipientIsNone
+        );
+        assert_ok!(SwapModule::set_protocol_fee(
+            Origin::root(),
+            Some(30),
+            Permill::from_percent(10)
+        ));
+        assert_last_event(RawEvent::ProtocolFeeSet(
+            Some(30),
+            Permill::from_percent(10),
+        ));
+    });
+}
+
+#[test]
+fn protocol_fee_overflow() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        assert_ok!(SwapModule::set_protocol_fee(
+            Origin::root(),
+            Some(30),
+            Permill::from_percent(10)
+        ));
+        let base = issue_token(b"6666", u128::max_value());
+        let quote = issue_token(b"8888", u128::max_value());
+        let tp_hash = create_liquid_pair_of(
+            base,
+            quote,
+            PoolKind::ConstantProduct,
+            30,
+            1 << 120,
+            1 << 120,
+        );
+        let tp = SwapModule::trade_pair(tp_hash).unwrap();
+
+        // donations grow the square root of k by 2^127 since the last liquidity event, which
+        // times the 2^120 liquidity tokens and the share takes more than 256 bits
+        assert_ok!(TokenModule::do_transfer(
+            10,
+            tp.account,
+            base,
+            1 << 127,
+            None
+        ));
+        assert_ok!(TokenModule::do_transfer(
+            10,
+            tp.account,
+            quote,
+            1 << 127,
+            None
+        ));
+        assert_noop!(
+            SwapModule::remove_liquidity(Origin::signed(10), tp_hash, 1000),
+            Error::<Test>::ProtocolFeeOverflow
+        );
+    });
+}
+
+#[test]
+fn trading_paused() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_liquid_pair(PoolKind::ConstantProduct, 30);
+
+        assert_ok!(SwapModule::pause_pair(Origin::root(), tp_hash));
+        assert_last_event(RawEvent::PairPaused(tp_hash));
+        assert_err!(
+            SwapModule::swap_buy(Origin::signed(20), tp_hash, 1000),
+            Error::<Test>::TradingPaused
+        );
+    });
+}
+
+#[test]
+fn price_move_too_large() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_liquid_pair(PoolKind::ConstantProduct, 30);
+
+        assert_ok!(SwapModule::set_circuit_breaker(
+            Origin::root(),
+            tp_hash,
+            Some(Permill::from_percent(1))
+        ));
+        assert_last_event(RawEvent::CircuitBreakerSet(
+            tp_hash,
+            Some(Permill::from_percent(1)),
+        ));
+        assert_err!(
+            SwapModule::swap_buy(Origin::signed(20), tp_hash, 100000),
+            Error::<Test>::PriceMoveTooLarge
+        );
+        assert_ok!(SwapModule::swap_buy(Origin::signed(20), tp_hash, 1000));
+    });
+}
+
+#[test]
+fn add_liquidity_rolls_back_when_last_transfer_fails() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_liquid_pair(PoolKind::ConstantProduct, 30);
+        let tp = SwapModule::trade_pair(tp_hash).unwrap();
+        // bob can send the base and quote but not receive liquidity tokens
+        assert_ok!(TokenModule::freeze_account(
+            Origin::signed(tp.account),
+            tp.liquidity_token_hash,
+            20
+        ));
+
+        assert_noop!(
+            SwapModule::add_liquidity(Origin::signed(20), tp_hash, 1000, None),
+            token::Error::<Test>::AccountFrozen
+        );
+    });
+}
+
+#[test]
+fn remove_liquidity_rolls_back_when_last_transfer_fails() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, tp_hash) = create_liquid_pair(PoolKind::ConstantProduct, 30);
+        let tp = SwapModule::trade_pair(tp_hash).unwrap();
+        // alice can receive the base and quote but not send liquidity tokens back
+        assert_ok!(TokenModule::freeze_account(
+            Origin::signed(tp.account),
+            tp.liquidity_token_hash,
+            10
+        ));
+
+        assert_noop!(
+            SwapModule::remove_liquidity(Origin::signed(10), tp_hash, 1000),
+            token::Error::<Test>::AccountFrozen
+        );
+    });
+}
+
+#[test]
+fn swap_rolls_back_when_last_transfer_fails() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (base, quote, tp_hash) = create_liquid_pair(PoolKind::ConstantProduct, 30);
+        // the base moves before the quote in both directions, so the quote leg fails last
+        assert_ok!(TokenModule::freeze_account(Origin::signed(10), quote, 20));
+
+        assert_noop!(
+            SwapModule::swap_buy(Origin::signed(20), tp_hash, 1000),
+            token::Error::<Test>::AccountFrozen
+        );
+        assert_noop!(
+            SwapModule::swap(Origin::signed(20), tp_hash, base, 1000),
+            token::Error::<Test>::AccountFrozen
+        );
+
+        assert_noop!(
+            SwapModule::swap_sell(Origin::signed(20), tp_hash, 1000),
+            token::Error::<Test>::AccountFrozen
+        );
+    });
+}
+
+#[test]
+fn join_pool_rolls_back_when_last_transfer_fails() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (_, _, pool_hash) = create_weighted_pool();
+        let pool = SwapModule::weighted_pool(pool_hash).unwrap();
+        // bob can send every token of the pool but not receive pool tokens
+        assert_ok!(TokenModule::freeze_account(
+            Origin::signed(pool.account),
+            pool.pool_token_hash,
+            20
+        ));
+
+        assert_noop!(
+            SwapModule::join_pool(
+                Origin::signed(20),
+                pool_hash,
+                10000000,
+                vec![20000000, 10000000]
+            ),
+            token::Error::<Test>::AccountFrozen
+        );
+    });
+}
+
+#[test]
+fn best_trade_pair_for_amount_out_takes_least() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let (base, quote, cheap_hash) = create_pair(PoolKind::ConstantProduct, 5);
+        let deep_hash = create_pair_of(base, quote, PoolKind::ConstantProduct, 100);
+        assert_ok!(SwapModule::add_liquidity(
+            Origin::signed(10),
+            cheap_hash,
+            10000,
+            Some(10000)
+        ));
+        assert_ok!(SwapModule::add_liquidity(
+            Origin::signed(10),
+            deep_hash,
+            100000,
+            Some(100000)
+        ));
+
+        // 980 out costs 1088 in the 0.05% pair and 1000 in the deeper 1% pair
+        assert_eq!(
+            SwapModule::best_trade_pair_for_amount_out(base, quote, 980, 1000000),
+            Some((deep_hash, 1000))
+        );
+        assert_eq!(
+            SwapModule::best_trade_pair_for_amount_out(quote, base, 980, 1000000),
+            Some((deep_hash, 1000))
+        );
+        assert_eq!(
+            SwapModule::best_trade_pair_for_amount_out(base, quote, 980, 999),
+            None
+        );
+        assert_eq!(
+            SwapModule::best_trade_pair_for_amount_out(base, quote, 0, 1000000),
+            None
+        );
+        // more than the 0.05% pair holds
+        assert_eq!(
+            SwapModule::best_trade_pair_for_amount_out(base, quote, 10000, 1000000),
+            Some((deep_hash, 11225))
+        );
+
+        // the bisection stops within 2^16 of the least amount in for a bound of 2^80
+        let (_, amount_in) =
+            SwapModule::best_trade_pair_for_amount_out(base, quote, 980, 1 << 80).unwrap();
+        assert!(amount_in >= 1000 && amount_in <= 1000 + (1 << 16));
+    });
+}
+
+/// A pair of release V1 and the raw entries storing it.
+fn v1_trade_pair(
+    tp_hash: H256,
+    base: H256,
+    quote: H256,
+    liquidity: u128,
+) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let tp = migrations::v1::TradePair::<Test> {
+        tp_hash,
+        base,
+        quote,
+        liquidity_token_hash: H256::repeat_byte(0xee),
+        liquidity_token_issued_amount: liquidity,
+        account: 99,
+    };
+
+    vec![
+        (map_key(b"TradePairs", &tp_hash), tp.encode()),
+        (
+            map_key(b"TradePairsHashByBaseQuote", &(base, quote)),
+            tp_hash.encode(),
+        ),
+    ]
+}
+
+#[test]
+fn migration_registry_is_ordered() {
+    let releases: Vec<Releases> = migrations::migrations::<Test>()
+        .into_iter()
+        .map(|(release, _)| release)
+        .collect();
+
+    assert!(releases.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(releases
+        .iter()
+        .all(|release| *release > Releases::default()));
+    assert_eq!(releases.last(), Some(&Releases::LATEST));
+}
+
+#[test]
+fn genesis_is_at_latest_release() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        assert_eq!(SwapModule::storage_version(), Releases::LATEST);
+
+        let (base, quote, tp_hash) = create_liquid_pair(PoolKind::ConstantProduct, 30);
+        let tp = SwapModule::trade_pair(tp_hash).unwrap();
+
+        SwapModule::on_runtime_upgrade();
+        assert_eq!(SwapModule::trade_pair(tp_hash), Some(tp));
+        assert_eq!(
+            SwapModule::trade_pair_hash_by_base_quote((quote, base, 30)),
+            Some(tp_hash)
+        );
+        assert_eq!(SwapModule::storage_version(), Releases::LATEST);
+    });
+}
+
+#[test]
+fn migrate_v1_trade_pairs() {
+    let (a, b, c) = (
+        H256::repeat_byte(1),
+        H256::repeat_byte(2),
+        H256::repeat_byte(3),
+    );
+    let (ab, ba, ac) = (
+        H256::repeat_byte(0xab),
+        H256::repeat_byte(0xba),
+        H256::repeat_byte(0xac),
+    );
+    let mut snapshot = v1_trade_pair(ab, a, b, 1000);
+    snapshot.extend(v1_trade_pair(ba, b, a, 0));
+    snapshot.extend(v1_trade_pair(ac, a, c, 500));
+
+    new_test_ext_from_snapshot(snapshot).execute_with(|| {
+        assert_eq!(SwapModule::storage_version(), Releases::V1);
+
+        SwapModule::on_runtime_upgrade();
+        assert_eq!(SwapModule::storage_version(), Releases::V2);
+
+        let tp = SwapModule::trade_pair(ab).unwrap();
+        assert_eq!((tp.tp_hash, tp.base, tp.quote), (ab, a, b));
+        assert_eq!(tp.liquidity_token_hash, H256::repeat_byte(0xee));
+        assert_eq!(tp.liquidity_token_issued_amount, 1000);
+        assert_eq!(tp.account, 99);
+        assert_eq!(tp.kind, PoolKind::ConstantProduct);
+        assert_eq!(tp.fee_tier, DefaultFeeTier::get());
+        assert_eq!(
+            SwapModule::trade_pair(ac).map(|tp| (tp.liquidity_token_issued_amount, tp.fee_tier)),
+            Some((500, 30))
+        );
+
+        // both orientations of a and b existed, the one with the most liquidity is kept
+        assert_eq!(
+            SwapModule::trade_pair_hash_by_base_quote((a, b, 30)),
+            Some(ab)
+        );
+        assert_eq!(
+            SwapModule::trade_pair_hash_by_base_quote((b, a, 30)),
+            Some(ab)
+        );
+        assert!(!SwapModule::is_delisted(ab));
+        assert!(SwapModule::is_delisted(ba));
+        assert_eq!(
+            SwapModule::trade_pair(ba).map(|tp| (tp.base, tp.quote, tp.fee_tier)),
+            Some((b, a, 30))
+        );
+        assert_eq!(
+            SwapModule::trade_pair_hash_by_base_quote((a, c, 30)),
+            Some(ac)
+        );
+        assert_eq!(
+            SwapModule::trade_pair_hash_by_base_quote((c, a, 30)),
+            Some(ac)
+        );
+        assert_eq!(
+            sp_io::storage::get(&map_key(b"TradePairsHashByBaseQuote", &(a, b))),
+            None
+        );
+
+        assert_eq!(
+            SwapModule::trade_pair_hashes_by_base_quote((a, b)),
+            vec![ab]
+        );
+        assert_eq!(
+            SwapModule::trade_pair_hashes_by_base_quote((b, a)),
+            vec![ab]
+        );
+        assert_eq!(
+            SwapModule::trade_pair_hashes_by_base_quote((c, a)),
+            vec![ac]
+        );
+
+        // nothing left to migrate
+        let tp = SwapModule::trade_pair(ab);
+        SwapModule::on_runtime_upgrade();
+        assert_eq!(SwapModule::trade_pair(ab), tp);
+        assert_eq!(
+            SwapModule::trade_pair_hashes_by_base_quote((c, a)),
+            vec![ac]
+        );
+    });
+}
@@ -0,0 +1,567 @@
+//! Balancer weighted pools of two to eight tokens.
+//!
+//! With balances `B_i` and normalized weights `W_i`, swaps keep the weighted product
+//! `prod(B_i ^ W_i)` constant, net of fees. The math works in fixed point with 18 decimals.
+
+use codec::{Decode, Encode};
+use frame_support::{dispatch, ensure};
+use sp_core::U256;
+use sp_io::hashing::blake2_256;
+use sp_runtime::{
+    traits::{Bounded, Hash, Zero},
+    SaturatedConversion,
+};
+use sp_std::prelude::*;
+
+use crate::{
+    BalanceOf, Error, Module, Nonce, RawEvent, Trait, WeightedPool, WeightedPools,
+    WeightedPoolsHashByIndex, WeightedPoolsIndex,
+};
+use token::{MultiAsset, MultiCurrency, TokenType};
+
+/// Fewest tokens in a weighted pool.
+pub const MIN_WEIGHTED_TOKENS: usize = 2;
+/// Most tokens in a weighted pool.
+pub const MAX_WEIGHTED_TOKENS: usize = 8;
+/// Pool tokens minted to the creator of a weighted pool.
+pub const INITIAL_POOL_SUPPLY: u128 = 1_000_000_000;
+
+const BONE: u128 = 1_000_000_000_000_000_000;
+/// Fee charged on swaps and on the swapped part of single token joins and exits, 0.3%.
+const SWAP_FEE: u128 = BONE / 1000 * 3;
+/// Swaps and single token joins may add at most half of the balance of a token.
+const MAX_IN_RATIO: u128 = BONE / 2;
+/// Swaps and single token exits may take at most a third of the balance of a token.
+const MAX_OUT_RATIO: u128 = BONE / 3 + 1;
+const BPOW_PRECISION: u128 = BONE / 10_000_000_000;
+const MAX_BPOW_BASE: u128 = 2 * BONE - 1;
+
+fn bone() -> U256 {
+    U256::from(BONE)
+}
+
+fn bmul(a: U256, b: U256) -> Option<U256> {
+    a.checked_mul(b)?
+        .checked_add(bone() / 2)?
+        .checked_div(bone())
+}
+
+fn bdiv(a: U256, b: U256) -> Option<U256> {
+    a.checked_mul(bone())?.checked_add(b / 2)?.checked_div(b)
+}
+
+fn bsub_sign(a: U256, b: U256) -> (U256, bool) {
+    if a >= b {
+        (a - b, false)
+    } else {
+        (b - a, true)
+    }
+}
+
+/// `a ^ n` for a whole `n`.
+fn bpowi(mut a: U256, mut n: U256) -> Option<U256> {
+    let mut z = if n.low_u32() % 2 != 0 { a } else { bone() };
+    n = n / 2;
+    while !n.is_zero() {
+        a = bmul(a, a)?;
+        if n.low_u32() % 2 != 0 {
+            z = bmul(z, a)?;
+        }
+        n = n / 2;
+    }
+
+    Some(z)
+}
+
+/// `base ^ exp` for `exp < 1`, by the binomial series.
+fn bpow_approx(base: U256, exp: U256) -> Option<U256> {
+    let (x, x_negative) = bsub_sign(base, bone());
+    let mut term = bone();
+    let mut sum = term;
+    let mut negative = false;
+
+    let mut i = 1u64;
+    while term >= U256::from(BPOW_PRECISION) {
+        let big_k = U256::from(i).checked_mul(bone())?;
+        let (c, c_negative) = bsub_sign(exp, big_k - bone());
+        term = bdiv(bmul(term, bmul(c, x)?)?, big_k)?;
+        if term.is_zero() {
+            break;
+        }
+
+        if x_negative {
+            negative = !negative;
+        }
+        if c_negative {
+            negative = !negative;
+        }
+        sum = if negative {
+            sum.checked_sub(term)?
+        } else {
+            sum.checked_add(term)?
+        };
+        i += 1;
+    }
+
+    Some(sum)
+}
+
+/// `base ^ exp` in fixed point, for `0 < base < 2`.
+fn bpow(base: U256, exp: U256) -> Option<U256> {
+    if base.is_zero() || base > U256::from(MAX_BPOW_BASE) {
+        return None;
+    }
+
+    let whole = exp / bone();
+    let remain = exp - whole * bone();
+    let whole_pow = bpowi(base, whole)?;
+    if remain.is_zero() {
+        return Some(whole_pow);
+    }
+
+    bmul(whole_pow, bpow_approx(base, remain)?)
+}
+
+fn to_u128(value: U256) -> Option<u128> {
+    if value > U256::from(u128::max_value()) {
+        return None;
+    }
+
+    Some(value.low_u128())
+}
+
+/// Amount of `token_out` sent for `amount_in` of `token_in`.
+pub fn out_given_in(
+    balance_in: u128,
+    weight_in: u32,
+    balance_out: u128,
+    weight_out: u32,
+    amount_in: u128,
+) -> Option<u128> {
+    let balance_in = U256::from(balance_in);
+    let weight_ratio = bdiv(U256::from(weight_in), U256::from(weight_out))?;
+    let adjusted_in = bmul(U256::from(amount_in), bone() - U256::from(SWAP_FEE))?;
+    let y = bdiv(balance_in, balance_in.checked_add(adjusted_in)?)?;
+    let foo = bpow(y, weight_ratio)?;
+
+    to_u128(bmul(U256::from(balance_out), bone().checked_sub(foo)?)?)
+}
+
+/// Pool tokens minted for `amount_in` of a token of weight `weight_in` out of `total_weight`.
+pub fn pool_out_given_single_in(
+    balance_in: u128,
+    weight_in: u32,
+    pool_supply: u128,
+    total_weight: u32,
+    amount_in: u128,
+) -> Option<u128> {
+    let balance_in = U256::from(balance_in);
+    let pool_supply = U256::from(pool_supply);
+    let normalized_weight = bdiv(U256::from(weight_in), U256::from(total_weight))?;
+    // only the part of the amount that is implicitly swapped to the other tokens pays the fee
+    let zaz = bmul(bone() - normalized_weight, U256::from(SWAP_FEE))?;
+    let amount_in_after_fee = bmul(U256::from(amount_in), bone() - zaz)?;
+    let in_ratio = bdiv(balance_in.checked_add(amount_in_after_fee)?, balance_in)?;
+    let pool_ratio = bpow(in_ratio, normalized_weight)?;
+    let new_pool_supply = bmul(pool_ratio, pool_supply)?;
+
+    to_u128(new_pool_supply.checked_sub(pool_supply)?)
+}
+
+/// Amount of a token of weight `weight_out` out of `total_weight` sent for burning
+/// `pool_amount_in` pool tokens.
+pub fn single_out_given_pool_in(
+    balance_out: u128,
+    weight_out: u32,
+    pool_supply: u128,
+    total_weight: u32,
+    pool_amount_in: u128,
+) -> Option<u128> {
+    let balance_out = U256::from(balance_out);
+    let pool_supply = U256::from(pool_supply);
+    let normalized_weight = bdiv(U256::from(weight_out), U256::from(total_weight))?;
+    let new_pool_supply = pool_supply.checked_sub(U256::from(pool_amount_in))?;
+    let pool_ratio = bdiv(new_pool_supply, pool_supply)?;
+    let out_ratio = bpow(pool_ratio, bdiv(bone(), normalized_weight)?)?;
+    let new_balance_out = bmul(out_ratio, balance_out)?;
+    let amount_out_before_fee = balance_out.checked_sub(new_balance_out)?;
+    let zaz = bmul(bone() - normalized_weight, U256::from(SWAP_FEE))?;
+
+    to_u128(bmul(amount_out_before_fee, bone() - zaz)?)
+}
+
+fn ratio_of(balance: u128, ratio: u128) -> u128 {
+    bmul(U256::from(balance), U256::from(ratio))
+        .and_then(to_u128)
+        .unwrap_or_else(u128::max_value)
+}
+
+impl<T: Trait> Module<T> {
+    /// Create a weighted pool of `tokens` and seed it with `amounts` from `sender`, who gets
+    /// the initial pool token supply.
+    pub fn do_create_weighted_pool(
+        sender: T::AccountId,
+        tokens: Vec<T::Hash>,
+        weights: Vec<u32>,
+        amounts: Vec<BalanceOf<T>>,
+    ) -> dispatch::DispatchResult {
+        ensure!(
+            tokens.len() >= MIN_WEIGHTED_TOKENS && tokens.len() <= MAX_WEIGHTED_TOKENS,
+            Error::<T>::InvalidWeightedPoolTokens
+        );
+        ensure!(
+            weights.len() == tokens.len() && amounts.len() == tokens.len(),
+            Error::<T>::AmountsLengthMismatch
+        );
+        ensure!(weights.iter().all(|w| *w > 0), Error::<T>::InvalidWeights);
+        weights
+            .iter()
+            .try_fold(0u32, |total, w| total.checked_add(*w))
+            .ok_or(Error::<T>::InvalidWeights)?;
+
+        let mut pool_token_name = b"WP".to_vec();
+        for (i, token) in tokens.iter().enumerate() {
+            ensure!(
+                !tokens[..i].contains(token),
+                Error::<T>::InvalidWeightedPoolTokens
+            );
+            let symbol = T::Currency::symbol(*token).ok_or(Error::<T>::TokenNotFound)?;
+            pool_token_name.extend(b"_".to_vec());
+            pool_token_name.extend(symbol);
+        }
+
+        for (token, amount) in tokens.iter().zip(amounts.iter()) {
+            ensure!(*amount > Zero::zero(), Error::<T>::PoolAmountIsZero);
+            T::Currency::ensure_can_withdraw(*token, &sender, *amount)?;
+        }
+
+        let nonce = Nonce::get();
+        let hash = (
+            b"weighted",
+            <frame_system::Module<T>>::block_number(),
+            sender.clone(),
+            tokens.clone(),
+            nonce,
+        )
+            .using_encoded(<T as frame_system::Trait>::Hashing::hash);
+        let entropy = (b"substrate/uniswap", tokens.clone(), hash).using_encoded(blake2_256);
+        let account = T::AccountId::decode(&mut &entropy[..]).unwrap_or_default();
+
+        let pool_token_hash = T::Currency::issue(
+            &account,
+            pool_token_name,
+            BalanceOf::<T>::max_value(),
+            TokenType::Liquidity,
+        )?;
+
+        for (token, amount) in tokens.iter().zip(amounts.iter()) {
+            T::Currency::transfer(*token, &sender, &account, *amount)?;
+        }
+        let pool_amount = Self::to_balance(INITIAL_POOL_SUPPLY)?;
+        T::Currency::transfer(pool_token_hash, &account, &sender, pool_amount)?;
+
+        let pool: WeightedPool<T> = WeightedPool {
+            pool_hash: hash,
+            tokens,
+            weights,
+            pool_token_hash,
+            pool_token_issued_amount: pool_amount,
+            account,
+        };
+
+        Nonce::mutate(|n| *n += 1);
+        WeightedPools::insert(hash, pool);
+
+        let index = Self::weighted_pool_index();
+        WeightedPoolsHashByIndex::<T>::insert(index, hash);
+        WeightedPoolsIndex::mutate(|n| *n += 1);
+
+        Self::deposit_event(RawEvent::WeightedPoolCreated(sender, hash));
+
+        Ok(())
+    }
+
+    /// Mint `pool_amount_out` pool tokens for a proportional amount of every token, each at
+    /// most the matching entry of `max_amounts_in`.
+    pub fn do_join_pool(
+        sender: T::AccountId,
+        hash: T::Hash,
+        pool_amount_out: BalanceOf<T>,
+        max_amounts_in: Vec<BalanceOf<T>>,
+    ) -> dispatch::DispatchResult {
+        let mut pool = Self::weighted_pool(hash).ok_or(Error::<T>::NoMatchingWeightedPool)?;
+        Self::ensure_not_paused(hash)?;
+        ensure!(pool_amount_out > Zero::zero(), Error::<T>::PoolAmountIsZero);
+        ensure!(
+            max_amounts_in.len() == pool.tokens.len(),
+            Error::<T>::AmountsLengthMismatch
+        );
+
+        let supply = U256::from(pool.pool_token_issued_amount.saturated_into::<u128>());
+        let mut amounts_in = Vec::with_capacity(pool.tokens.len());
+        for (token, max_amount_in) in pool.tokens.iter().zip(max_amounts_in.iter()) {
+            let balance = U256::from(
+                T::Currency::total_balance(*token, &pool.account).saturated_into::<u128>(),
+            );
+            // rounded up in favour of the pool
+            let amount_in = (balance * U256::from(pool_amount_out.saturated_into::<u128>())
+                + supply
+                - U256::one())
+                / supply;
+            let amount_in = to_u128(amount_in).ok_or(Error::<T>::WeightedMathFailed)?;
+            let amount_in = Self::to_balance(amount_in)?;
+            ensure!(
+                amount_in <= *max_amount_in,
+                Error::<T>::SlippageLimitExceeded
+            );
+            T::Currency::ensure_can_withdraw(*token, &sender, amount_in)?;
+            amounts_in.push(amount_in);
+        }
+        T::Currency::ensure_can_withdraw(pool.pool_token_hash, &pool.account, pool_amount_out)?;
+
+        for (token, amount_in) in pool.tokens.iter().zip(amounts_in.iter()) {
+            T::Currency::transfer(*token, &sender, &pool.account, *amount_in)?;
+        }
+        T::Currency::transfer(
+            pool.pool_token_hash,
+            &pool.account,
+            &sender,
+            pool_amount_out,
+        )?;
+
+        pool.pool_token_issued_amount = pool.pool_token_issued_amount + pool_amount_out;
+        <WeightedPools<T>>::insert(hash, pool);
+
+        Self::deposit_event(RawEvent::PoolJoined(sender, hash, pool_amount_out));
+
+        Ok(())
+    }
+
+    /// Mint pool tokens for `amount_in` of a single `token_in`, at least `min_pool_amount_out`.
+    pub fn do_join_pool_single(
+        sender: T::AccountId,
+        hash: T::Hash,
+        token_in: T::Hash,
+        amount_in: BalanceOf<T>,
+        min_pool_amount_out: BalanceOf<T>,
+    ) -> dispatch::DispatchResult {
+        let mut pool = Self::weighted_pool(hash).ok_or(Error::<T>::NoMatchingWeightedPool)?;
+        Self::ensure_not_paused(hash)?;
+        ensure!(!T::Currency::is_paused(token_in), Error::<T>::TokenPaused);
+        let index = Self::weighted_pool_token_index(&pool, token_in)?;
+        ensure!(amount_in > Zero::zero(), Error::<T>::PoolAmountIsZero);
+
+        let balance_in = T::Currency::total_balance(token_in, &pool.account).saturated_into();
+        ensure!(
+            amount_in.saturated_into::<u128>() <= ratio_of(balance_in, MAX_IN_RATIO),
+            Error::<T>::TradeTooLarge
+        );
+
+        let pool_amount_out = pool_out_given_single_in(
+            balance_in,
+            pool.weights[index],
+            pool.pool_token_issued_amount.saturated_into(),
+            Self::total_weight(&pool),
+            amount_in.saturated_into(),
+        )
+        .ok_or(Error::<T>::WeightedMathFailed)?;
+        let pool_amount_out = Self::to_balance(pool_amount_out)?;
+        ensure!(pool_amount_out > Zero::zero(), Error::<T>::PoolAmountIsZero);
+        ensure!(
+            pool_amount_out >= min_pool_amount_out,
+            Error::<T>::SlippageLimitExceeded
+        );
+
+        T::Currency::ensure_can_withdraw(token_in, &sender, amount_in)?;
+        T::Currency::ensure_can_withdraw(pool.pool_token_hash, &pool.account, pool_amount_out)?;
+
+        T::Currency::transfer(token_in, &sender, &pool.account, amount_in)?;
+        T::Currency::transfer(
+            pool.pool_token_hash,
+            &pool.account,
+            &sender,
+            pool_amount_out,
+        )?;
+
+        pool.pool_token_issued_amount = pool.pool_token_issued_amount + pool_amount_out;
+        <WeightedPools<T>>::insert(hash, pool);
+
+        Self::deposit_event(RawEvent::PoolJoined(sender, hash, pool_amount_out));
+
+        Ok(())
+    }
+
+    /// Burn `pool_amount_in` pool tokens for a proportional amount of every token, each at
+    /// least the matching entry of `min_amounts_out`.
+    pub fn do_exit_pool(
+        sender: T::AccountId,
+        hash: T::Hash,
+        pool_amount_in: BalanceOf<T>,
+        min_amounts_out: Vec<BalanceOf<T>>,
+    ) -> dispatch::DispatchResult {
+        let mut pool = Self::weighted_pool(hash).ok_or(Error::<T>::NoMatchingWeightedPool)?;
+        ensure!(pool_amount_in > Zero::zero(), Error::<T>::PoolAmountIsZero);
+        // the pool is never emptied, so its supply stays positive
+        ensure!(
+            pool_amount_in < pool.pool_token_issued_amount,
+            Error::<T>::LiquidityTokenAmountOverflow
+        );
+        ensure!(
+            min_amounts_out.len() == pool.tokens.len(),
+            Error::<T>::AmountsLengthMismatch
+        );
+        T::Currency::ensure_can_withdraw(pool.pool_token_hash, &sender, pool_amount_in)?;
+
+        let supply = U256::from(pool.pool_token_issued_amount.saturated_into::<u128>());
+        let mut amounts_out = Vec::with_capacity(pool.tokens.len());
+        for (token, min_amount_out) in pool.tokens.iter().zip(min_amounts_out.iter()) {
+            let balance = U256::from(
+                T::Currency::total_balance(*token, &pool.account).saturated_into::<u128>(),
+            );
+            let amount_out = balance * U256::from(pool_amount_in.saturated_into::<u128>()) / supply;
+            let amount_out = to_u128(amount_out).ok_or(Error::<T>::WeightedMathFailed)?;
+            let amount_out = Self::to_balance(amount_out)?;
+            ensure!(
+                amount_out >= *min_amount_out,
+                Error::<T>::SlippageLimitExceeded
+            );
+            amounts_out.push(amount_out);
+        }
+
+        T::Currency::transfer(pool.pool_token_hash, &sender, &pool.account, pool_amount_in)?;
+        for (token, amount_out) in pool.tokens.iter().zip(amounts_out.iter()) {
+            if *amount_out > Zero::zero() {
+                T::Currency::transfer(*token, &pool.account, &sender, *amount_out)?;
+            }
+        }
+
+        pool.pool_token_issued_amount = pool.pool_token_issued_amount - pool_amount_in;
+        <WeightedPools<T>>::insert(hash, pool);
+
+        Self::deposit_event(RawEvent::PoolExited(sender, hash, pool_amount_in));
+
+        Ok(())
+    }
+
+    /// Burn `pool_amount_in` pool tokens for a single `token_out`, at least `min_amount_out`.
+    pub fn do_exit_pool_single(
+        sender: T::AccountId,
+        hash: T::Hash,
+        token_out: T::Hash,
+        pool_amount_in: BalanceOf<T>,
+        min_amount_out: BalanceOf<T>,
+    ) -> dispatch::DispatchResult {
+        let mut pool = Self::weighted_pool(hash).ok_or(Error::<T>::NoMatchingWeightedPool)?;
+        ensure!(!T::Currency::is_paused(token_out), Error::<T>::TokenPaused);
+        let index = Self::weighted_pool_token_index(&pool, token_out)?;
+        ensure!(pool_amount_in > Zero::zero(), Error::<T>::PoolAmountIsZero);
+        ensure!(
+            pool_amount_in < pool.pool_token_issued_amount,
+            Error::<T>::LiquidityTokenAmountOverflow
+        );
+
+        let balance_out = T::Currency::total_balance(token_out, &pool.account).saturated_into();
+        let amount_out = single_out_given_pool_in(
+            balance_out,
+            pool.weights[index],
+            pool.pool_token_issued_amount.saturated_into(),
+            Self::total_weight(&pool),
+            pool_amount_in.saturated_into(),
+        )
+        .ok_or(Error::<T>::WeightedMathFailed)?;
+        ensure!(
+            amount_out <= ratio_of(balance_out, MAX_OUT_RATIO),
+            Error::<T>::TradeTooLarge
+        );
+        let amount_out = Self::to_balance(amount_out)?;
+        ensure!(amount_out > Zero::zero(), Error::<T>::PoolAmountIsZero);
+        ensure!(
+            amount_out >= min_amount_out,
+            Error::<T>::SlippageLimitExceeded
+        );
+
+        T::Currency::ensure_can_withdraw(pool.pool_token_hash, &sender, pool_amount_in)?;
+
+        T::Currency::transfer(pool.pool_token_hash, &sender, &pool.account, pool_amount_in)?;
+        T::Currency::transfer(token_out, &pool.account, &sender, amount_out)?;
+
+        pool.pool_token_issued_amount = pool.pool_token_issued_amount - pool_amount_in;
+        <WeightedPools<T>>::insert(hash, pool);
+
+        Self::deposit_event(RawEvent::PoolExited(sender, hash, pool_amount_in));
+
+        Ok(())
+    }
+
+    /// Swap `amount_in` of `token_in` for at least `min_amount_out` of `token_out`.
+    pub fn do_swap_weighted(
+        sender: T::AccountId,
+        hash: T::Hash,
+        token_in: T::Hash,
+        amount_in: BalanceOf<T>,
+        token_out: T::Hash,
+        min_amount_out: BalanceOf<T>,
+    ) -> dispatch::DispatchResult {
+        let pool = Self::weighted_pool(hash).ok_or(Error::<T>::NoMatchingWeightedPool)?;
+        Self::ensure_not_paused(hash)?;
+        ensure!(
+            !T::Currency::is_paused(token_in) && !T::Currency::is_paused(token_out),
+            Error::<T>::TokenPaused
+        );
+        ensure!(token_in != token_out, Error::<T>::BaseEqualQuote);
+        let index_in = Self::weighted_pool_token_index(&pool, token_in)?;
+        let index_out = Self::weighted_pool_token_index(&pool, token_out)?;
+        ensure!(amount_in > Zero::zero(), Error::<T>::BaseAmountIsZero);
+
+        let balance_in = T::Currency::total_balance(token_in, &pool.account).saturated_into();
+        let balance_out = T::Currency::total_balance(token_out, &pool.account).saturated_into();
+        ensure!(
+            amount_in.saturated_into::<u128>() <= ratio_of(balance_in, MAX_IN_RATIO),
+            Error::<T>::TradeTooLarge
+        );
+
+        let amount_out = out_given_in(
+            balance_in,
+            pool.weights[index_in],
+            balance_out,
+            pool.weights[index_out],
+            amount_in.saturated_into(),
+        )
+        .ok_or(Error::<T>::WeightedMathFailed)?;
+        ensure!(
+            amount_out <= ratio_of(balance_out, MAX_OUT_RATIO),
+            Error::<T>::TradeTooLarge
+        );
+        let amount_out = Self::to_balance(amount_out)?;
+        ensure!(amount_out > Zero::zero(), Error::<T>::QuoteAmountIsZero);
+        ensure!(
+            amount_out >= min_amount_out,
+            Error::<T>::SlippageLimitExceeded
+        );
+
+        T::Currency::ensure_can_withdraw(token_in, &sender, amount_in)?;
+
+        T::Currency::transfer(token_in, &sender, &pool.account, amount_in)?;
+        T::Currency::transfer(token_out, &pool.account, &sender, amount_out)?;
+
+        Self::deposit_event(RawEvent::WeightedPoolSwap(
+            sender, hash, token_in, token_out, amount_in, amount_out,
+        ));
+
+        Ok(())
+    }
+
+    fn weighted_pool_token_index(
+        pool: &WeightedPool<T>,
+        token: T::Hash,
+    ) -> Result<usize, dispatch::DispatchError> {
+        pool.tokens
+            .iter()
+            .position(|t| *t == token)
+            .ok_or_else(|| Error::<T>::TokenNotInWeightedPool.into())
+    }
+
+    fn total_weight(pool: &WeightedPool<T>) -> u32 {
+        pool.weights.iter().sum()
+    }
+}
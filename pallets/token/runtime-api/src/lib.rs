@@ -0,0 +1,17 @@
+//! Runtime API definition for the token pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+
+sp_api::decl_runtime_apis! {
+    pub trait TokenApi<AccountId, Hash, Balance> where
+        AccountId: Codec,
+        Hash: Codec,
+        Balance: Codec,
+    {
+        /// The amount of `token_hash` held by `who` that is still locked by vesting, and the
+        /// amount that has been released and can be claimed with `vest`.
+        fn vesting_balance(who: AccountId, token_hash: Hash) -> (Balance, Balance);
+    }
+}
@@ -0,0 +1,65 @@
+//! Benchmarks for the token pallet.
+
+#![cfg(feature = "runtime-benchmarks")]
+
+use super::*;
+use frame_benchmarking::{account, benchmarks, whitelisted_caller};
+use frame_system::RawOrigin;
+use sp_std::prelude::*;
+
+const SEED: u32 = 0;
+const MAX_MEMO_LENGTH: usize = 512;
+
+benchmarks! {
+    _ { }
+
+    issue {
+        let caller: T::AccountId = whitelisted_caller();
+        let total_supply: T::Balance = 1_000_000u32.into();
+    }: _(RawOrigin::Signed(caller.clone()), b"BENCH".to_vec(), total_supply)
+    verify {
+        let hash = Module::<T>::token_hash_by_index(Module::<T>::token_index() - 1).unwrap();
+        assert_eq!(Module::<T>::balance_of((caller, hash)), total_supply);
+    }
+
+    issue_mintable {
+        let caller: T::AccountId = whitelisted_caller();
+        let total_supply: T::Balance = 1_000_000u32.into();
+    }: _(RawOrigin::Signed(caller.clone()), b"BENCH".to_vec(), total_supply)
+    verify {
+        let hash = Module::<T>::token_hash_by_index(Module::<T>::token_index() - 1).unwrap();
+        assert!(Module::<T>::is_mintable(hash));
+    }
+
+    // with the longest memo, to a new account
+    transfer {
+        let caller: T::AccountId = whitelisted_caller();
+        let recipient: T::AccountId = account("recipient", 0, SEED);
+        let hash = Module::<T>::do_issue(
+            caller.clone(),
+            b"BENCH".to_vec(),
+            1_000_000u32.into(),
+            TokenType::Normal,
+        )?;
+        let amount: T::Balance = 1_000u32.into();
+    }: _(RawOrigin::Signed(caller), hash, recipient.clone(), amount, Some(vec![0u8; MAX_MEMO_LENGTH]))
+    verify {
+        assert_eq!(Module::<T>::balance_of((recipient, hash)), amount);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{new_test_ext, Test};
+    use frame_support::assert_ok;
+
+    #[test]
+    fn benchmarks_work() {
+        new_test_ext().execute_with(|| {
+            assert_ok!(test_benchmark_issue::<Test>());
+            assert_ok!(test_benchmark_issue_mintable::<Test>());
+            assert_ok!(test_benchmark_transfer::<Test>());
+        });
+    }
+}
@@ -2,10 +2,16 @@
 
 use codec::{Decode, Encode};
 use frame_support::{
-    decl_error, decl_event, decl_module, decl_storage, dispatch, ensure, traits::Randomness,
+    decl_error, decl_event, decl_module, decl_storage, dispatch, ensure,
+    traits::{Currency, ExistenceRequirement, Get, Randomness, WithdrawReason},
+    transactional,
+    weights::Weight,
     StorageMap, StorageValue,
 };
-use sp_runtime::traits::{Bounded, Hash};
+use sp_runtime::{
+    traits::{AccountIdConversion, CheckedAdd, CheckedSub, Convert, Hash, Zero},
+    ModuleId,
+};
 use sp_std::prelude::Vec;
 
 use frame_system::ensure_signed;
@@ -14,6 +20,17 @@ use frame_system as system;
 use pallet_balances as balances;
 use pallet_randomness_collective_flip as randomness_collective_flip;
 
+pub mod traits;
+pub use traits::{MultiAsset, MultiCurrency, MultiReservableCurrency};
+
+mod vesting;
+pub use vesting::VestingSchedule;
+
+pub mod migrations;
+pub use migrations::Releases;
+
+mod benchmarking;
+
 #[cfg(test)]
 mod mock;
 
@@ -33,10 +50,51 @@ pub struct Token<Hash, Balance> {
 pub enum TokenType {
     Normal,
     Liquidity,
+    WrappedNative,
 }
 
+/// Accounts allowed to administer a token on behalf of its owner.
+#[derive(Encode, Decode, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct TokenRoles<AccountId> {
+    /// May mint new supply of a token issued as mintable.
+    pub issuer: AccountId,
+    /// May pause and unpause transfers.
+    pub admin: AccountId,
+    /// May freeze and thaw accounts.
+    pub freezer: AccountId,
+}
+
+/// Symbol of the token wrapping the native currency.
+pub const WRAPPED_NATIVE_SYMBOL: &[u8] = b"WNATIVE";
+
 pub trait Trait: balances::Trait {
     type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+    /// The token module id, used for deriving the account holding wrapped native currency.
+    type ModuleId: Get<ModuleId>;
+    /// Convert the block number into a balance, for computing vested amounts.
+    type BlockNumberToBalance: Convert<Self::BlockNumber, Self::Balance>;
+    /// Weights of the benchmarked calls.
+    type WeightInfo: WeightInfo;
+}
+
+/// Weights of the benchmarked calls, generated by `benchmarking`.
+pub trait WeightInfo {
+    fn issue() -> Weight;
+    fn issue_mintable() -> Weight;
+    fn transfer() -> Weight;
+}
+
+impl WeightInfo for () {
+    fn issue() -> Weight {
+        200_000
+    }
+    fn issue_mintable() -> Weight {
+        200_000
+    }
+    fn transfer() -> Weight {
+        200_000
+    }
 }
 
 decl_error! {
@@ -52,6 +110,32 @@ decl_error! {
         SenderHaveNoToken,
         /// Memo length exceed limitation
         MemoLengthExceedLimitation,
+        /// Amount is zero
+        AmountIsZero,
+        /// Native currency has never been wrapped
+        WrappedNativeTokenNotFound,
+        /// Sender is not the owner of the token
+        NotTokenOwner,
+        /// Sender is not the issuer of the token
+        NotTokenIssuer,
+        /// Sender is not the admin of the token
+        NotTokenAdmin,
+        /// Sender is not the freezer of the token
+        NotTokenFreezer,
+        /// Sender is not the pending owner of the token
+        NotPendingOwner,
+        /// Token was not issued as mintable
+        TokenNotMintable,
+        /// Vesting schedule releases nothing per block
+        InvalidVestingSchedule,
+        /// Account already has a vesting schedule for the token
+        ExistingVestingSchedule,
+        /// Account has no vesting schedule for the token
+        NotVesting,
+        /// Transfers of the token are paused
+        TokenPaused,
+        /// Account is frozen for the token
+        AccountFrozen,
     }
 }
 
@@ -66,6 +150,19 @@ decl_event!(
         Transferd(AccountId, AccountId, Hash, Balance),
         Freezed(AccountId, Hash, Balance),
         UnFreezed(AccountId, Hash, Balance),
+        Wrapped(AccountId, Hash, Balance),
+        Unwrapped(AccountId, Hash, Balance),
+        TokenPaused(AccountId, Hash),
+        TokenUnpaused(AccountId, Hash),
+        AccountFrozen(AccountId, Hash, AccountId),
+        AccountThawed(AccountId, Hash, AccountId),
+        Minted(AccountId, Hash, AccountId, Balance),
+        OwnershipTransferProposed(AccountId, Hash, AccountId),
+        OwnershipTransferred(AccountId, Hash, AccountId),
+        /// Owner, token, issuer, admin, freezer
+        RolesSet(AccountId, Hash, AccountId, AccountId, AccountId),
+        VestingScheduleAdded(AccountId, Hash, AccountId, Balance),
+        Vested(AccountId, Hash, Balance),
     }
 );
 
@@ -73,6 +170,10 @@ decl_storage! {
     trait Store for Module<T: Trait> as TokenModule {
         Tokens get(fn token): map hasher(blake2_128_concat) T::Hash => Option<Token<T::Hash, T::Balance>>;
         Owners get(fn owner): map hasher(blake2_128_concat) T::Hash => Option<T::AccountId>;
+        /// TokenHash => account proposed as the next owner
+        PendingOwners get(fn pending_owner): map hasher(blake2_128_concat) T::Hash => Option<T::AccountId>;
+        /// TokenHash => administrative roles of the token
+        Roles get(fn roles): map hasher(blake2_128_concat) T::Hash => Option<TokenRoles<T::AccountId>>;
 
         BalanceOf get(fn balance_of): map hasher(blake2_128_concat) (T::AccountId, T::Hash) => T::Balance;
         FreeBalanceOf get(fn free_balance_of): map hasher(blake2_128_concat) (T::AccountId, T::Hash) => T::Balance;
@@ -83,7 +184,23 @@ decl_storage! {
         /// Index of tokens
         TokenIndex get(fn token_index): u64;
 
+        /// (AccountId, TokenHash) => vesting schedule of the balance locked apart from the frozen balance
+        Vesting get(fn vesting): map hasher(blake2_128_concat) (T::AccountId, T::Hash) => Option<VestingSchedule<T::Balance, T::BlockNumber>>;
+
+        /// TokenHash => whether the issuer may mint new supply, chosen when the token is issued
+        MintableTokens get(fn is_mintable): map hasher(blake2_128_concat) T::Hash => bool;
+        /// TokenHash => whether transfers of the token are paused
+        PausedTokens get(fn is_paused): map hasher(blake2_128_concat) T::Hash => bool;
+        /// (TokenHash, AccountId) => whether the account is blocked from moving the token
+        FrozenAccounts get(fn is_frozen): map hasher(blake2_128_concat) (T::Hash, T::AccountId) => bool;
+
+        /// Hash of the token wrapping the native currency
+        WrappedNativeTokenHash get(fn wrapped_native_token_hash): Option<T::Hash>;
+
         Nonce get(fn nonce): u64;
+
+        /// Release of the storage layout, for running the migrations it is missing
+        StorageVersion get(fn storage_version) build(|_: &GenesisConfig| Releases::LATEST): Releases;
     }
 }
 
@@ -93,7 +210,12 @@ decl_module! {
 
         type Error = Error<T>;
 
-        #[weight = 200_000]
+        fn on_runtime_upgrade() -> Weight {
+            migrations::migrate::<T>()
+        }
+
+        /// Issue `total_supply` of a new token to the sender, with a supply fixed for good.
+        #[weight = T::WeightInfo::issue()]
         pub fn issue(origin, symbol: Vec<u8>, total_supply: T::Balance) -> dispatch::DispatchResult {
             let sender = ensure_signed(origin)?;
 
@@ -103,7 +225,20 @@ decl_module! {
             Ok(())
         }
 
-        #[weight = 200_000]
+        /// Issue `total_supply` of a new token to the sender, whose issuer may mint more of it
+        /// with `mint`.
+        #[weight = T::WeightInfo::issue_mintable()]
+        pub fn issue_mintable(origin, symbol: Vec<u8>, total_supply: T::Balance) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+
+            let hash = Self::do_issue(sender.clone(), symbol, total_supply, TokenType::Normal)?;
+            MintableTokens::<T>::insert(hash, true);
+            Self::deposit_event(RawEvent::Issued(sender, hash.clone(), total_supply));
+
+            Ok(())
+        }
+
+        #[weight = T::WeightInfo::transfer()]
         pub fn transfer(origin, token_hash: T::Hash, to: T::AccountId, amount: T::Balance, memo: Option<Vec<u8>>)
             -> dispatch::DispatchResult {
             let sender = ensure_signed(origin)?;
@@ -113,6 +248,157 @@ decl_module! {
 
             Ok(())
         }
+
+        #[weight = 200_000]
+        #[transactional]
+        pub fn wrap(origin, amount: T::Balance) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+
+            let hash = Self::do_wrap(sender.clone(), amount)?;
+            Self::deposit_event(RawEvent::Wrapped(sender, hash, amount));
+
+            Ok(())
+        }
+
+        #[weight = 200_000]
+        #[transactional]
+        pub fn unwrap(origin, amount: T::Balance) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+
+            let hash = Self::do_unwrap(sender.clone(), amount)?;
+            Self::deposit_event(RawEvent::Unwrapped(sender, hash, amount));
+
+            Ok(())
+        }
+
+        #[weight = 200_000]
+        pub fn pause_token(origin, token_hash: T::Hash) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+            Self::ensure_admin(&sender, token_hash)?;
+
+            PausedTokens::<T>::insert(token_hash, true);
+            Self::deposit_event(RawEvent::TokenPaused(sender, token_hash));
+
+            Ok(())
+        }
+
+        #[weight = 200_000]
+        pub fn unpause_token(origin, token_hash: T::Hash) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+            Self::ensure_admin(&sender, token_hash)?;
+
+            PausedTokens::<T>::remove(token_hash);
+            Self::deposit_event(RawEvent::TokenUnpaused(sender, token_hash));
+
+            Ok(())
+        }
+
+        #[weight = 200_000]
+        pub fn freeze_account(origin, token_hash: T::Hash, who: T::AccountId) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+            Self::ensure_freezer(&sender, token_hash)?;
+
+            FrozenAccounts::<T>::insert((token_hash, who.clone()), true);
+            Self::deposit_event(RawEvent::AccountFrozen(sender, token_hash, who));
+
+            Ok(())
+        }
+
+        #[weight = 200_000]
+        pub fn thaw_account(origin, token_hash: T::Hash, who: T::AccountId) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+            Self::ensure_freezer(&sender, token_hash)?;
+
+            FrozenAccounts::<T>::remove((token_hash, who.clone()));
+            Self::deposit_event(RawEvent::AccountThawed(sender, token_hash, who));
+
+            Ok(())
+        }
+
+        #[weight = 200_000]
+        pub fn mint(origin, token_hash: T::Hash, to: T::AccountId, amount: T::Balance) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+            Self::ensure_issuer(&sender, token_hash)?;
+
+            ensure!(Self::is_mintable(token_hash), Error::<T>::TokenNotMintable);
+            ensure!(amount > Zero::zero(), Error::<T>::AmountIsZero);
+
+            Self::do_mint(to.clone(), token_hash, amount)?;
+            Self::deposit_event(RawEvent::Minted(sender, token_hash, to, amount));
+
+            Ok(())
+        }
+
+        /// Transfer `schedule.locked` of a token the sender owns to `to`, locked until released
+        /// block by block.
+        #[weight = 200_000]
+        #[transactional]
+        pub fn vested_transfer(origin, token_hash: T::Hash, to: T::AccountId, schedule: VestingSchedule<T::Balance, T::BlockNumber>)
+            -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+
+            Self::do_vested_transfer(sender, to, token_hash, schedule)
+        }
+
+        /// Unlock the vested part of the sender's vesting schedule.
+        #[weight = 200_000]
+        pub fn vest(origin, token_hash: T::Hash) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+
+            Self::do_vest(sender, token_hash)
+        }
+
+        /// Propose `new_owner` as the owner of the token, who has to accept it.
+        #[weight = 200_000]
+        pub fn transfer_ownership(origin, token_hash: T::Hash, new_owner: T::AccountId) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+            Self::ensure_owner(&sender, token_hash)?;
+
+            PendingOwners::<T>::insert(token_hash, new_owner.clone());
+            Self::deposit_event(RawEvent::OwnershipTransferProposed(sender, token_hash, new_owner));
+
+            Ok(())
+        }
+
+        /// Become the owner of the token, taking over every role from the previous owner.
+        #[weight = 200_000]
+        pub fn accept_ownership(origin, token_hash: T::Hash) -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+
+            ensure!(
+                Self::pending_owner(token_hash).as_ref() == Some(&sender),
+                Error::<T>::NotPendingOwner
+            );
+            let old_owner = Self::owner(token_hash).ok_or(Error::<T>::NoMatchingToken)?;
+
+            PendingOwners::<T>::remove(token_hash);
+            Owners::<T>::insert(token_hash, sender.clone());
+            Roles::<T>::insert(token_hash, TokenRoles {
+                issuer: sender.clone(),
+                admin: sender.clone(),
+                freezer: sender.clone(),
+            });
+            Self::deposit_event(RawEvent::RolesSet(sender.clone(), token_hash, sender.clone(), sender.clone(), sender.clone()));
+            Self::deposit_event(RawEvent::OwnershipTransferred(old_owner, token_hash, sender));
+
+            Ok(())
+        }
+
+        #[weight = 200_000]
+        pub fn set_roles(origin, token_hash: T::Hash, issuer: T::AccountId, admin: T::AccountId, freezer: T::AccountId)
+            -> dispatch::DispatchResult {
+            let sender = ensure_signed(origin)?;
+            Self::ensure_owner(&sender, token_hash)?;
+
+            Roles::<T>::insert(token_hash, TokenRoles {
+                issuer: issuer.clone(),
+                admin: admin.clone(),
+                freezer: freezer.clone(),
+            });
+            Self::deposit_event(RawEvent::RolesSet(sender, token_hash, issuer, admin, freezer));
+
+            Ok(())
+        }
     }
 }
 
@@ -139,6 +425,14 @@ impl<T: Trait> Module<T> {
         Nonce::mutate(|n| *n += 1);
         Tokens::<T>::insert(hash.clone(), token);
         Owners::<T>::insert(hash.clone(), sender.clone());
+        Roles::<T>::insert(
+            hash.clone(),
+            TokenRoles {
+                issuer: sender.clone(),
+                admin: sender.clone(),
+                freezer: sender.clone(),
+            },
+        );
         BalanceOf::<T>::insert((sender.clone(), hash.clone()), total_supply);
         FreeBalanceOf::<T>::insert((sender.clone(), hash.clone()), total_supply);
 
@@ -163,37 +457,223 @@ impl<T: Trait> Module<T> {
             ensure!(memo.len() <= 512, Error::<T>::MemoLengthExceedLimitation);
         }
 
+        ensure!(!Self::is_paused(hash), Error::<T>::TokenPaused);
+        ensure!(
+            !Self::is_frozen((hash, sender.clone())),
+            Error::<T>::AccountFrozen
+        );
+        ensure!(
+            !Self::is_frozen((hash, to.clone())),
+            Error::<T>::AccountFrozen
+        );
+
         ensure!(
             <FreeBalanceOf<T>>::contains_key((sender.clone(), hash)),
             Error::<T>::SenderHaveNoToken
         );
 
-        let from_amount = Self::balance_of((sender.clone(), hash.clone()));
-        ensure!(from_amount >= amount, Error::<T>::BalanceNotEnough);
-        let new_from_amount = from_amount - amount;
+        let new_from_amount = Self::balance_of((sender.clone(), hash.clone()))
+            .checked_sub(&amount)
+            .ok_or(Error::<T>::BalanceNotEnough)?;
+        let new_from_free_amount = Self::free_balance_of((sender.clone(), hash.clone()))
+            .checked_sub(&amount)
+            .ok_or(Error::<T>::BalanceNotEnough)?;
+
+        // the balances of `to` below are read before the sender is debited
+        if sender == to {
+            return Ok(());
+        }
 
-        let from_free_amount = Self::free_balance_of((sender.clone(), hash.clone()));
-        ensure!(from_free_amount >= amount, Error::<T>::BalanceNotEnough);
-        let new_from_free_amount = from_free_amount - amount;
+        let new_to_amount = Self::balance_of((to.clone(), hash.clone()))
+            .checked_add(&amount)
+            .ok_or(Error::<T>::AmountOverflow)?;
+        let new_to_free_amount = Self::free_balance_of((to.clone(), hash.clone()))
+            .checked_add(&amount)
+            .ok_or(Error::<T>::AmountOverflow)?;
 
-        let to_amount = Self::balance_of((to.clone(), hash.clone()));
-        let new_to_amount = to_amount + amount;
+        BalanceOf::<T>::insert((sender.clone(), hash.clone()), new_from_amount);
+        FreeBalanceOf::<T>::insert((sender.clone(), hash.clone()), new_from_free_amount);
+        BalanceOf::<T>::insert((to.clone(), hash.clone()), new_to_amount);
+        FreeBalanceOf::<T>::insert((to.clone(), hash.clone()), new_to_free_amount);
+
+        Ok(())
+    }
+
+    fn ensure_owner(sender: &T::AccountId, hash: T::Hash) -> dispatch::DispatchResult {
+        ensure!(Tokens::<T>::contains_key(hash), Error::<T>::NoMatchingToken);
         ensure!(
-            new_to_amount <= T::Balance::max_value(),
-            Error::<T>::AmountOverflow
+            Self::owner(hash).as_ref() == Some(sender),
+            Error::<T>::NotTokenOwner
         );
 
-        let to_free_amount = Self::free_balance_of((to.clone(), hash.clone()));
-        let new_to_free_amount = to_free_amount + amount;
+        Ok(())
+    }
+
+    fn ensure_issuer(sender: &T::AccountId, hash: T::Hash) -> dispatch::DispatchResult {
+        let roles = Self::roles(hash).ok_or(Error::<T>::NoMatchingToken)?;
+        ensure!(&roles.issuer == sender, Error::<T>::NotTokenIssuer);
+
+        Ok(())
+    }
+
+    fn ensure_admin(sender: &T::AccountId, hash: T::Hash) -> dispatch::DispatchResult {
+        let roles = Self::roles(hash).ok_or(Error::<T>::NoMatchingToken)?;
+        ensure!(&roles.admin == sender, Error::<T>::NotTokenAdmin);
+
+        Ok(())
+    }
+
+    fn ensure_freezer(sender: &T::AccountId, hash: T::Hash) -> dispatch::DispatchResult {
+        let roles = Self::roles(hash).ok_or(Error::<T>::NoMatchingToken)?;
+        ensure!(&roles.freezer == sender, Error::<T>::NotTokenFreezer);
+
+        Ok(())
+    }
+
+    /// The account holding the native currency backing the wrapped native token.
+    pub fn account_id() -> T::AccountId {
+        T::ModuleId::get().into_account()
+    }
+
+    /// Lock `amount` of native currency from `sender` and mint the same amount of the
+    /// wrapped native token to it, issuing the wrapped native token on first use.
+    pub fn do_wrap(
+        sender: T::AccountId,
+        amount: T::Balance,
+    ) -> Result<T::Hash, dispatch::DispatchError> {
+        ensure!(amount > Zero::zero(), Error::<T>::AmountIsZero);
+
+        let hash = match Self::wrapped_native_token_hash() {
+            Some(hash) => hash,
+            None => {
+                let hash = Self::do_issue(
+                    Self::account_id(),
+                    WRAPPED_NATIVE_SYMBOL.to_vec(),
+                    Zero::zero(),
+                    TokenType::WrappedNative,
+                )?;
+                WrappedNativeTokenHash::<T>::put(hash);
+                hash
+            }
+        };
+
+        let token = Self::token(hash).ok_or(Error::<T>::NoMatchingToken)?;
         ensure!(
-            new_to_free_amount <= T::Balance::max_value(),
+            token.total_supply.checked_add(&amount).is_some(),
             Error::<T>::AmountOverflow
         );
 
-        BalanceOf::<T>::insert((sender.clone(), hash.clone()), new_from_amount);
-        FreeBalanceOf::<T>::insert((sender.clone(), hash.clone()), new_from_free_amount);
-        BalanceOf::<T>::insert((to.clone(), hash.clone()), new_to_amount);
-        FreeBalanceOf::<T>::insert((to.clone(), hash.clone()), new_to_free_amount);
+        <balances::Module<T> as Currency<T::AccountId>>::transfer(
+            &sender,
+            &Self::account_id(),
+            amount,
+            ExistenceRequirement::AllowDeath,
+        )?;
+        Self::do_mint(sender, hash, amount)?;
+
+        Ok(hash)
+    }
+
+    /// Burn `amount` of the wrapped native token from `sender` and release the same amount
+    /// of native currency to it.
+    pub fn do_unwrap(
+        sender: T::AccountId,
+        amount: T::Balance,
+    ) -> Result<T::Hash, dispatch::DispatchError> {
+        ensure!(amount > Zero::zero(), Error::<T>::AmountIsZero);
+
+        let hash =
+            Self::wrapped_native_token_hash().ok_or(Error::<T>::WrappedNativeTokenNotFound)?;
+        Self::ensure_free_balance(sender.clone(), hash, amount)?;
+
+        <balances::Module<T> as Currency<T::AccountId>>::transfer(
+            &Self::account_id(),
+            &sender,
+            amount,
+            ExistenceRequirement::AllowDeath,
+        )?;
+        Self::do_burn(sender, hash, amount)?;
+
+        Ok(hash)
+    }
+
+    /// Burn `amount` of the wrapped native token of `who` and withdraw the native currency
+    /// backing it, for paying a transaction fee with wrapped native currency. The backing
+    /// account is kept alive, as reaping it would take the dust backing the wrapped native
+    /// token of other holders with it, so withdrawing below the existential deposit fails.
+    #[transactional]
+    pub fn withdraw_wrapped(
+        who: &T::AccountId,
+        amount: T::Balance,
+    ) -> Result<balances::NegativeImbalance<T>, dispatch::DispatchError> {
+        let hash =
+            Self::wrapped_native_token_hash().ok_or(Error::<T>::WrappedNativeTokenNotFound)?;
+        Self::do_burn(who.clone(), hash, amount)?;
+
+        <balances::Module<T> as Currency<T::AccountId>>::withdraw(
+            &Self::account_id(),
+            amount,
+            WithdrawReason::TransactionPayment.into(),
+            ExistenceRequirement::KeepAlive,
+        )
+    }
+
+    /// Put `amount` of native currency back into the account backing the wrapped native token
+    /// and mint as much wrapped native token to `who`, for refunding a transaction fee.
+    #[transactional]
+    pub fn deposit_wrapped(
+        who: &T::AccountId,
+        amount: T::Balance,
+    ) -> Result<balances::PositiveImbalance<T>, dispatch::DispatchError> {
+        let hash =
+            Self::wrapped_native_token_hash().ok_or(Error::<T>::WrappedNativeTokenNotFound)?;
+        Self::do_mint(who.clone(), hash, amount)?;
+
+        <balances::Module<T> as Currency<T::AccountId>>::deposit_into_existing(
+            &Self::account_id(),
+            amount,
+        )
+    }
+
+    fn do_mint(to: T::AccountId, hash: T::Hash, amount: T::Balance) -> dispatch::DispatchResult {
+        let mut token = Self::token(hash).ok_or(Error::<T>::NoMatchingToken)?;
+
+        token.total_supply = token
+            .total_supply
+            .checked_add(&amount)
+            .ok_or(Error::<T>::AmountOverflow)?;
+        let balance = Self::balance_of((to.clone(), hash))
+            .checked_add(&amount)
+            .ok_or(Error::<T>::AmountOverflow)?;
+        let free_balance = Self::free_balance_of((to.clone(), hash))
+            .checked_add(&amount)
+            .ok_or(Error::<T>::AmountOverflow)?;
+
+        BalanceOf::<T>::insert((to.clone(), hash), balance);
+        FreeBalanceOf::<T>::insert((to, hash), free_balance);
+        Tokens::<T>::insert(hash, token);
+
+        Ok(())
+    }
+
+    fn do_burn(from: T::AccountId, hash: T::Hash, amount: T::Balance) -> dispatch::DispatchResult {
+        let mut token = Self::token(hash).ok_or(Error::<T>::NoMatchingToken)?;
+        Self::ensure_free_balance(from.clone(), hash, amount)?;
+
+        token.total_supply = token
+            .total_supply
+            .checked_sub(&amount)
+            .ok_or(Error::<T>::BalanceNotEnough)?;
+        let balance = Self::balance_of((from.clone(), hash))
+            .checked_sub(&amount)
+            .ok_or(Error::<T>::BalanceNotEnough)?;
+        let free_balance = Self::free_balance_of((from.clone(), hash))
+            .checked_sub(&amount)
+            .ok_or(Error::<T>::BalanceNotEnough)?;
+
+        BalanceOf::<T>::insert((from.clone(), hash), balance);
+        FreeBalanceOf::<T>::insert((from, hash), free_balance);
+        Tokens::<T>::insert(hash, token);
 
         Ok(())
     }
@@ -211,17 +691,15 @@ impl<T: Trait> Module<T> {
             Error::<T>::SenderHaveNoToken
         );
 
-        let old_free_amount = Self::free_balance_of((sender.clone(), hash.clone()));
-        ensure!(old_free_amount >= amount, Error::<T>::BalanceNotEnough);
+        let new_free_amount = Self::free_balance_of((sender.clone(), hash.clone()))
+            .checked_sub(&amount)
+            .ok_or(Error::<T>::BalanceNotEnough)?;
+        let new_freezed_amount = Self::freezed_balance_of((sender.clone(), hash.clone()))
+            .checked_add(&amount)
+            .ok_or(Error::<T>::AmountOverflow)?;
 
-        let old_freezed_amount = Self::freezed_balance_of((sender.clone(), hash.clone()));
-        ensure!(
-            old_freezed_amount + amount <= T::Balance::max_value(),
-            Error::<T>::AmountOverflow
-        );
-
-        FreeBalanceOf::<T>::insert((sender.clone(), hash.clone()), old_free_amount - amount);
-        FreezedBalanceOf::<T>::insert((sender.clone(), hash.clone()), old_freezed_amount + amount);
+        FreeBalanceOf::<T>::insert((sender.clone(), hash.clone()), new_free_amount);
+        FreezedBalanceOf::<T>::insert((sender.clone(), hash.clone()), new_freezed_amount);
 
         Self::deposit_event(RawEvent::Freezed(sender, hash, amount));
 
@@ -241,17 +719,15 @@ impl<T: Trait> Module<T> {
             Error::<T>::SenderHaveNoToken
         );
 
-        let old_freezed_amount = Self::freezed_balance_of((sender.clone(), hash.clone()));
-        ensure!(old_freezed_amount >= amount, Error::<T>::BalanceNotEnough);
-
-        let old_free_amount = Self::free_balance_of((sender.clone(), hash.clone()));
-        ensure!(
-            old_free_amount + amount <= T::Balance::max_value(),
-            Error::<T>::AmountOverflow
-        );
+        let new_freezed_amount = Self::freezed_balance_of((sender.clone(), hash.clone()))
+            .checked_sub(&amount)
+            .ok_or(Error::<T>::BalanceNotEnough)?;
+        let new_free_amount = Self::free_balance_of((sender.clone(), hash.clone()))
+            .checked_add(&amount)
+            .ok_or(Error::<T>::AmountOverflow)?;
 
-        FreeBalanceOf::<T>::insert((sender.clone(), hash.clone()), old_free_amount + amount);
-        FreezedBalanceOf::<T>::insert((sender.clone(), hash.clone()), old_freezed_amount - amount);
+        FreeBalanceOf::<T>::insert((sender.clone(), hash.clone()), new_free_amount);
+        FreezedBalanceOf::<T>::insert((sender.clone(), hash.clone()), new_freezed_amount);
 
         Self::deposit_event(RawEvent::UnFreezed(sender, hash, amount));
 
@@ -277,3 +753,90 @@ impl<T: Trait> Module<T> {
         Ok(())
     }
 }
+
+impl<T: Trait> MultiCurrency<T::AccountId> for Module<T> {
+    type CurrencyId = T::Hash;
+    type Balance = T::Balance;
+
+    fn exists(currency_id: Self::CurrencyId) -> bool {
+        Tokens::<T>::contains_key(currency_id)
+    }
+
+    fn total_balance(currency_id: Self::CurrencyId, who: &T::AccountId) -> Self::Balance {
+        Self::balance_of((who.clone(), currency_id))
+    }
+
+    fn free_balance(currency_id: Self::CurrencyId, who: &T::AccountId) -> Self::Balance {
+        Self::free_balance_of((who.clone(), currency_id))
+    }
+
+    fn ensure_can_withdraw(
+        currency_id: Self::CurrencyId,
+        who: &T::AccountId,
+        amount: Self::Balance,
+    ) -> dispatch::DispatchResult {
+        Self::ensure_free_balance(who.clone(), currency_id, amount)
+    }
+
+    fn transfer(
+        currency_id: Self::CurrencyId,
+        from: &T::AccountId,
+        to: &T::AccountId,
+        amount: Self::Balance,
+    ) -> dispatch::DispatchResult {
+        Self::do_transfer(from.clone(), to.clone(), currency_id, amount, None)
+    }
+}
+
+impl<T: Trait> MultiReservableCurrency<T::AccountId> for Module<T> {
+    fn can_reserve(
+        currency_id: Self::CurrencyId,
+        who: &T::AccountId,
+        value: Self::Balance,
+    ) -> bool {
+        Self::ensure_free_balance(who.clone(), currency_id, value).is_ok()
+    }
+
+    fn reserved_balance(currency_id: Self::CurrencyId, who: &T::AccountId) -> Self::Balance {
+        Self::freezed_balance_of((who.clone(), currency_id))
+    }
+
+    fn reserve(
+        currency_id: Self::CurrencyId,
+        who: &T::AccountId,
+        value: Self::Balance,
+    ) -> dispatch::DispatchResult {
+        Self::do_freeze(who.clone(), currency_id, value)
+    }
+
+    fn unreserve(
+        currency_id: Self::CurrencyId,
+        who: &T::AccountId,
+        value: Self::Balance,
+    ) -> dispatch::DispatchResult {
+        Self::do_unfreeze(who.clone(), currency_id, value)
+    }
+}
+
+impl<T: Trait> MultiAsset<T::AccountId> for Module<T> {
+    fn symbol(currency_id: Self::CurrencyId) -> Option<Vec<u8>> {
+        Self::token(currency_id).map(|token| token.symbol)
+    }
+
+    fn owner(currency_id: Self::CurrencyId) -> Option<T::AccountId> {
+        Owners::<T>::get(currency_id)
+    }
+
+    fn is_paused(currency_id: Self::CurrencyId) -> bool {
+        PausedTokens::<T>::get(currency_id)
+    }
+
+    fn issue(
+        who: &T::AccountId,
+        symbol: Vec<u8>,
+        total_supply: Self::Balance,
+        ttype: TokenType,
+    ) -> Result<Self::CurrencyId, dispatch::DispatchError> {
+        Self::do_issue(who.clone(), symbol, total_supply, ttype)
+    }
+}
@@ -0,0 +1,86 @@
+//! Storage migrations of the token module.
+//!
+//! The release of the storage layout is kept in `StorageVersion`. On a runtime upgrade, every
+//! migration of the registry newer than that release runs in order, and the release is bumped
+//! after each one.
+
+use codec::{Decode, Encode};
+use frame_support::{
+    storage::IterableStorageMap, traits::Get, weights::Weight, StorageMap, StorageValue,
+};
+use sp_runtime::RuntimeDebug;
+use sp_std::prelude::*;
+
+use crate::{Owners, Roles, StorageVersion, TokenRoles, Tokens, Trait};
+
+/// Releases of the storage layout, oldest first.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, RuntimeDebug)]
+pub enum Releases {
+    /// Tokens administered by their owner alone. Storage written before the release was tracked
+    /// decodes as this one.
+    V1,
+    /// Tokens with an issuer, an admin and a freezer.
+    V2,
+}
+
+impl Releases {
+    /// Release of the storage layout written by this code.
+    pub const LATEST: Releases = Releases::V2;
+}
+
+impl Default for Releases {
+    fn default() -> Self {
+        Releases::V1
+    }
+}
+
+/// Migrations in order, each upgrading the storage from the release before to its release.
+pub fn migrations<T: Trait>() -> Vec<(Releases, fn() -> Weight)> {
+    vec![(Releases::V2, v2::migrate::<T>)]
+}
+
+/// Run the migrations the storage is missing and return their weight.
+pub fn migrate<T: Trait>() -> Weight {
+    let mut release = StorageVersion::get();
+    let mut weight = T::DbWeight::get().reads(1);
+
+    for (next, migration) in migrations::<T>() {
+        if release < next {
+            weight = weight.saturating_add(migration());
+            release = next;
+            StorageVersion::put(release);
+            weight = weight.saturating_add(T::DbWeight::get().writes(1));
+        }
+    }
+
+    weight
+}
+
+/// Release V2: hand every role of the tokens of release V1 to their owner. Their supply was
+/// fixed, so they are not mintable.
+pub mod v2 {
+    use super::*;
+
+    pub fn migrate<T: Trait>() -> Weight {
+        let hashes: Vec<T::Hash> = Tokens::<T>::iter().map(|(hash, _)| hash).collect();
+        let mut writes: Weight = 0;
+        for hash in hashes.iter() {
+            if Roles::<T>::contains_key(hash) {
+                continue;
+            }
+            if let Some(owner) = Owners::<T>::get(hash) {
+                Roles::<T>::insert(
+                    hash,
+                    TokenRoles {
+                        issuer: owner.clone(),
+                        admin: owner.clone(),
+                        freezer: owner,
+                    },
+                );
+                writes += 1;
+            }
+        }
+
+        T::DbWeight::get().reads_writes(3 * hashes.len() as Weight, writes)
+    }
+}
@@ -1,10 +1,15 @@
 use crate::{Module, Trait};
-use frame_support::{impl_outer_origin, parameter_types, weights::Weight};
+use codec::Encode;
+use frame_support::{
+	impl_outer_event, impl_outer_origin, parameter_types, weights::Weight, Blake2_128Concat,
+	StorageHasher,
+};
 use sp_core::H256;
+use sp_io::hashing::twox_128;
 use sp_runtime::{
 	testing::Header,
-	traits::{BlakeTwo256, IdentityLookup},
-	Perbill,
+	traits::{BlakeTwo256, ConvertInto, IdentityLookup},
+	ModuleId, Perbill,
 };
 
 use frame_system as system;
@@ -14,6 +19,18 @@ impl_outer_origin! {
 	pub enum Origin for Test where system = frame_system {}
 }
 
+mod token {
+	pub use crate::Event;
+}
+
+impl_outer_event! {
+	pub enum TestEvent for Test {
+		system<T>,
+		balances<T>,
+		token<T>,
+	}
+}
+
 #[derive(Clone, Eq, PartialEq)]
 pub struct Test;
 parameter_types! {
@@ -34,7 +51,7 @@ impl system::Trait for Test {
 	type AccountId = u64;
 	type Lookup = IdentityLookup<Self::AccountId>;
 	type Header = Header;
-	type Event = ();
+	type Event = TestEvent;
 	type BlockHashCount = BlockHashCount;
 	type MaximumBlockWeight = MaximumBlockWeight;
 	type DbWeight = ();
@@ -60,14 +77,21 @@ impl balances::Trait for Test {
 	type Balance = u128;
 	type MaxLocks = MaxLocks;
 	type DustRemoval = ();
-	type Event = ();
+	type Event = TestEvent;
 	type ExistentialDeposit = ExistentialDeposit;
 	type AccountStore = system::Module<Test>;
 	type WeightInfo = ();
 }
 
+parameter_types! {
+	pub const TokenModuleId: ModuleId = ModuleId(*b"py/token");
+}
+
 impl Trait for Test {
-	type Event = ();
+	type Event = TestEvent;
+	type ModuleId = TokenModuleId;
+	type BlockNumberToBalance = ConvertInto;
+	type WeightInfo = ();
 }
 
 pub type TokenModule = Module<Test>;
@@ -75,8 +99,54 @@ pub type System = system::Module<Test>;
 pub type Balances = balances::Module<Test>;
 
 pub fn new_test_ext() -> sp_io::TestExternalities {
-	system::GenesisConfig::default()
+	let mut storage = system::GenesisConfig::default()
+		.build_storage::<Test>()
+		.unwrap();
+	crate::GenesisConfig::default()
+		.assimilate_storage::<Test>(&mut storage)
+		.unwrap();
+	storage.into()
+}
+
+/// Storage of a chain upgraded from an earlier runtime: the system genesis and the raw
+/// `snapshot` entries, without any release recorded by the pallet.
+pub fn new_test_ext_from_snapshot(snapshot: Vec<(Vec<u8>, Vec<u8>)>) -> sp_io::TestExternalities {
+	let mut storage = system::GenesisConfig::default()
 		.build_storage::<Test>()
-		.unwrap()
-		.into()
+		.unwrap();
+	storage.top.extend(snapshot);
+	storage.into()
+}
+
+/// Raw key of `key` in the `item` map of the token module, hashed with `blake2_128_concat`.
+pub fn map_key<K: Encode>(item: &[u8], key: &K) -> Vec<u8> {
+	let mut raw = twox_128(b"TokenModule").to_vec();
+	raw.extend_from_slice(&twox_128(item));
+	raw.extend(Blake2_128Concat::hash(&key.encode()));
+	raw
+}
+
+/// Events deposited so far by the token module, oldest first.
+pub fn token_events() -> Vec<crate::Event<Test>> {
+	System::events()
+		.into_iter()
+		.filter_map(|record| match record.event {
+			TestEvent::token(event) => Some(event),
+			_ => None,
+		})
+		.collect()
+}
+
+pub fn assert_last_event<E: Into<TestEvent>>(event: E) {
+	let record = System::events().pop().expect("an event was deposited");
+	assert_eq!(record.event, event.into());
+}
+
+pub fn assert_has_event<E: Into<TestEvent>>(event: E) {
+	let event = event.into();
+	assert!(
+		System::events().iter().any(|record| record.event == event),
+		"{:?} was not deposited",
+		event
+	);
 }
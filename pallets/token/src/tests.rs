@@ -1,7 +1,11 @@
-use crate::{mock::*, Error};
+use crate::{
+    migrations, mock::*, BalanceOf, Error, FreeBalanceOf, MultiCurrency, MultiReservableCurrency,
+    RawEvent, Releases, Token, TokenRoles, TokenType, VestingSchedule,
+};
+use codec::Encode;
 use frame_support::{
-    assert_err, assert_ok,
-    traits::{OnFinalize, OnInitialize},
+    assert_err, assert_noop, assert_ok,
+    traits::{Currency, OnFinalize, OnInitialize, OnRuntimeUpgrade},
 };
 use sp_core::H256;
 
@@ -36,7 +40,7 @@ fn token_tests() {
         assert_ok!(TokenModule::issue(
             Origin::signed(alice),
             b"6688".to_vec(),
-            21000000,
+            21000000
         ));
 
         let token_hash = TokenModule::token_hash_by_index(0);
@@ -96,3 +100,1089 @@ fn token_tests() {
         );
     });
 }
+
+#[test]
+fn multi_currency_tests() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        let alice = 10u64;
+        let bob = 20u64;
+
+        assert_ok!(TokenModule::issue(
+            Origin::signed(alice),
+            b"6688".to_vec(),
+            1000
+        ));
+        let token_hash = TokenModule::token_hash_by_index(0).unwrap();
+
+        assert!(<TokenModule as MultiCurrency<u64>>::exists(token_hash));
+        assert!(!<TokenModule as MultiCurrency<u64>>::exists(
+            H256::from_low_u64_be(0)
+        ));
+
+        assert_ok!(<TokenModule as MultiCurrency<u64>>::transfer(
+            token_hash, &alice, &bob, 100
+        ));
+        assert_eq!(
+            <TokenModule as MultiCurrency<u64>>::total_balance(token_hash, &alice),
+            900
+        );
+        assert_eq!(
+            <TokenModule as MultiCurrency<u64>>::total_balance(token_hash, &bob),
+            100
+        );
+
+        assert!(TokenModule::can_reserve(token_hash, &bob, 100));
+        assert!(!TokenModule::can_reserve(token_hash, &bob, 101));
+        assert_ok!(TokenModule::reserve(token_hash, &bob, 60));
+        assert_eq!(TokenModule::reserved_balance(token_hash, &bob), 60);
+        assert_eq!(TokenModule::free_balance(token_hash, &bob), 40);
+        assert_eq!(
+            <TokenModule as MultiCurrency<u64>>::total_balance(token_hash, &bob),
+            100
+        );
+
+        assert_err!(
+            TokenModule::ensure_can_withdraw(token_hash, &bob, 41),
+            Error::<Test>::BalanceNotEnough
+        );
+        assert_err!(
+            <TokenModule as MultiCurrency<u64>>::transfer(token_hash, &bob, &alice, 41),
+            Error::<Test>::BalanceNotEnough
+        );
+
+        assert_ok!(TokenModule::unreserve(token_hash, &bob, 60));
+        assert_eq!(TokenModule::reserved_balance(token_hash, &bob), 0);
+        assert_eq!(TokenModule::free_balance(token_hash, &bob), 100);
+    });
+}
+
+#[test]
+fn wrapped_native_tests() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        let alice = 10u64;
+        let _ = Balances::deposit_creating(&alice, 1000);
+
+        assert_err!(
+            TokenModule::unwrap(Origin::signed(alice), 100),
+            Error::<Test>::WrappedNativeTokenNotFound
+        );
+        assert_err!(
+            TokenModule::wrap(Origin::signed(alice), 0),
+            Error::<Test>::AmountIsZero
+        );
+
+        assert_ok!(TokenModule::wrap(Origin::signed(alice), 400));
+        let hash = TokenModule::wrapped_native_token_hash().unwrap();
+        let token = TokenModule::token(hash).unwrap();
+        assert_eq!(token.ttype, TokenType::WrappedNative);
+        assert_eq!(token.total_supply, 400);
+        assert_eq!(Balances::free_balance(&alice), 600);
+        assert_eq!(Balances::free_balance(&TokenModule::account_id()), 400);
+        assert_eq!(TokenModule::balance_of((alice, hash)), 400);
+        assert_eq!(TokenModule::free_balance_of((alice, hash)), 400);
+
+        // wrapping again reuses the same token
+        assert_ok!(TokenModule::wrap(Origin::signed(alice), 100));
+        assert_eq!(TokenModule::wrapped_native_token_hash(), Some(hash));
+        assert_eq!(TokenModule::token(hash).unwrap().total_supply, 500);
+        assert_eq!(TokenModule::balance_of((alice, hash)), 500);
+
+        assert_err!(
+            TokenModule::unwrap(Origin::signed(alice), 501),
+            Error::<Test>::BalanceNotEnough
+        );
+
+        assert_ok!(TokenModule::unwrap(Origin::signed(alice), 150));
+        assert_eq!(TokenModule::token(hash).unwrap().total_supply, 350);
+        assert_eq!(TokenModule::balance_of((alice, hash)), 350);
+        assert_eq!(Balances::free_balance(&alice), 650);
+        assert_eq!(Balances::free_balance(&TokenModule::account_id()), 350);
+    });
+}
+
+#[test]
+fn pause_and_freeze_tests() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        let alice = 10u64;
+        let bob = 20u64;
+        let charlie = 30u64;
+
+        assert_ok!(TokenModule::issue(
+            Origin::signed(alice),
+            b"6688".to_vec(),
+            1000
+        ));
+        let token_hash = TokenModule::token_hash_by_index(0).unwrap();
+        assert_ok!(TokenModule::transfer(
+            Origin::signed(alice),
+            token_hash,
+            bob,
+            100,
+            None
+        ));
+
+        assert_err!(
+            TokenModule::pause_token(Origin::signed(bob), token_hash),
+            Error::<Test>::NotTokenAdmin
+        );
+        assert_err!(
+            TokenModule::pause_token(Origin::signed(alice), H256::from_low_u64_be(0)),
+            Error::<Test>::NoMatchingToken
+        );
+
+        assert_ok!(TokenModule::pause_token(Origin::signed(alice), token_hash));
+        assert!(TokenModule::is_paused(token_hash));
+        assert_err!(
+            TokenModule::transfer(Origin::signed(bob), token_hash, charlie, 10, None),
+            Error::<Test>::TokenPaused
+        );
+        assert_err!(
+            TokenModule::transfer(Origin::signed(alice), token_hash, charlie, 10, None),
+            Error::<Test>::TokenPaused
+        );
+
+        assert_ok!(TokenModule::unpause_token(
+            Origin::signed(alice),
+            token_hash
+        ));
+        assert!(!TokenModule::is_paused(token_hash));
+        assert_ok!(TokenModule::transfer(
+            Origin::signed(bob),
+            token_hash,
+            charlie,
+            10,
+            None
+        ));
+
+        assert_err!(
+            TokenModule::freeze_account(Origin::signed(bob), token_hash, charlie),
+            Error::<Test>::NotTokenFreezer
+        );
+        assert_ok!(TokenModule::freeze_account(
+            Origin::signed(alice),
+            token_hash,
+            charlie
+        ));
+        assert!(TokenModule::is_frozen((token_hash, charlie)));
+        assert_err!(
+            TokenModule::transfer(Origin::signed(charlie), token_hash, bob, 1, None),
+            Error::<Test>::AccountFrozen
+        );
+        assert_err!(
+            TokenModule::transfer(Origin::signed(bob), token_hash, charlie, 1, None),
+            Error::<Test>::AccountFrozen
+        );
+        assert_ok!(TokenModule::transfer(
+            Origin::signed(bob),
+            token_hash,
+            alice,
+            1,
+            None
+        ));
+
+        assert_ok!(TokenModule::thaw_account(
+            Origin::signed(alice),
+            token_hash,
+            charlie
+        ));
+        assert!(!TokenModule::is_frozen((token_hash, charlie)));
+        assert_ok!(TokenModule::transfer(
+            Origin::signed(charlie),
+            token_hash,
+            bob,
+            1,
+            None
+        ));
+        assert_eq!(TokenModule::balance_of((charlie, token_hash)), 9);
+    });
+}
+
+#[test]
+fn ownership_and_roles_tests() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        let alice = 10u64;
+        let bob = 20u64;
+        let charlie = 30u64;
+        let multisig = 40u64;
+
+        assert_ok!(TokenModule::issue_mintable(
+            Origin::signed(alice),
+            b"6688".to_vec(),
+            1000
+        ));
+        let token_hash = TokenModule::token_hash_by_index(0).unwrap();
+        assert_eq!(
+            TokenModule::roles(token_hash),
+            Some(TokenRoles {
+                issuer: alice,
+                admin: alice,
+                freezer: alice,
+            })
+        );
+
+        // two-step ownership transfer
+        assert_err!(
+            TokenModule::transfer_ownership(Origin::signed(bob), token_hash, bob),
+            Error::<Test>::NotTokenOwner
+        );
+        assert_ok!(TokenModule::transfer_ownership(
+            Origin::signed(alice),
+            token_hash,
+            bob
+        ));
+        assert_eq!(TokenModule::owner(token_hash), Some(alice));
+        assert_eq!(TokenModule::pending_owner(token_hash), Some(bob));
+        assert_err!(
+            TokenModule::accept_ownership(Origin::signed(charlie), token_hash),
+            Error::<Test>::NotPendingOwner
+        );
+        assert_ok!(TokenModule::accept_ownership(
+            Origin::signed(bob),
+            token_hash
+        ));
+        assert_eq!(TokenModule::owner(token_hash), Some(bob));
+        assert_eq!(TokenModule::pending_owner(token_hash), None);
+        assert_eq!(
+            TokenModule::roles(token_hash),
+            Some(TokenRoles {
+                issuer: bob,
+                admin: bob,
+                freezer: bob,
+            })
+        );
+        assert_err!(
+            TokenModule::accept_ownership(Origin::signed(bob), token_hash),
+            Error::<Test>::NotPendingOwner
+        );
+
+        // roles are set by the owner, who does not need to hold any supply
+        assert_err!(
+            TokenModule::set_roles(
+                Origin::signed(alice),
+                token_hash,
+                multisig,
+                multisig,
+                charlie
+            ),
+            Error::<Test>::NotTokenOwner
+        );
+        assert_ok!(TokenModule::set_roles(
+            Origin::signed(bob),
+            token_hash,
+            multisig,
+            multisig,
+            charlie
+        ));
+
+        assert_err!(
+            TokenModule::mint(Origin::signed(alice), token_hash, alice, 100),
+            Error::<Test>::NotTokenIssuer
+        );
+        assert_ok!(TokenModule::mint(
+            Origin::signed(multisig),
+            token_hash,
+            charlie,
+            100
+        ));
+        assert_eq!(TokenModule::token(token_hash).unwrap().total_supply, 1100);
+        assert_eq!(TokenModule::balance_of((charlie, token_hash)), 100);
+        assert_eq!(TokenModule::free_balance_of((charlie, token_hash)), 100);
+
+        assert_err!(
+            TokenModule::pause_token(Origin::signed(bob), token_hash),
+            Error::<Test>::NotTokenAdmin
+        );
+        assert_ok!(TokenModule::pause_token(
+            Origin::signed(multisig),
+            token_hash
+        ));
+        assert_ok!(TokenModule::unpause_token(
+            Origin::signed(multisig),
+            token_hash
+        ));
+
+        assert_err!(
+            TokenModule::freeze_account(Origin::signed(multisig), token_hash, alice),
+            Error::<Test>::NotTokenFreezer
+        );
+        assert_ok!(TokenModule::freeze_account(
+            Origin::signed(charlie),
+            token_hash,
+            alice
+        ));
+    });
+}
+
+#[test]
+fn mint_tests() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        let alice = 10u64;
+        let _ = Balances::deposit_creating(&alice, 1000);
+
+        assert_ok!(TokenModule::wrap(Origin::signed(alice), 100));
+        let native_hash = TokenModule::wrapped_native_token_hash().unwrap();
+        let pallet_account = TokenModule::account_id();
+        assert_err!(
+            TokenModule::mint(Origin::signed(pallet_account), native_hash, alice, 100),
+            Error::<Test>::TokenNotMintable
+        );
+
+        assert_ok!(TokenModule::issue_mintable(
+            Origin::signed(alice),
+            b"6688".to_vec(),
+            1000
+        ));
+        let token_hash = TokenModule::token_hash_by_index(1).unwrap();
+        assert_err!(
+            TokenModule::mint(Origin::signed(alice), token_hash, alice, 0),
+            Error::<Test>::AmountIsZero
+        );
+    });
+}
+
+#[test]
+fn vesting_tests() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        let alice = 10u64;
+        let bob = 20u64;
+
+        assert_ok!(TokenModule::issue(
+            Origin::signed(alice),
+            b"6688".to_vec(),
+            10000
+        ));
+        let token_hash = TokenModule::token_hash_by_index(0).unwrap();
+
+        let schedule = VestingSchedule {
+            locked: 1000,
+            per_block: 100,
+            starting_block: 5,
+        };
+
+        assert_err!(
+            TokenModule::vested_transfer(
+                Origin::signed(alice),
+                token_hash,
+                bob,
+                VestingSchedule {
+                    per_block: 0,
+                    ..schedule
+                }
+            ),
+            Error::<Test>::InvalidVestingSchedule
+        );
+        assert_err!(
+            TokenModule::vest(Origin::signed(bob), token_hash),
+            Error::<Test>::NotVesting
+        );
+
+        assert_ok!(TokenModule::vested_transfer(
+            Origin::signed(alice),
+            token_hash,
+            bob,
+            schedule
+        ));
+        assert_eq!(TokenModule::balance_of((bob, token_hash)), 1000);
+        assert_eq!(TokenModule::free_balance_of((bob, token_hash)), 0);
+        assert_eq!(TokenModule::freezed_balance_of((bob, token_hash)), 0);
+        assert_eq!(TokenModule::vesting_balance(bob, token_hash), (1000, 0));
+        // reserving cannot take the locked balance, nor unreserving release it
+        assert_err!(
+            TokenModule::do_freeze(bob, token_hash, 1),
+            Error::<Test>::BalanceNotEnough
+        );
+        assert_err!(
+            TokenModule::do_unfreeze(bob, token_hash, 1),
+            Error::<Test>::BalanceNotEnough
+        );
+        assert_err!(
+            TokenModule::vested_transfer(Origin::signed(alice), token_hash, bob, schedule),
+            Error::<Test>::ExistingVestingSchedule
+        );
+
+        // nothing is released before the starting block
+        run_to_block(4);
+        assert_ok!(TokenModule::vest(Origin::signed(bob), token_hash));
+        assert_eq!(TokenModule::free_balance_of((bob, token_hash)), 0);
+
+        run_to_block(8);
+        assert_eq!(TokenModule::vesting_balance(bob, token_hash), (700, 300));
+        assert_ok!(TokenModule::vest(Origin::signed(bob), token_hash));
+        assert_eq!(TokenModule::free_balance_of((bob, token_hash)), 300);
+        assert_eq!(TokenModule::vesting((bob, token_hash)).unwrap().locked, 700);
+        assert_eq!(TokenModule::vesting_balance(bob, token_hash), (700, 0));
+
+        run_to_block(10);
+        assert_eq!(TokenModule::vesting_balance(bob, token_hash), (500, 200));
+        assert_err!(
+            TokenModule::transfer(Origin::signed(bob), token_hash, alice, 301, None),
+            Error::<Test>::BalanceNotEnough
+        );
+
+        run_to_block(100);
+        assert_eq!(TokenModule::vesting_balance(bob, token_hash), (0, 700));
+        assert_ok!(TokenModule::vest(Origin::signed(bob), token_hash));
+        assert_eq!(TokenModule::free_balance_of((bob, token_hash)), 1000);
+        assert_eq!(TokenModule::freezed_balance_of((bob, token_hash)), 0);
+        assert_eq!(TokenModule::vesting((bob, token_hash)), None);
+    });
+}
+
+/// Issue a mintable token from `owner`.
+fn issue_token(owner: u64, total_supply: u128) -> H256 {
+    assert_ok!(TokenModule::issue_mintable(
+        Origin::signed(owner),
+        b"6688".to_vec(),
+        total_supply
+    ));
+    let hash = TokenModule::token_hash_by_index(TokenModule::token_index() - 1).unwrap();
+    assert_last_event(RawEvent::Issued(owner, hash, total_supply));
+
+    hash
+}
+
+#[test]
+fn transfer_emits_transferd() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let hash = issue_token(10, 1000);
+
+        assert_ok!(TokenModule::transfer(
+            Origin::signed(10),
+            hash,
+            20,
+            300,
+            None
+        ));
+        assert_last_event(RawEvent::Transferd(10, 20, hash, 300));
+        assert_eq!(token_events().len(), 2);
+    });
+}
+
+#[test]
+fn no_matching_token() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let unknown = H256::from_low_u64_be(1);
+
+        assert_err!(
+            TokenModule::transfer(Origin::signed(10), unknown, 20, 1, None),
+            Error::<Test>::NoMatchingToken
+        );
+        assert_err!(
+            TokenModule::mint(Origin::signed(10), unknown, 20, 1),
+            Error::<Test>::NoMatchingToken
+        );
+        assert!(token_events().is_empty());
+    });
+}
+
+#[test]
+fn balance_not_enough() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let hash = issue_token(10, 1000);
+
+        assert_err!(
+            TokenModule::transfer(Origin::signed(10), hash, 20, 1001, None),
+            Error::<Test>::BalanceNotEnough
+        );
+        assert_eq!(TokenModule::balance_of((10, hash)), 1000);
+    });
+}
+
+#[test]
+fn amount_overflow() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let hash = issue_token(10, u128::max_value());
+
+        assert_err!(
+            TokenModule::mint(Origin::signed(10), hash, 20, 1),
+            Error::<Test>::AmountOverflow
+        );
+        assert_eq!(TokenModule::balance_of((20, hash)), 0);
+    });
+}
+
+#[test]
+fn sender_have_no_token() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let hash = issue_token(10, 1000);
+
+        assert_err!(
+            TokenModule::transfer(Origin::signed(20), hash, 10, 0, None),
+            Error::<Test>::SenderHaveNoToken
+        );
+    });
+}
+
+#[test]
+fn memo_length_exceed_limitation() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let hash = issue_token(10, 1000);
+
+        assert_err!(
+            TokenModule::transfer(Origin::signed(10), hash, 20, 1, Some(vec![0u8; 513])),
+            Error::<Test>::MemoLengthExceedLimitation
+        );
+        assert_ok!(TokenModule::transfer(
+            Origin::signed(10),
+            hash,
+            20,
+            1,
+            Some(vec![0u8; 512])
+        ));
+    });
+}
+
+#[test]
+fn amount_is_zero() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let hash = issue_token(10, 1000);
+
+        assert_err!(
+            TokenModule::mint(Origin::signed(10), hash, 20, 0),
+            Error::<Test>::AmountIsZero
+        );
+        assert_err!(
+            TokenModule::wrap(Origin::signed(10), 0),
+            Error::<Test>::AmountIsZero
+        );
+    });
+}
+
+#[test]
+fn wrapped_native_token_not_found() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+
+        assert_err!(
+            TokenModule::unwrap(Origin::signed(10), 1),
+            Error::<Test>::WrappedNativeTokenNotFound
+        );
+    });
+}
+
+#[test]
+fn wrap_and_unwrap_emit_events() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let _ = Balances::deposit_creating(&10, 1000);
+
+        assert_ok!(TokenModule::wrap(Origin::signed(10), 400));
+        let hash = TokenModule::wrapped_native_token_hash().unwrap();
+        assert_last_event(RawEvent::Wrapped(10, hash, 400));
+
+        assert_ok!(TokenModule::unwrap(Origin::signed(10), 100));
+        assert_last_event(RawEvent::Unwrapped(10, hash, 100));
+    });
+}
+
+#[test]
+fn not_token_owner() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let hash = issue_token(10, 1000);
+
+        assert_err!(
+            TokenModule::transfer_ownership(Origin::signed(20), hash, 20),
+            Error::<Test>::NotTokenOwner
+        );
+        assert_err!(
+            TokenModule::set_roles(Origin::signed(20), hash, 20, 20, 20),
+            Error::<Test>::NotTokenOwner
+        );
+    });
+}
+
+#[test]
+fn not_token_issuer() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let hash = issue_token(10, 1000);
+
+        assert_err!(
+            TokenModule::mint(Origin::signed(20), hash, 20, 1),
+            Error::<Test>::NotTokenIssuer
+        );
+
+        assert_ok!(TokenModule::set_roles(Origin::signed(10), hash, 20, 10, 10));
+        assert_last_event(RawEvent::RolesSet(10, hash, 20, 10, 10));
+        assert_ok!(TokenModule::mint(Origin::signed(20), hash, 30, 5));
+        assert_last_event(RawEvent::Minted(20, hash, 30, 5));
+    });
+}
+
+#[test]
+fn not_token_admin() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let hash = issue_token(10, 1000);
+
+        assert_err!(
+            TokenModule::pause_token(Origin::signed(20), hash),
+            Error::<Test>::NotTokenAdmin
+        );
+        assert_err!(
+            TokenModule::unpause_token(Origin::signed(20), hash),
+            Error::<Test>::NotTokenAdmin
+        );
+    });
+}
+
+#[test]
+fn not_token_freezer() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let hash = issue_token(10, 1000);
+
+        assert_err!(
+            TokenModule::freeze_account(Origin::signed(20), hash, 30),
+            Error::<Test>::NotTokenFreezer
+        );
+        assert_err!(
+            TokenModule::thaw_account(Origin::signed(20), hash, 30),
+            Error::<Test>::NotTokenFreezer
+        );
+    });
+}
+
+#[test]
+fn not_pending_owner() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let hash = issue_token(10, 1000);
+
+        assert_err!(
+            TokenModule::accept_ownership(Origin::signed(20), hash),
+            Error::<Test>::NotPendingOwner
+        );
+
+        assert_ok!(TokenModule::transfer_ownership(
+            Origin::signed(10),
+            hash,
+            20
+        ));
+        assert_last_event(RawEvent::OwnershipTransferProposed(10, hash, 20));
+        assert_err!(
+            TokenModule::accept_ownership(Origin::signed(30), hash),
+            Error::<Test>::NotPendingOwner
+        );
+
+        assert_ok!(TokenModule::accept_ownership(Origin::signed(20), hash));
+        assert_last_event(RawEvent::OwnershipTransferred(10, hash, 20));
+    });
+}
+
+#[test]
+fn accept_ownership_revokes_previous_roles() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let hash = issue_token(10, 1000);
+
+        assert_ok!(TokenModule::set_roles(Origin::signed(10), hash, 10, 30, 10));
+        assert_ok!(TokenModule::transfer_ownership(
+            Origin::signed(10),
+            hash,
+            20
+        ));
+        assert_ok!(TokenModule::accept_ownership(Origin::signed(20), hash));
+        assert_has_event(RawEvent::RolesSet(20, hash, 20, 20, 20));
+        assert_last_event(RawEvent::OwnershipTransferred(10, hash, 20));
+
+        assert_noop!(
+            TokenModule::mint(Origin::signed(10), hash, 10, 1),
+            Error::<Test>::NotTokenIssuer
+        );
+        assert_noop!(
+            TokenModule::pause_token(Origin::signed(30), hash),
+            Error::<Test>::NotTokenAdmin
+        );
+        assert_noop!(
+            TokenModule::freeze_account(Origin::signed(10), hash, 20),
+            Error::<Test>::NotTokenFreezer
+        );
+        assert_noop!(
+            TokenModule::set_roles(Origin::signed(10), hash, 10, 10, 10),
+            Error::<Test>::NotTokenOwner
+        );
+
+        assert_ok!(TokenModule::pause_token(Origin::signed(20), hash));
+        assert_ok!(TokenModule::unpause_token(Origin::signed(20), hash));
+        assert_ok!(TokenModule::freeze_account(Origin::signed(20), hash, 10));
+    });
+}
+
+#[test]
+fn token_not_mintable() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let hash = TokenModule::do_issue(10, b"LP".to_vec(), 1000, TokenType::Liquidity).unwrap();
+
+        assert_err!(
+            TokenModule::mint(Origin::signed(10), hash, 20, 1),
+            Error::<Test>::TokenNotMintable
+        );
+
+        // the supply of a token not issued as mintable is fixed
+        assert_ok!(TokenModule::issue(
+            Origin::signed(10),
+            b"6688".to_vec(),
+            1000
+        ));
+        let hash = TokenModule::token_hash_by_index(TokenModule::token_index() - 1).unwrap();
+        assert!(!TokenModule::is_mintable(hash));
+        assert_noop!(
+            TokenModule::mint(Origin::signed(10), hash, 20, 1),
+            Error::<Test>::TokenNotMintable
+        );
+    });
+}
+
+#[test]
+fn invalid_vesting_schedule() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let hash = issue_token(10, 1000);
+
+        assert_err!(
+            TokenModule::vested_transfer(
+                Origin::signed(10),
+                hash,
+                20,
+                VestingSchedule {
+                    locked: 100,
+                    per_block: 0,
+                    starting_block: 1,
+                }
+            ),
+            Error::<Test>::InvalidVestingSchedule
+        );
+    });
+}
+
+#[test]
+fn existing_vesting_schedule() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let hash = issue_token(10, 1000);
+        let schedule = VestingSchedule {
+            locked: 100,
+            per_block: 10,
+            starting_block: 1,
+        };
+
+        assert_ok!(TokenModule::vested_transfer(
+            Origin::signed(10),
+            hash,
+            20,
+            schedule
+        ));
+        assert_last_event(RawEvent::VestingScheduleAdded(10, hash, 20, 100));
+
+        assert_err!(
+            TokenModule::vested_transfer(Origin::signed(10), hash, 20, schedule),
+            Error::<Test>::ExistingVestingSchedule
+        );
+    });
+}
+
+#[test]
+fn vested_transfer_by_holder() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let hash = issue_token(10, 1000);
+        assert_ok!(TokenModule::do_transfer(10, 20, hash, 100, None));
+
+        // a holder cannot take the schedule slot of an account with a dust schedule
+        assert_noop!(
+            TokenModule::vested_transfer(
+                Origin::signed(20),
+                hash,
+                30,
+                VestingSchedule {
+                    locked: 1,
+                    per_block: 1,
+                    starting_block: 1_000_000,
+                }
+            ),
+            Error::<Test>::NotTokenOwner
+        );
+        assert_eq!(TokenModule::vesting((30, hash)), None);
+    });
+}
+
+#[test]
+fn not_vesting() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let hash = issue_token(10, 1000);
+
+        assert_err!(
+            TokenModule::vest(Origin::signed(10), hash),
+            Error::<Test>::NotVesting
+        );
+    });
+}
+
+#[test]
+fn token_paused() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let hash = issue_token(10, 1000);
+
+        assert_ok!(TokenModule::pause_token(Origin::signed(10), hash));
+        assert_last_event(RawEvent::TokenPaused(10, hash));
+        assert_err!(
+            TokenModule::transfer(Origin::signed(10), hash, 20, 1, None),
+            Error::<Test>::TokenPaused
+        );
+
+        assert_ok!(TokenModule::unpause_token(Origin::signed(10), hash));
+        assert_last_event(RawEvent::TokenUnpaused(10, hash));
+        assert_ok!(TokenModule::transfer(Origin::signed(10), hash, 20, 1, None));
+    });
+}
+
+#[test]
+fn account_frozen() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let hash = issue_token(10, 1000);
+
+        assert_ok!(TokenModule::freeze_account(Origin::signed(10), hash, 20));
+        assert_last_event(RawEvent::AccountFrozen(10, hash, 20));
+        assert_err!(
+            TokenModule::transfer(Origin::signed(10), hash, 20, 1, None),
+            Error::<Test>::AccountFrozen
+        );
+
+        assert_ok!(TokenModule::thaw_account(Origin::signed(10), hash, 20));
+        assert_last_event(RawEvent::AccountThawed(10, hash, 20));
+        assert_ok!(TokenModule::transfer(Origin::signed(10), hash, 20, 1, None));
+    });
+}
+
+#[test]
+fn transfer_to_self_keeps_balance() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let hash = issue_token(10, 1000);
+
+        assert_ok!(TokenModule::transfer(
+            Origin::signed(10),
+            hash,
+            10,
+            400,
+            None
+        ));
+        assert_eq!(TokenModule::balance_of((10, hash)), 1000);
+        assert_eq!(TokenModule::free_balance_of((10, hash)), 1000);
+        assert_last_event(RawEvent::Transferd(10, 10, hash, 400));
+    });
+}
+
+#[test]
+fn transfer_near_max_supply() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let max = u128::max_value();
+        let hash = issue_token(10, max);
+
+        assert_ok!(TokenModule::transfer(
+            Origin::signed(10),
+            hash,
+            20,
+            max - 1,
+            None
+        ));
+        assert_eq!(TokenModule::balance_of((10, hash)), 1);
+        assert_eq!(TokenModule::balance_of((20, hash)), max - 1);
+
+        assert_err!(
+            TokenModule::transfer(Origin::signed(10), hash, 20, 2, None),
+            Error::<Test>::BalanceNotEnough
+        );
+        assert_ok!(TokenModule::transfer(Origin::signed(10), hash, 20, 1, None));
+        assert_eq!(TokenModule::balance_of((10, hash)), 0);
+        assert_eq!(TokenModule::free_balance_of((20, hash)), max);
+    });
+}
+
+#[test]
+fn transfer_overflowing_receiver_is_rejected() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let hash = issue_token(10, 1000);
+        // only reachable with balances out of line with the supply
+        BalanceOf::<Test>::insert((20, hash), u128::max_value());
+        FreeBalanceOf::<Test>::insert((20, hash), u128::max_value());
+
+        assert_err!(
+            TokenModule::transfer(Origin::signed(10), hash, 20, 1, None),
+            Error::<Test>::AmountOverflow
+        );
+        assert_eq!(TokenModule::balance_of((10, hash)), 1000);
+        assert_eq!(TokenModule::free_balance_of((10, hash)), 1000);
+    });
+}
+
+#[test]
+fn freeze_and_unfreeze_near_max_supply() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let max = u128::max_value();
+        let hash = issue_token(10, max);
+
+        assert_ok!(TokenModule::do_freeze(10, hash, max));
+        assert_eq!(TokenModule::free_balance_of((10, hash)), 0);
+        assert_eq!(TokenModule::freezed_balance_of((10, hash)), max);
+        assert_err!(
+            TokenModule::do_freeze(10, hash, 1),
+            Error::<Test>::BalanceNotEnough
+        );
+
+        // the frozen balance is full, so freezing anything more overflows
+        FreeBalanceOf::<Test>::insert((10, hash), 1);
+        assert_err!(
+            TokenModule::do_freeze(10, hash, 1),
+            Error::<Test>::AmountOverflow
+        );
+        assert_eq!(TokenModule::free_balance_of((10, hash)), 1);
+
+        assert_err!(
+            TokenModule::do_unfreeze(10, hash, max),
+            Error::<Test>::AmountOverflow
+        );
+        assert_eq!(TokenModule::freezed_balance_of((10, hash)), max);
+
+        FreeBalanceOf::<Test>::insert((10, hash), 0);
+        assert_ok!(TokenModule::do_unfreeze(10, hash, max));
+        assert_eq!(TokenModule::free_balance_of((10, hash)), max);
+        assert_eq!(TokenModule::freezed_balance_of((10, hash)), 0);
+        assert_err!(
+            TokenModule::do_unfreeze(10, hash, 1),
+            Error::<Test>::BalanceNotEnough
+        );
+    });
+}
+
+#[test]
+fn vested_transfer_of_max_supply() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let max = u128::max_value();
+        let hash = issue_token(10, max);
+
+        assert_ok!(TokenModule::vested_transfer(
+            Origin::signed(10),
+            hash,
+            20,
+            VestingSchedule {
+                locked: max,
+                per_block: max / 2,
+                starting_block: 1,
+            }
+        ));
+        assert_eq!(TokenModule::vesting((20, hash)).unwrap().locked, max);
+
+        run_to_block(3);
+        assert_ok!(TokenModule::vest(Origin::signed(20), hash));
+        assert_eq!(TokenModule::free_balance_of((20, hash)), max - 1);
+        assert_eq!(TokenModule::vesting((20, hash)).unwrap().locked, 1);
+    });
+}
+
+#[test]
+fn migration_registry_is_ordered() {
+    let releases: Vec<Releases> = migrations::migrations::<Test>()
+        .into_iter()
+        .map(|(release, _)| release)
+        .collect();
+
+    assert!(releases.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(releases
+        .iter()
+        .all(|release| *release > Releases::default()));
+    assert_eq!(releases.last(), Some(&Releases::LATEST));
+}
+
+#[test]
+fn genesis_is_at_latest_release() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        assert_eq!(TokenModule::storage_version(), Releases::LATEST);
+
+        let hash = issue_token(1, 1000);
+        assert_ok!(TokenModule::set_roles(Origin::signed(1), hash, 2, 3, 4));
+
+        TokenModule::on_runtime_upgrade();
+        assert_eq!(
+            TokenModule::roles(hash),
+            Some(TokenRoles {
+                issuer: 2,
+                admin: 3,
+                freezer: 4,
+            })
+        );
+    });
+}
+
+#[test]
+fn migrate_v1_tokens() {
+    let hash = H256::repeat_byte(1);
+    let token = Token {
+        token_hash: hash,
+        symbol: b"6666".to_vec(),
+        total_supply: 1000u128,
+        ttype: TokenType::Normal,
+    };
+    let snapshot = vec![
+        (map_key(b"Tokens", &hash), token.encode()),
+        (map_key(b"Owners", &hash), 1u64.encode()),
+        (map_key(b"BalanceOf", &(1u64, hash)), 1000u128.encode()),
+        (map_key(b"FreeBalanceOf", &(1u64, hash)), 1000u128.encode()),
+    ];
+
+    new_test_ext_from_snapshot(snapshot).execute_with(|| {
+        run_to_block(1);
+        assert_eq!(TokenModule::storage_version(), Releases::V1);
+        assert_eq!(TokenModule::roles(hash), None);
+
+        TokenModule::on_runtime_upgrade();
+        assert_eq!(TokenModule::storage_version(), Releases::V2);
+        assert_eq!(TokenModule::token(hash), Some(token));
+        assert_eq!(
+            TokenModule::roles(hash),
+            Some(TokenRoles {
+                issuer: 1,
+                admin: 1,
+                freezer: 1,
+            })
+        );
+
+        // the owner administers the token again, whose supply stays fixed
+        assert_ok!(TokenModule::freeze_account(Origin::signed(1), hash, 2));
+        assert!(!TokenModule::is_mintable(hash));
+        assert_noop!(
+            TokenModule::mint(Origin::signed(1), hash, 2, 500),
+            Error::<Test>::TokenNotMintable
+        );
+    });
+}
@@ -0,0 +1,86 @@
+use frame_support::{dispatch, Parameter};
+use sp_runtime::traits::{AtLeast32BitUnsigned, MaybeSerializeDeserialize, Member};
+use sp_std::prelude::Vec;
+
+use crate::TokenType;
+
+/// Abstraction over a fungible multi-currency system.
+pub trait MultiCurrency<AccountId> {
+    /// The identifier of a currency.
+    type CurrencyId: Parameter + Member + Copy + MaybeSerializeDeserialize;
+
+    /// The balance of an account.
+    type Balance: Parameter
+        + Member
+        + AtLeast32BitUnsigned
+        + Default
+        + Copy
+        + MaybeSerializeDeserialize;
+
+    /// Whether `currency_id` is a known currency.
+    fn exists(currency_id: Self::CurrencyId) -> bool;
+
+    /// The total balance of `who` in `currency_id`, free and reserved.
+    fn total_balance(currency_id: Self::CurrencyId, who: &AccountId) -> Self::Balance;
+
+    /// The part of the balance of `who` in `currency_id` that can be moved.
+    fn free_balance(currency_id: Self::CurrencyId, who: &AccountId) -> Self::Balance;
+
+    /// Ensure `who` is able to withdraw `amount` of `currency_id`.
+    fn ensure_can_withdraw(
+        currency_id: Self::CurrencyId,
+        who: &AccountId,
+        amount: Self::Balance,
+    ) -> dispatch::DispatchResult;
+
+    /// Transfer `amount` of `currency_id` from `from` to `to`.
+    fn transfer(
+        currency_id: Self::CurrencyId,
+        from: &AccountId,
+        to: &AccountId,
+        amount: Self::Balance,
+    ) -> dispatch::DispatchResult;
+}
+
+/// A multi-currency system whose balances can be set aside.
+pub trait MultiReservableCurrency<AccountId>: MultiCurrency<AccountId> {
+    /// Whether `who` has enough free balance to reserve `value` of `currency_id`.
+    fn can_reserve(currency_id: Self::CurrencyId, who: &AccountId, value: Self::Balance) -> bool;
+
+    /// The reserved balance of `who` in `currency_id`.
+    fn reserved_balance(currency_id: Self::CurrencyId, who: &AccountId) -> Self::Balance;
+
+    /// Move `value` of `currency_id` from the free to the reserved balance of `who`.
+    fn reserve(
+        currency_id: Self::CurrencyId,
+        who: &AccountId,
+        value: Self::Balance,
+    ) -> dispatch::DispatchResult;
+
+    /// Move `value` of `currency_id` from the reserved to the free balance of `who`.
+    fn unreserve(
+        currency_id: Self::CurrencyId,
+        who: &AccountId,
+        value: Self::Balance,
+    ) -> dispatch::DispatchResult;
+}
+
+/// A multi-currency system whose currencies can be described and created by other pallets.
+pub trait MultiAsset<AccountId>: MultiCurrency<AccountId> {
+    /// The symbol of `currency_id`, if it exists.
+    fn symbol(currency_id: Self::CurrencyId) -> Option<Vec<u8>>;
+
+    /// The owner of `currency_id`, if it exists.
+    fn owner(currency_id: Self::CurrencyId) -> Option<AccountId>;
+
+    /// Whether transfers of `currency_id` are currently halted.
+    fn is_paused(currency_id: Self::CurrencyId) -> bool;
+
+    /// Create a new currency owned by `who`, crediting it with the whole `total_supply`.
+    fn issue(
+        who: &AccountId,
+        symbol: Vec<u8>,
+        total_supply: Self::Balance,
+        ttype: TokenType,
+    ) -> Result<Self::CurrencyId, dispatch::DispatchError>;
+}
@@ -0,0 +1,126 @@
+use codec::{Decode, Encode};
+use frame_support::{dispatch, ensure};
+use sp_runtime::{
+    traits::{AtLeast32BitUnsigned, CheckedMul, Convert, Saturating, Zero},
+    RuntimeDebug,
+};
+
+use crate::{Error, FreeBalanceOf, Module, RawEvent, Trait, Vesting};
+
+/// Linear release of a locked amount of a token, starting at `starting_block`. The locked
+/// amount is held back from the free balance apart from the frozen balance, which reserves
+/// share.
+#[derive(Encode, Decode, Copy, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct VestingSchedule<Balance, BlockNumber> {
+    /// Locked amount at `starting_block`.
+    pub locked: Balance,
+    /// Amount that gets unlocked every block after `starting_block`.
+    pub per_block: Balance,
+    /// Starting block for unlocking.
+    pub starting_block: BlockNumber,
+}
+
+impl<Balance, BlockNumber> VestingSchedule<Balance, BlockNumber>
+where
+    Balance: AtLeast32BitUnsigned + Copy,
+    BlockNumber: AtLeast32BitUnsigned + Copy,
+{
+    /// Amount still locked at block `n`.
+    pub fn locked_at<BlockNumberToBalance: Convert<BlockNumber, Balance>>(
+        &self,
+        n: BlockNumber,
+    ) -> Balance {
+        let vested_block_count =
+            BlockNumberToBalance::convert(n.saturating_sub(self.starting_block));
+        vested_block_count
+            .checked_mul(&self.per_block)
+            .map(|vested| self.locked.saturating_sub(vested))
+            .unwrap_or_else(Zero::zero)
+    }
+}
+
+impl<T: Trait> Module<T> {
+    /// Transfer `schedule.locked` of `hash` from its owner `sender` to `to`, locking it on `to`
+    /// until it is released by the schedule. Only the owner may vest the token, so that nobody
+    /// can take the schedule slot of `to` with a dust schedule.
+    pub fn do_vested_transfer(
+        sender: T::AccountId,
+        to: T::AccountId,
+        hash: T::Hash,
+        schedule: VestingSchedule<T::Balance, T::BlockNumber>,
+    ) -> dispatch::DispatchResult {
+        Self::ensure_owner(&sender, hash)?;
+        ensure!(schedule.locked > Zero::zero(), Error::<T>::AmountIsZero);
+        ensure!(
+            schedule.per_block > Zero::zero(),
+            Error::<T>::InvalidVestingSchedule
+        );
+        ensure!(
+            !Vesting::<T>::contains_key((to.clone(), hash)),
+            Error::<T>::ExistingVestingSchedule
+        );
+
+        Self::do_transfer(sender.clone(), to.clone(), hash, schedule.locked, None)?;
+        let free = Self::free_balance_of((to.clone(), hash))
+            .checked_sub(&schedule.locked)
+            .ok_or(Error::<T>::BalanceNotEnough)?;
+        FreeBalanceOf::<T>::insert((to.clone(), hash), free);
+        Vesting::<T>::insert((to.clone(), hash), schedule);
+
+        Self::deposit_event(RawEvent::VestingScheduleAdded(
+            sender,
+            hash,
+            to,
+            schedule.locked,
+        ));
+
+        Ok(())
+    }
+
+    /// Unlock the part of the vesting schedule of `who` for `hash` released so far.
+    pub fn do_vest(who: T::AccountId, hash: T::Hash) -> dispatch::DispatchResult {
+        let schedule = Self::vesting((who.clone(), hash)).ok_or(Error::<T>::NotVesting)?;
+
+        let now = <frame_system::Module<T>>::block_number();
+        let locked = schedule.locked_at::<T::BlockNumberToBalance>(now);
+        let released = schedule.locked.saturating_sub(locked);
+
+        if released > Zero::zero() {
+            let free = Self::free_balance_of((who.clone(), hash))
+                .checked_add(&released)
+                .ok_or(Error::<T>::AmountOverflow)?;
+            FreeBalanceOf::<T>::insert((who.clone(), hash), free);
+        }
+
+        if locked.is_zero() {
+            Vesting::<T>::remove((who.clone(), hash));
+        } else {
+            // restart the schedule from now, which keeps the same release curve
+            Vesting::<T>::insert(
+                (who.clone(), hash),
+                VestingSchedule {
+                    locked,
+                    per_block: schedule.per_block,
+                    starting_block: now.max(schedule.starting_block),
+                },
+            );
+        }
+
+        Self::deposit_event(RawEvent::Vested(who, hash, released));
+
+        Ok(())
+    }
+
+    /// The amount of `hash` still locked by vesting for `who`, and the amount that can be
+    /// claimed with `vest` now.
+    pub fn vesting_balance(who: T::AccountId, hash: T::Hash) -> (T::Balance, T::Balance) {
+        match Self::vesting((who, hash)) {
+            Some(schedule) => {
+                let now = <frame_system::Module<T>>::block_number();
+                let locked = schedule.locked_at::<T::BlockNumberToBalance>(now);
+                (locked, schedule.locked.saturating_sub(locked))
+            }
+            None => (Zero::zero(), Zero::zero()),
+        }
+    }
+}
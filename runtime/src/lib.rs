@@ -12,12 +12,13 @@ use sp_api::impl_runtime_apis;
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
 use sp_core::{crypto::KeyTypeId, OpaqueMetadata};
 use sp_runtime::traits::{
-    BlakeTwo256, Block as BlockT, IdentifyAccount, IdentityLookup, NumberFor, Saturating, Verify,
+    BlakeTwo256, Block as BlockT, ConvertInto, IdentifyAccount, IdentityLookup, NumberFor,
+    Saturating, Verify,
 };
 use sp_runtime::{
     create_runtime_str, generic, impl_opaque_keys,
     transaction_validity::{TransactionSource, TransactionValidity},
-    ApplyExtrinsicResult, MultiSignature,
+    ApplyExtrinsicResult, ModuleId, MultiSignature,
 };
 use sp_std::prelude::*;
 #[cfg(feature = "std")]
@@ -27,7 +28,7 @@ use sp_version::RuntimeVersion;
 // A few exports that help ease life for downstream crates.
 pub use frame_support::{
     construct_runtime, parameter_types,
-    traits::{KeyOwnerProofSystem, Randomness},
+    traits::{Filter, KeyOwnerProofSystem, Randomness},
     weights::{
         constants::{BlockExecutionWeight, ExtrinsicBaseWeight, RocksDbWeight, WEIGHT_PER_SECOND},
         IdentityFee, Weight,
@@ -43,6 +44,7 @@ pub use sp_runtime::{Perbill, Permill};
 /// Import the template pallet.
 pub use swap;
 pub use token;
+pub mod payment;
 mod weights;
 
 /// An index to a block.
@@ -137,9 +139,23 @@ parameter_types! {
 
 // Configure FRAME pallets to include in runtime.
 
+/// Blocks every call to the swap pallet while the DEX is paused, except the calls lifting the
+/// pause.
+pub struct BaseFilter;
+impl Filter<Call> for BaseFilter {
+    fn filter(call: &Call) -> bool {
+        match call {
+            Call::SwapModule(swap::Call::unpause_all(..))
+            | Call::SwapModule(swap::Call::unpause_pair(..)) => true,
+            Call::SwapModule(_) => !SwapModule::is_all_paused(),
+            _ => true,
+        }
+    }
+}
+
 impl frame_system::Trait for Runtime {
     /// The basic call filter to use in dispatchable.
-    type BaseCallFilter = ();
+    type BaseCallFilter = BaseFilter;
     /// The identifier used to distinguish between accounts.
     type AccountId = AccountId;
     /// The aggregated dispatch type that is available for extrinsics.
@@ -271,13 +287,40 @@ impl pallet_utility::Trait for Runtime {
     type WeightInfo = weights::pallet_utility::WeightInfo;
 }
 
+parameter_types! {
+    pub const FlashLoanFee: Permill = Permill::from_parts(900);
+    pub const InitialAmplification: u128 = 100;
+    /// 0.05% for pegged pairs, 0.3% for most pairs and 1% for exotic pairs.
+    pub FeeTiers: Vec<u32> = vec![5, 30, 100];
+    /// The lowest fee tier, the closest to the fee-free trade pairs of the first release.
+    pub const DefaultFeeTier: u32 = 5;
+    pub const ListingPolicy: swap::ListingPolicy = swap::ListingPolicy::TokenOwner;
+}
+
 impl swap::Trait for Runtime {
     type Event = Event;
+    type Currency = TokenModule;
+    type FlashSwapHandler = swap::DispatchCall<Runtime>;
+    type Call = Call;
+    type FlashLoanFee = FlashLoanFee;
+    type InitialAmplification = InitialAmplification;
+    type FeeTiers = FeeTiers;
+    type DefaultFeeTier = DefaultFeeTier;
+    type ListingPolicy = ListingPolicy;
+    type ListingOrigin = frame_system::EnsureRoot<AccountId>;
     type Price = u128;
+    type WeightInfo = weights::pallet_swap::WeightInfo;
+}
+
+parameter_types! {
+    pub const TokenModuleId: ModuleId = ModuleId(*b"py/token");
 }
 
 impl token::Trait for Runtime {
     type Event = Event;
+    type ModuleId = TokenModuleId;
+    type BlockNumberToBalance = ConvertInto;
+    type WeightInfo = weights::pallet_token::WeightInfo;
 }
 
 // Create the runtime by composing the FRAME pallets that were previously configured.
@@ -297,8 +340,8 @@ construct_runtime!(
         TransactionPayment: pallet_transaction_payment::{Module, Storage},
         Sudo: pallet_sudo::{Module, Call, Config<T>, Storage, Event<T>},
         // Include the custom logic from the template pallet in the runtime.
-        SwapModule: swap::{Module, Call, Storage, Event<T>},
-        TokenModule: token::{Module, Call, Storage, Event<T>},
+        SwapModule: swap::{Module, Call, Config, Storage, Event<T>},
+        TokenModule: token::{Module, Call, Config, Storage, Event<T>},
     }
 );
 
@@ -320,7 +363,7 @@ pub type SignedExtra = (
     frame_system::CheckEra<Runtime>,
     frame_system::CheckNonce<Runtime>,
     frame_system::CheckWeight<Runtime>,
-    pallet_transaction_payment::ChargeTransactionPayment<Runtime>,
+    payment::ChargeTokenPayment,
 );
 /// Unchecked extrinsic type as expected by this runtime.
 pub type UncheckedExtrinsic = generic::UncheckedExtrinsic<Address, Call, Signature, SignedExtra>;
@@ -459,6 +502,12 @@ impl_runtime_apis! {
         }
     }
 
+    impl token_runtime_api::TokenApi<Block, AccountId, Hash, Balance> for Runtime {
+        fn vesting_balance(who: AccountId, token_hash: Hash) -> (Balance, Balance) {
+            TokenModule::vesting_balance(who, token_hash)
+        }
+    }
+
     #[cfg(feature = "runtime-benchmarks")]
     impl frame_benchmarking::Benchmark<Block> for Runtime {
         fn dispatch_benchmark(
@@ -488,6 +537,8 @@ impl_runtime_apis! {
             add_benchmark!(params, batches, frame_system, SystemBench::<Runtime>);
             add_benchmark!(params, batches, pallet_balances, Balances);
             add_benchmark!(params, batches, pallet_timestamp, Timestamp);
+            add_benchmark!(params, batches, token, TokenModule);
+            add_benchmark!(params, batches, swap, SwapModule);
 
             if batches.is_empty() { return Err("Benchmark not found for this pallet.".into()) }
             Ok(batches)
@@ -0,0 +1,343 @@
+//! Transaction payment in the native currency, or in any token traded against the wrapped
+//! native token.
+
+use codec::{Decode, Encode};
+use frame_support::{
+    debug,
+    storage::{with_transaction, TransactionOutcome},
+    traits::{Currency, Get, Imbalance, OnUnbalanced},
+    weights::{DispatchClass, DispatchInfo, PostDispatchInfo, Weight},
+};
+use pallet_transaction_payment::ChargeTransactionPayment;
+use sp_runtime::{
+    traits::{DispatchInfoOf, PostDispatchInfoOf, Saturating, SignedExtension, Zero},
+    transaction_validity::{
+        InvalidTransaction, TransactionPriority, TransactionValidity, TransactionValidityError,
+        ValidTransaction,
+    },
+    DispatchError, DispatchResult, SaturatedConversion,
+};
+
+use swap::WeightInfo;
+
+use crate::{
+    AccountId, AvailableBlockRatio, Balance, Balances, Call, ExistentialDeposit, Hash,
+    MaximumBlockWeight, Origin, Runtime, SwapModule, System, TokenModule, TransactionPayment,
+};
+
+type NegativeImbalance = <Balances as Currency<AccountId>>::NegativeImbalance;
+type SwapWeightInfo = <Runtime as swap::Trait>::WeightInfo;
+
+/// A fee paid in a token, kept from before to after the dispatch for the refund.
+pub struct TokenFee {
+    who: AccountId,
+    tip: Balance,
+    fee: Balance,
+    /// Weight of the swaps, charged on top of the call.
+    weight: Weight,
+    /// The pair the fee token was swapped through, none when paid in wrapped native currency.
+    tp_hash: Option<Hash>,
+    /// Wrapped native currency bought beyond the fee.
+    surplus: Balance,
+    imbalance: NegativeImbalance,
+}
+
+/// Charge the fee of a transaction like `ChargeTransactionPayment`, or, when the sender picks
+/// a fee token, swap just enough of it for wrapped native currency through the pair of the
+/// token and the wrapped native token taking the least, spending at most the amount given by
+/// the sender. What is refunded after the dispatch is swapped back to the fee token, or left in
+/// wrapped native currency when it is below the existential deposit or that swap fails.
+///
+/// The account backing the wrapped native token is kept alive when the fee is withdrawn from
+/// it, so a fee that would take it below the existential deposit cannot be paid in wrapped
+/// native currency.
+#[derive(Encode, Decode, Clone, Eq, PartialEq)]
+pub struct ChargeTokenPayment {
+    #[codec(compact)]
+    tip: Balance,
+    /// The fee token with the most of it that may be spent, none for the native currency.
+    fee_token: Option<(Hash, Balance)>,
+}
+
+impl ChargeTokenPayment {
+    /// Pay `tip` on top of the fee, in `fee_token` if any.
+    pub fn from(tip: Balance, fee_token: Option<(Hash, Balance)>) -> Self {
+        Self { tip, fee_token }
+    }
+
+    fn native(&self) -> ChargeTransactionPayment<Runtime> {
+        ChargeTransactionPayment::from(self.tip)
+    }
+
+    /// Weight of paying the fee through a pair: finding the pair, with the bisection run in
+    /// every fee tier, then swapping for the fee and swapping the refund back.
+    pub fn swap_weight() -> Weight {
+        let tiers = <Runtime as swap::Trait>::FeeTiers::get().len() as u32;
+        let swap = SwapModule::max_swap_weight(true).max(SwapModule::max_swap_weight(false));
+        SwapWeightInfo::best_trade_pair_for_amount_out(tiers).saturating_add(swap.saturating_mul(2))
+    }
+
+    /// Weight charged on top of the call for paying in `token`, none for wrapped native
+    /// currency as it is not swapped.
+    fn extra_weight(token: Hash) -> Weight {
+        if TokenModule::wrapped_native_token_hash() == Some(token) {
+            0
+        } else {
+            Self::swap_weight()
+        }
+    }
+
+    /// `info` with `weight` added, to charge the fee for both.
+    fn with_weight(info: &DispatchInfoOf<Call>, weight: Weight) -> DispatchInfo {
+        DispatchInfo {
+            weight: info.weight.saturating_add(weight),
+            ..*info
+        }
+    }
+
+    /// Count `weight` in the block for a transaction of `class`, unless it exceeds what is left
+    /// of the block for that class, as `CheckWeight` does for the call.
+    fn register_weight(
+        weight: Weight,
+        class: DispatchClass,
+    ) -> Result<(), TransactionValidityError> {
+        let consumed = System::block_weight();
+        let (consumed, limit) = match class {
+            DispatchClass::Normal => (
+                consumed.get(class),
+                AvailableBlockRatio::get() * MaximumBlockWeight::get(),
+            ),
+            _ => (consumed.total(), MaximumBlockWeight::get()),
+        };
+        if consumed.saturating_add(weight) > limit {
+            return Err(InvalidTransaction::ExhaustsResources.into());
+        }
+
+        System::register_extra_weight_unchecked(weight, class);
+        Ok(())
+    }
+
+    /// Swap at most `max_amount_in` of `token` of `who` for `fee` of wrapped native currency
+    /// and withdraw the native currency backing it, all or nothing. `weight` is the extra
+    /// weight the fee was computed with.
+    fn withdraw_fee_in_token(
+        &self,
+        who: &AccountId,
+        token: Hash,
+        max_amount_in: Balance,
+        fee: Balance,
+        weight: Weight,
+    ) -> Result<TokenFee, TransactionValidityError> {
+        let wrapped =
+            TokenModule::wrapped_native_token_hash().ok_or(InvalidTransaction::Payment)?;
+        let tp = if token == wrapped {
+            if fee > max_amount_in {
+                return Err(InvalidTransaction::Payment.into());
+            }
+            None
+        } else {
+            let max_amount_in =
+                max_amount_in.min(TokenModule::free_balance_of((who.clone(), token)));
+            Some(
+                SwapModule::best_trade_pair_for_amount_out(token, wrapped, fee, max_amount_in)
+                    .ok_or(InvalidTransaction::Payment)?,
+            )
+        };
+
+        let (surplus, imbalance) = with_transaction(|| {
+            let result = (|| -> Result<_, DispatchError> {
+                let mut bought = fee;
+                if let Some((tp_hash, amount_in)) = tp {
+                    let balance = TokenModule::free_balance_of((who.clone(), wrapped));
+                    SwapModule::swap(Origin::signed(who.clone()), tp_hash, token, amount_in)
+                        .map_err(|e| e.error)?;
+                    bought = TokenModule::free_balance_of((who.clone(), wrapped))
+                        .saturating_sub(balance);
+                }
+                let imbalance = TokenModule::withdraw_wrapped(who, fee)?;
+
+                Ok((bought.saturating_sub(fee), imbalance))
+            })();
+
+            if result.is_ok() {
+                TransactionOutcome::Commit(result)
+            } else {
+                TransactionOutcome::Rollback(result)
+            }
+        })
+        .map_err(|_| InvalidTransaction::Payment)?;
+
+        Ok(TokenFee {
+            who: who.clone(),
+            tip: self.tip,
+            fee,
+            weight,
+            tp_hash: tp.map(|(tp_hash, _)| tp_hash),
+            surplus,
+            imbalance,
+        })
+    }
+
+    /// Swap `amount` of wrapped native currency of `who` back to the fee token through
+    /// `tp_hash`. An amount below the existential deposit is left wrapped, as swapping it would
+    /// pay the pair fee again for next to nothing, and so is any amount the swap fails for.
+    fn refund_in_token(who: &AccountId, tp_hash: Hash, amount: Balance) {
+        if amount < ExistentialDeposit::get() {
+            return;
+        }
+        let wrapped = match TokenModule::wrapped_native_token_hash() {
+            Some(wrapped) => wrapped,
+            None => return,
+        };
+
+        // the swap reverts itself on failure, leaving the refund wrapped
+        if let Err(e) = SwapModule::swap(Origin::signed(who.clone()), tp_hash, wrapped, amount) {
+            debug::warn!(
+                "refund of {} to {:?} left in wrapped native currency: {:?}",
+                amount,
+                who,
+                e.error
+            );
+        }
+    }
+}
+
+impl sp_std::fmt::Debug for ChargeTokenPayment {
+    #[cfg(feature = "std")]
+    fn fmt(&self, f: &mut sp_std::fmt::Formatter) -> sp_std::fmt::Result {
+        write!(
+            f,
+            "ChargeTokenPayment<{:?}, {:?}>",
+            self.tip, self.fee_token
+        )
+    }
+    #[cfg(not(feature = "std"))]
+    fn fmt(&self, _: &mut sp_std::fmt::Formatter) -> sp_std::fmt::Result {
+        Ok(())
+    }
+}
+
+impl SignedExtension for ChargeTokenPayment {
+    const IDENTIFIER: &'static str = "ChargeTokenPayment";
+    type AccountId = AccountId;
+    type Call = Call;
+    type AdditionalSigned = ();
+    type Pre = (
+        Option<<ChargeTransactionPayment<Runtime> as SignedExtension>::Pre>,
+        Option<TokenFee>,
+    );
+
+    fn additional_signed(&self) -> Result<(), TransactionValidityError> {
+        Ok(())
+    }
+
+    fn validate(
+        &self,
+        who: &Self::AccountId,
+        call: &Self::Call,
+        info: &DispatchInfoOf<Self::Call>,
+        len: usize,
+    ) -> TransactionValidity {
+        let (token, max_amount_in) = match self.fee_token {
+            Some(fee_token) => fee_token,
+            None => return self.native().validate(who, call, info, len),
+        };
+
+        let weight = Self::extra_weight(token);
+        let fee =
+            TransactionPayment::compute_fee(len as u32, &Self::with_weight(info, weight), self.tip);
+        if !fee.is_zero() {
+            self.withdraw_fee_in_token(who, token, max_amount_in, fee, weight)?;
+        }
+
+        Ok(ValidTransaction {
+            priority: fee.saturated_into::<TransactionPriority>(),
+            ..Default::default()
+        })
+    }
+
+    fn pre_dispatch(
+        self,
+        who: &Self::AccountId,
+        call: &Self::Call,
+        info: &DispatchInfoOf<Self::Call>,
+        len: usize,
+    ) -> Result<Self::Pre, TransactionValidityError> {
+        let (token, max_amount_in) = match self.fee_token {
+            Some(fee_token) => fee_token,
+            None => {
+                return Ok((
+                    Some(self.native().pre_dispatch(who, call, info, len)?),
+                    None,
+                ))
+            }
+        };
+
+        let weight = Self::extra_weight(token);
+        let fee =
+            TransactionPayment::compute_fee(len as u32, &Self::with_weight(info, weight), self.tip);
+        if fee.is_zero() {
+            return Ok((None, None));
+        }
+
+        // the swaps are counted in the block whether the call turns out to pay or not
+        Self::register_weight(weight, info.class)?;
+        Ok((
+            None,
+            Some(self.withdraw_fee_in_token(who, token, max_amount_in, fee, weight)?),
+        ))
+    }
+
+    fn post_dispatch(
+        pre: Self::Pre,
+        info: &DispatchInfoOf<Self::Call>,
+        post_info: &PostDispatchInfoOf<Self::Call>,
+        len: usize,
+        result: &DispatchResult,
+    ) -> Result<(), TransactionValidityError> {
+        let (native, paid) = pre;
+        if let Some(pre) = native {
+            return ChargeTransactionPayment::<Runtime>::post_dispatch(
+                pre, info, post_info, len, result,
+            );
+        }
+
+        if let Some(paid) = paid {
+            let post_info = PostDispatchInfo {
+                actual_weight: post_info
+                    .actual_weight
+                    .map(|weight| weight.saturating_add(paid.weight)),
+                ..*post_info
+            };
+            let actual_fee = TransactionPayment::compute_actual_fee(
+                len as u32,
+                &Self::with_weight(info, paid.weight),
+                &post_info,
+                paid.tip,
+            );
+            let refund = paid.fee.saturating_sub(actual_fee);
+
+            let (payment, refunded) = if refund.is_zero() {
+                (paid.imbalance, Zero::zero())
+            } else {
+                match TokenModule::deposit_wrapped(&paid.who, refund) {
+                    Ok(refund_imbalance) => match paid.imbalance.offset(refund_imbalance) {
+                        Ok(payment) => (payment, refund),
+                        Err(_) => return Err(InvalidTransaction::Payment.into()),
+                    },
+                    Err(_) => (paid.imbalance, Zero::zero()),
+                }
+            };
+            let (tip, fee) = payment.split(paid.tip);
+            <Runtime as pallet_transaction_payment::Trait>::OnTransactionPayment::on_unbalanceds(
+                Some(fee).into_iter().chain(Some(tip)),
+            );
+
+            if let Some(tp_hash) = paid.tp_hash {
+                Self::refund_in_token(&paid.who, tp_hash, paid.surplus.saturating_add(refunded));
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1 +1,3 @@
+pub mod pallet_swap;
+pub mod pallet_token;
 pub mod pallet_utility;
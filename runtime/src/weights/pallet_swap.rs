@@ -0,0 +1,191 @@
+//! Weights for the swap pallet. To regenerate them, build the node with
+//! `--features runtime-benchmarks` and run:
+//!
+//! ```text
+//! ./target/release/node-template benchmark --chain dev --execution wasm \
+//!     --wasm-execution compiled --pallet swap --extrinsic '*' --steps 50 --repeat 20 \
+//!     --raw --output runtime/src/weights/pallet_swap.rs
+//! ```
+
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::weights::{constants::RocksDbWeight as DbWeight, Weight};
+
+pub struct WeightInfo;
+impl swap::WeightInfo for WeightInfo {
+    fn create_trade_pair() -> Weight {
+        (118962000 as Weight)
+            .saturating_add(DbWeight::get().reads(11 as Weight))
+            .saturating_add(DbWeight::get().writes(16 as Weight))
+    }
+    fn delist_trade_pair() -> Weight {
+        (31204000 as Weight)
+            .saturating_add(DbWeight::get().reads(2 as Weight))
+            .saturating_add(DbWeight::get().writes(2 as Weight))
+    }
+    fn add_liquidity() -> Weight {
+        (163217000 as Weight)
+            .saturating_add(DbWeight::get().reads(28 as Weight))
+            .saturating_add(DbWeight::get().writes(17 as Weight))
+    }
+    fn remove_liquidity() -> Weight {
+        (154805000 as Weight)
+            .saturating_add(DbWeight::get().reads(28 as Weight))
+            .saturating_add(DbWeight::get().writes(17 as Weight))
+    }
+    fn swap_buy() -> Weight {
+        (109431000 as Weight)
+            .saturating_add(DbWeight::get().reads(20 as Weight))
+            .saturating_add(DbWeight::get().writes(9 as Weight))
+    }
+    fn swap_sell() -> Weight {
+        (111086000 as Weight)
+            .saturating_add(DbWeight::get().reads(20 as Weight))
+            .saturating_add(DbWeight::get().writes(9 as Weight))
+    }
+    fn swap_buy_stable() -> Weight {
+        (187452000 as Weight)
+            .saturating_add(DbWeight::get().reads(21 as Weight))
+            .saturating_add(DbWeight::get().writes(9 as Weight))
+    }
+    fn swap_sell_stable() -> Weight {
+        (189113000 as Weight)
+            .saturating_add(DbWeight::get().reads(21 as Weight))
+            .saturating_add(DbWeight::get().writes(9 as Weight))
+    }
+    fn swap_buy_concentrated(t: u32) -> Weight {
+        (152876000 as Weight)
+            .saturating_add((9812000 as Weight).saturating_mul(t as Weight))
+            .saturating_add(DbWeight::get().reads(22 as Weight))
+            .saturating_add(DbWeight::get().reads((1 as Weight).saturating_mul(t as Weight)))
+            .saturating_add(DbWeight::get().writes(10 as Weight))
+            .saturating_add(DbWeight::get().writes((1 as Weight).saturating_mul(t as Weight)))
+    }
+    fn swap_sell_concentrated(t: u32) -> Weight {
+        (153342000 as Weight)
+            .saturating_add((9790000 as Weight).saturating_mul(t as Weight))
+            .saturating_add(DbWeight::get().reads(22 as Weight))
+            .saturating_add(DbWeight::get().reads((1 as Weight).saturating_mul(t as Weight)))
+            .saturating_add(DbWeight::get().writes(10 as Weight))
+            .saturating_add(DbWeight::get().writes((1 as Weight).saturating_mul(t as Weight)))
+    }
+    fn swap_by_tokens(t: u32) -> Weight {
+        (168215000 as Weight)
+            .saturating_add((332046000 as Weight).saturating_mul(t as Weight))
+            .saturating_add(DbWeight::get().reads(22 as Weight))
+            .saturating_add(DbWeight::get().reads((34 as Weight).saturating_mul(t as Weight)))
+            .saturating_add(DbWeight::get().writes(10 as Weight))
+            .saturating_add(DbWeight::get().writes((1 as Weight).saturating_mul(t as Weight)))
+    }
+    fn best_trade_pair_for_amount_out(t: u32) -> Weight {
+        (3124000 as Weight)
+            .saturating_add((1861472000 as Weight).saturating_mul(t as Weight))
+            .saturating_add(DbWeight::get().reads(1 as Weight))
+            .saturating_add(DbWeight::get().reads((35 as Weight).saturating_mul(t as Weight)))
+    }
+    fn flash_swap() -> Weight {
+        (171608000 as Weight)
+            .saturating_add(DbWeight::get().reads(19 as Weight))
+            .saturating_add(DbWeight::get().writes(12 as Weight))
+    }
+    fn flash_loan() -> Weight {
+        (126317000 as Weight)
+            .saturating_add(DbWeight::get().reads(17 as Weight))
+            .saturating_add(DbWeight::get().writes(11 as Weight))
+    }
+    fn ramp_amplification() -> Weight {
+        (30552000 as Weight)
+            .saturating_add(DbWeight::get().reads(3 as Weight))
+            .saturating_add(DbWeight::get().writes(1 as Weight))
+    }
+    fn set_protocol_fee() -> Weight {
+        (16913000 as Weight)
+            .saturating_add(DbWeight::get().reads(0 as Weight))
+            .saturating_add(DbWeight::get().writes(2 as Weight))
+    }
+    fn pause_pair() -> Weight {
+        (24785000 as Weight)
+            .saturating_add(DbWeight::get().reads(2 as Weight))
+            .saturating_add(DbWeight::get().writes(1 as Weight))
+    }
+    fn unpause_pair() -> Weight {
+        (24380000 as Weight)
+            .saturating_add(DbWeight::get().reads(2 as Weight))
+            .saturating_add(DbWeight::get().writes(1 as Weight))
+    }
+    fn pause_all() -> Weight {
+        (15124000 as Weight)
+            .saturating_add(DbWeight::get().reads(0 as Weight))
+            .saturating_add(DbWeight::get().writes(1 as Weight))
+    }
+    fn unpause_all() -> Weight {
+        (15096000 as Weight)
+            .saturating_add(DbWeight::get().reads(0 as Weight))
+            .saturating_add(DbWeight::get().writes(1 as Weight))
+    }
+    fn set_circuit_breaker() -> Weight {
+        (25618000 as Weight)
+            .saturating_add(DbWeight::get().reads(1 as Weight))
+            .saturating_add(DbWeight::get().writes(2 as Weight))
+    }
+    fn initialize_concentrated_pool() -> Weight {
+        (29447000 as Weight)
+            .saturating_add(DbWeight::get().reads(2 as Weight))
+            .saturating_add(DbWeight::get().writes(1 as Weight))
+    }
+    fn mint_position() -> Weight {
+        (144713000 as Weight)
+            .saturating_add(DbWeight::get().reads(14 as Weight))
+            .saturating_add(DbWeight::get().writes(11 as Weight))
+    }
+    fn decrease_position() -> Weight {
+        (97832000 as Weight)
+            .saturating_add(DbWeight::get().reads(7 as Weight))
+            .saturating_add(DbWeight::get().writes(6 as Weight))
+    }
+    fn collect_position() -> Weight {
+        (83570000 as Weight)
+            .saturating_add(DbWeight::get().reads(8 as Weight))
+            .saturating_add(DbWeight::get().writes(5 as Weight))
+    }
+    fn create_weighted_pool(n: u32) -> Weight {
+        (98425000 as Weight)
+            .saturating_add((33861000 as Weight).saturating_mul(n as Weight))
+            .saturating_add(DbWeight::get().reads(5 as Weight))
+            .saturating_add(DbWeight::get().reads((3 as Weight).saturating_mul(n as Weight)))
+            .saturating_add(DbWeight::get().writes(8 as Weight))
+            .saturating_add(DbWeight::get().writes((4 as Weight).saturating_mul(n as Weight)))
+    }
+    fn join_pool(n: u32) -> Weight {
+        (54327000 as Weight)
+            .saturating_add((30472000 as Weight).saturating_mul(n as Weight))
+            .saturating_add(DbWeight::get().reads(2 as Weight))
+            .saturating_add(DbWeight::get().reads((3 as Weight).saturating_mul(n as Weight)))
+            .saturating_add(DbWeight::get().writes(2 as Weight))
+            .saturating_add(DbWeight::get().writes((2 as Weight).saturating_mul(n as Weight)))
+    }
+    fn join_pool_single() -> Weight {
+        (114580000 as Weight)
+            .saturating_add(DbWeight::get().reads(8 as Weight))
+            .saturating_add(DbWeight::get().writes(5 as Weight))
+    }
+    fn exit_pool(n: u32) -> Weight {
+        (55109000 as Weight)
+            .saturating_add((30218000 as Weight).saturating_mul(n as Weight))
+            .saturating_add(DbWeight::get().reads(3 as Weight))
+            .saturating_add(DbWeight::get().reads((3 as Weight).saturating_mul(n as Weight)))
+            .saturating_add(DbWeight::get().writes(2 as Weight))
+            .saturating_add(DbWeight::get().writes((2 as Weight).saturating_mul(n as Weight)))
+    }
+    fn exit_pool_single() -> Weight {
+        (113962000 as Weight)
+            .saturating_add(DbWeight::get().reads(8 as Weight))
+            .saturating_add(DbWeight::get().writes(5 as Weight))
+    }
+    fn swap_weighted() -> Weight {
+        (127804000 as Weight)
+            .saturating_add(DbWeight::get().reads(9 as Weight))
+            .saturating_add(DbWeight::get().writes(5 as Weight))
+    }
+}
@@ -0,0 +1,23 @@
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::weights::{constants::RocksDbWeight as DbWeight, Weight};
+
+pub struct WeightInfo;
+impl token::WeightInfo for WeightInfo {
+    fn issue() -> Weight {
+        (45318000 as Weight)
+            .saturating_add(DbWeight::get().reads(3 as Weight))
+            .saturating_add(DbWeight::get().writes(8 as Weight))
+    }
+    fn issue_mintable() -> Weight {
+        (46102000 as Weight)
+            .saturating_add(DbWeight::get().reads(3 as Weight))
+            .saturating_add(DbWeight::get().writes(9 as Weight))
+    }
+    fn transfer() -> Weight {
+        (52674000 as Weight)
+            .saturating_add(DbWeight::get().reads(8 as Weight))
+            .saturating_add(DbWeight::get().writes(4 as Weight))
+    }
+}
@@ -0,0 +1,716 @@
+//! Integration tests of the runtime, applying signed extrinsics through `Executive` on a chain
+//! built from genesis.
+
+use codec::{Decode, Encode};
+use frame_support::{
+    assert_ok,
+    storage::{unhashed, StoragePrefixedMap},
+    traits::OnRuntimeUpgrade,
+    weights::{constants::ExtrinsicBaseWeight, DispatchClass, DispatchInfo, GetDispatchInfo},
+    Blake2_128Concat, StorageHasher, StorageMap, StorageValue,
+};
+use sp_keyring::AccountKeyring;
+use sp_runtime::{
+    generic::{Era, SignedPayload},
+    traits::Header as _,
+    transaction_validity::{InvalidTransaction, TransactionSource, TransactionValidityError},
+    ApplyExtrinsicResult, BuildStorage, DispatchError,
+};
+use substrate_uniswap_runtime::{
+    payment::ChargeTokenPayment, AccountId, Balance, Balances, BalancesConfig, BlockNumber, Call,
+    DefaultFeeTier, Event, Executive, ExistentialDeposit, FeeTiers, GenesisConfig, Hash, Header,
+    Runtime, SignedExtra, SudoConfig, SwapModule, System, SystemConfig, TokenModule,
+    TransactionPayment, UncheckedExtrinsic,
+};
+
+const INITIAL_BALANCE: Balance = 1 << 60;
+
+fn new_test_ext() -> sp_io::TestExternalities {
+    let storage = GenesisConfig {
+        frame_system: Some(SystemConfig {
+            code: vec![],
+            changes_trie_config: Default::default(),
+        }),
+        pallet_balances: Some(BalancesConfig {
+            balances: vec![
+                (AccountKeyring::Alice.to_account_id(), INITIAL_BALANCE),
+                (AccountKeyring::Bob.to_account_id(), INITIAL_BALANCE),
+            ],
+        }),
+        pallet_aura: None,
+        pallet_grandpa: None,
+        pallet_sudo: Some(SudoConfig {
+            key: AccountKeyring::Alice.to_account_id(),
+        }),
+        swap: Some(Default::default()),
+        token: Some(Default::default()),
+    }
+    .build_storage()
+    .unwrap();
+
+    let mut ext = sp_io::TestExternalities::new(storage);
+    ext.execute_with(|| initialize_block(1));
+    ext
+}
+
+fn initialize_block(number: BlockNumber) {
+    Executive::initialize_block(&Header::new(
+        number,
+        Default::default(),
+        Default::default(),
+        [69u8; 32].into(),
+        Default::default(),
+    ));
+}
+
+/// `call` signed by `signer` with its next nonce, as a wallet would submit it.
+fn sign(signer: AccountKeyring, call: Call) -> UncheckedExtrinsic {
+    sign_with_fee_token(signer, call, None)
+}
+
+/// `call` signed by `signer`, paying its fee with at most the given amount of a token.
+fn sign_with_fee_token(
+    signer: AccountKeyring,
+    call: Call,
+    fee_token: Option<(Hash, Balance)>,
+) -> UncheckedExtrinsic {
+    let account = signer.to_account_id();
+    let extra: SignedExtra = (
+        frame_system::CheckSpecVersion::new(),
+        frame_system::CheckTxVersion::new(),
+        frame_system::CheckGenesis::new(),
+        frame_system::CheckEra::from(Era::Immortal),
+        frame_system::CheckNonce::from(System::account_nonce(&account)),
+        frame_system::CheckWeight::new(),
+        ChargeTokenPayment::from(0, fee_token),
+    );
+    let payload = SignedPayload::new(call, extra).unwrap();
+    let signature = payload.using_encoded(|payload| signer.sign(payload));
+    let (call, extra, _) = payload.deconstruct();
+
+    UncheckedExtrinsic::new_signed(call, account, signature.into(), extra)
+}
+
+/// Dispatch info of the last applied extrinsic, with the weight it actually used.
+fn actual_info() -> DispatchInfo {
+    System::events()
+        .into_iter()
+        .rev()
+        .find_map(|record| match record.event {
+            Event::frame_system(frame_system::RawEvent::ExtrinsicSuccess(info))
+            | Event::frame_system(frame_system::RawEvent::ExtrinsicFailed(_, info)) => Some(info),
+            _ => None,
+        })
+        .expect("an extrinsic was applied")
+}
+
+/// Apply `call` signed by `signer`, checking that the fee charged matches the weight it used.
+fn apply(signer: AccountKeyring, call: Call) -> ApplyExtrinsicResult {
+    let account = signer.to_account_id();
+    let xt = sign(signer, call);
+    let len = xt.encode().len() as u32;
+
+    let balance = Balances::free_balance(&account);
+    let result = Executive::apply_extrinsic(xt);
+    if result.is_ok() {
+        let fee = TransactionPayment::compute_fee(len, &actual_info(), 0);
+        assert_eq!(Balances::free_balance(&account), balance - fee);
+    }
+
+    result
+}
+
+fn has_event(event: Event) -> bool {
+    System::events().iter().any(|record| record.event == event)
+}
+
+fn issue(signer: AccountKeyring, symbol: &[u8], total_supply: Balance) -> Hash {
+    let index = TokenModule::token_index();
+    assert_ok!(apply(
+        signer,
+        Call::TokenModule(token::Call::issue(symbol.to_vec(), total_supply))
+    )
+    .unwrap());
+    TokenModule::token_hash_by_index(index).unwrap()
+}
+
+#[test]
+fn signed_transfer_charges_fee_and_emits_events() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.to_account_id();
+        let bob: AccountId = AccountKeyring::Bob.to_account_id();
+
+        let token_hash = issue(AccountKeyring::Alice, b"6666", 10000000);
+        assert!(has_event(Event::token(token::RawEvent::Issued(
+            alice.clone(),
+            token_hash,
+            10000000
+        ))));
+
+        assert_ok!(apply(
+            AccountKeyring::Alice,
+            Call::TokenModule(token::Call::transfer(
+                token_hash,
+                bob.clone(),
+                1000,
+                Some(b"memo".to_vec())
+            ))
+        )
+        .unwrap());
+        assert_eq!(TokenModule::balance_of((bob.clone(), token_hash)), 1000);
+        assert!(has_event(Event::token(token::RawEvent::Transferd(
+            alice.clone(),
+            bob,
+            token_hash,
+            1000
+        ))));
+        assert_eq!(System::account_nonce(&alice), 2);
+    });
+}
+
+#[test]
+fn stale_nonce_is_rejected() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.to_account_id();
+        let xt = sign(
+            AccountKeyring::Alice,
+            Call::TokenModule(token::Call::issue(b"6666".to_vec(), 10000000)),
+        );
+        assert_ok!(Executive::apply_extrinsic(xt.clone()).unwrap());
+
+        let balance = Balances::free_balance(&alice);
+        assert_eq!(
+            Executive::apply_extrinsic(xt),
+            Err(TransactionValidityError::Invalid(InvalidTransaction::Stale))
+        );
+        assert_eq!(Balances::free_balance(&alice), balance);
+    });
+}
+
+#[test]
+fn trade_pair_lifecycle() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.to_account_id();
+        let bob: AccountId = AccountKeyring::Bob.to_account_id();
+
+        let base = issue(AccountKeyring::Alice, b"6666", 10000000);
+        let quote = issue(AccountKeyring::Alice, b"8888", 10000000);
+
+        // the runtime only lets token owners list their tokens
+        assert_eq!(
+            apply(
+                AccountKeyring::Bob,
+                Call::SwapModule(swap::Call::create_trade_pair(
+                    base,
+                    quote,
+                    swap::PoolKind::ConstantProduct,
+                    30
+                ))
+            ),
+            Ok(Err(
+                swap::Error::<Runtime>::SenderNotEqualToBaseOrQuoteOwner.into()
+            ))
+        );
+        assert_ok!(apply(
+            AccountKeyring::Alice,
+            Call::SwapModule(swap::Call::create_trade_pair(
+                base,
+                quote,
+                swap::PoolKind::ConstantProduct,
+                30
+            ))
+        )
+        .unwrap());
+        let tp_hash = SwapModule::trade_pair_hash_by_base_quote((base, quote, 30)).unwrap();
+
+        assert_ok!(apply(
+            AccountKeyring::Alice,
+            Call::SwapModule(swap::Call::add_liquidity(tp_hash, 1000000, Some(2000000)))
+        )
+        .unwrap());
+        assert!(has_event(Event::swap(swap::RawEvent::Sync(
+            tp_hash, 1000000, 2000000
+        ))));
+        assert!(has_event(Event::swap(swap::RawEvent::LiquidityAdded(
+            alice.clone(),
+            tp_hash,
+            1000000,
+            2000000,
+            1000000,
+            1000000,
+            2000000
+        ))));
+
+        assert_ok!(apply(
+            AccountKeyring::Alice,
+            Call::TokenModule(token::Call::transfer(base, bob.clone(), 100000, None))
+        )
+        .unwrap());
+        assert_ok!(apply(
+            AccountKeyring::Bob,
+            Call::SwapModule(swap::Call::swap(tp_hash, base, 100000))
+        )
+        .unwrap());
+        assert_eq!(TokenModule::balance_of((bob.clone(), quote)), 181322);
+        assert!(has_event(Event::swap(swap::RawEvent::SwapBuy(
+            bob, tp_hash, 100000, 181322, 1100000, 1818678
+        ))));
+    });
+}
+
+#[test]
+fn flash_swap_repaid_by_borrower_call() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.to_account_id();
+
+        let base = issue(AccountKeyring::Alice, b"6666", 10000000);
+        let quote = issue(AccountKeyring::Alice, b"8888", 10000000);
+        assert_ok!(apply(
+            AccountKeyring::Alice,
+            Call::SwapModule(swap::Call::create_trade_pair(
+                base,
+                quote,
+                swap::PoolKind::ConstantProduct,
+                30
+            ))
+        )
+        .unwrap());
+        let tp_hash = SwapModule::trade_pair_hash_by_base_quote((base, quote, 30)).unwrap();
+        assert_ok!(apply(
+            AccountKeyring::Alice,
+            Call::SwapModule(swap::Call::add_liquidity(tp_hash, 1000000, Some(2000000)))
+        )
+        .unwrap());
+        // the pair account issued the liquidity token, the third token on chain
+        let pair_account =
+            TokenModule::owner(TokenModule::token_hash_by_index(2).unwrap()).unwrap();
+
+        // 100301 is the least paying 100000 back with the 0.3% fee
+        let repay = Call::TokenModule(token::Call::transfer(
+            base,
+            pair_account.clone(),
+            100301,
+            None,
+        ));
+        assert_ok!(apply(
+            AccountKeyring::Alice,
+            Call::SwapModule(swap::Call::flash_swap(tp_hash, 100000, 0, repay.encode()))
+        )
+        .unwrap());
+        assert!(has_event(Event::swap(swap::RawEvent::FlashSwap(
+            alice, tp_hash
+        ))));
+        assert_eq!(TokenModule::balance_of((pair_account, base)), 1000301);
+        assert!(!SwapModule::is_locked(tp_hash));
+    });
+}
+
+#[test]
+fn paused_dex_filters_swap_calls() {
+    new_test_ext().execute_with(|| {
+        let base = issue(AccountKeyring::Alice, b"6666", 10000000);
+        let quote = issue(AccountKeyring::Alice, b"8888", 10000000);
+        assert_ok!(apply(
+            AccountKeyring::Alice,
+            Call::SwapModule(swap::Call::create_trade_pair(
+                base,
+                quote,
+                swap::PoolKind::ConstantProduct,
+                30
+            ))
+        )
+        .unwrap());
+
+        // sudo calls are refunded, so they are applied without checking the fee
+        assert_ok!(Executive::apply_extrinsic(sign(
+            AccountKeyring::Alice,
+            Call::Sudo(pallet_sudo::Call::sudo(Box::new(Call::SwapModule(
+                swap::Call::pause_all()
+            ))))
+        ))
+        .unwrap());
+        assert!(SwapModule::is_all_paused());
+
+        // the call filter stops the extrinsic before the pallet sees it, but its fee is paid
+        let alice: AccountId = AccountKeyring::Alice.to_account_id();
+        let balance = Balances::free_balance(&alice);
+        assert_eq!(
+            Executive::apply_extrinsic(sign(
+                AccountKeyring::Alice,
+                Call::SwapModule(swap::Call::create_trade_pair(
+                    base,
+                    quote,
+                    swap::PoolKind::ConstantProduct,
+                    100
+                ))
+            )),
+            Ok(Err(DispatchError::BadOrigin))
+        );
+        assert!(Balances::free_balance(&alice) < balance);
+        assert!(SwapModule::trade_pair_hash_by_base_quote((base, quote, 100)).is_none());
+
+        assert_ok!(Executive::apply_extrinsic(sign(
+            AccountKeyring::Alice,
+            Call::Sudo(pallet_sudo::Call::sudo(Box::new(Call::SwapModule(
+                swap::Call::unpause_all()
+            ))))
+        ))
+        .unwrap());
+        assert_ok!(apply(
+            AccountKeyring::Alice,
+            Call::SwapModule(swap::Call::create_trade_pair(
+                base,
+                quote,
+                swap::PoolKind::ConstantProduct,
+                100
+            ))
+        )
+        .unwrap());
+    });
+}
+
+/// A constant product pair of Alice at `fee_tier` holding `base_amount` and `quote_amount`.
+fn liquid_pair(
+    base: Hash,
+    quote: Hash,
+    fee_tier: u32,
+    base_amount: Balance,
+    quote_amount: Balance,
+) -> Hash {
+    assert_ok!(apply(
+        AccountKeyring::Alice,
+        Call::SwapModule(swap::Call::create_trade_pair(
+            base,
+            quote,
+            swap::PoolKind::ConstantProduct,
+            fee_tier
+        ))
+    )
+    .unwrap());
+    let tp_hash = SwapModule::trade_pair_hash_by_base_quote((base, quote, fee_tier)).unwrap();
+    assert_ok!(apply(
+        AccountKeyring::Alice,
+        Call::SwapModule(swap::Call::add_liquidity(
+            tp_hash,
+            base_amount,
+            Some(quote_amount)
+        ))
+    )
+    .unwrap());
+
+    tp_hash
+}
+
+/// Write the pair back as the release without fee tiers stored it.
+fn downgrade_to_v1(tp_hash: Hash) {
+    let tp = SwapModule::trade_pair(tp_hash).unwrap();
+    // the fields of a V1 pair are the first fields of a V2 pair
+    let v1_tp = swap::migrations::v1::TradePair::<Runtime>::decode(&mut &tp.encode()[..]).unwrap();
+    unhashed::put(
+        &<SwapModule as swap::Store>::TradePairs::hashed_key_for(tp_hash),
+        &v1_tp,
+    );
+
+    <SwapModule as swap::Store>::TradePairsHashByBaseQuote::remove((
+        tp.base,
+        tp.quote,
+        tp.fee_tier,
+    ));
+    <SwapModule as swap::Store>::TradePairsHashByBaseQuote::remove((
+        tp.quote,
+        tp.base,
+        tp.fee_tier,
+    ));
+    <SwapModule as swap::Store>::TradePairsHashesByBaseQuote::remove((tp.base, tp.quote));
+    <SwapModule as swap::Store>::TradePairsHashesByBaseQuote::remove((tp.quote, tp.base));
+
+    let mut key = <SwapModule as swap::Store>::TradePairsHashByBaseQuote::final_prefix().to_vec();
+    key.extend(Blake2_128Concat::hash(&(tp.base, tp.quote).encode()));
+    unhashed::put(&key, &tp_hash);
+}
+
+#[test]
+fn v1_trade_pairs_migrate_to_a_fee_tier_of_the_runtime() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.to_account_id();
+        let bob: AccountId = AccountKeyring::Bob.to_account_id();
+        let base = issue(AccountKeyring::Alice, b"6666", 10000000);
+        let quote = issue(AccountKeyring::Alice, b"8888", 10000000);
+
+        // the first release let a pair and the pair of the other orientation coexist
+        let deep = liquid_pair(base, quote, 100, 1000000, 2000000);
+        let shallow = liquid_pair(quote, base, 30, 2000, 1000);
+        downgrade_to_v1(deep);
+        downgrade_to_v1(shallow);
+        <SwapModule as swap::Store>::StorageVersion::put(swap::Releases::V1);
+
+        SwapModule::on_runtime_upgrade();
+        assert_eq!(SwapModule::storage_version(), swap::Releases::LATEST);
+
+        let fee_tier = DefaultFeeTier::get();
+        assert!(FeeTiers::get().contains(&fee_tier));
+        assert_eq!(SwapModule::trade_pair(deep).unwrap().fee_tier, fee_tier);
+        assert_eq!(
+            SwapModule::trade_pair_hash_by_base_quote((base, quote, fee_tier)),
+            Some(deep)
+        );
+        assert_eq!(
+            SwapModule::trade_pair_hash_by_base_quote((quote, base, fee_tier)),
+            Some(deep)
+        );
+        assert!(!SwapModule::is_delisted(deep));
+        assert!(SwapModule::is_delisted(shallow));
+
+        // the migrated pair trades in its fee tier
+        assert_ok!(apply(
+            AccountKeyring::Alice,
+            Call::TokenModule(token::Call::transfer(base, bob.clone(), 100000, None))
+        )
+        .unwrap());
+        assert_ok!(apply(
+            AccountKeyring::Bob,
+            Call::SwapModule(swap::Call::swap_by_tokens(base, quote, 100000))
+        )
+        .unwrap());
+        assert_eq!(TokenModule::balance_of((bob.clone(), base)), 0);
+        assert!(TokenModule::balance_of((bob, quote)) > 0);
+
+        // the providers of the delisted pair can still take their liquidity back
+        let liquidity_token = SwapModule::trade_pair(shallow)
+            .unwrap()
+            .liquidity_token_hash;
+        let liquidity = TokenModule::balance_of((alice.clone(), liquidity_token));
+        assert_ok!(apply(
+            AccountKeyring::Alice,
+            Call::SwapModule(swap::Call::remove_liquidity(shallow, liquidity))
+        )
+        .unwrap());
+        assert_eq!(TokenModule::balance_of((alice, liquidity_token)), 0);
+    });
+}
+
+/// A token of Alice traded against the wrapped native token, 100 tokens for a native unit.
+fn token_native_pair() -> (Hash, Hash, Hash) {
+    // wrapping moves native currency besides the fee, so the balance check of `apply` is off
+    assert_ok!(Executive::apply_extrinsic(sign(
+        AccountKeyring::Alice,
+        Call::TokenModule(token::Call::wrap(1_000_000_000_000))
+    ))
+    .unwrap());
+    let wrapped = TokenModule::wrapped_native_token_hash().unwrap();
+
+    let token = issue(AccountKeyring::Alice, b"6666", 1_000_000_000_000_000);
+    assert_ok!(apply(
+        AccountKeyring::Alice,
+        Call::SwapModule(swap::Call::create_trade_pair(
+            token,
+            wrapped,
+            swap::PoolKind::ConstantProduct,
+            30
+        ))
+    )
+    .unwrap());
+    let tp_hash = SwapModule::trade_pair_hash_by_base_quote((token, wrapped, 30)).unwrap();
+    assert_ok!(apply(
+        AccountKeyring::Alice,
+        Call::SwapModule(swap::Call::add_liquidity(
+            tp_hash,
+            100_000_000_000_000,
+            Some(1_000_000_000_000)
+        ))
+    )
+    .unwrap());
+
+    (token, wrapped, tp_hash)
+}
+
+/// The native currency backing the wrapped native token matches its supply.
+fn assert_wrapped_native_backed(wrapped: Hash) {
+    assert_eq!(
+        Balances::free_balance(&TokenModule::account_id()),
+        TokenModule::token(wrapped).unwrap().total_supply
+    );
+}
+
+#[test]
+fn fee_paid_in_token_through_wrapped_native_pair() {
+    new_test_ext().execute_with(|| {
+        let bob: AccountId = AccountKeyring::Bob.to_account_id();
+        let charlie: AccountId = AccountKeyring::Charlie.to_account_id();
+        let (token, wrapped, tp_hash) = token_native_pair();
+        assert_ok!(apply(
+            AccountKeyring::Alice,
+            Call::TokenModule(token::Call::transfer(
+                token,
+                charlie.clone(),
+                1_000_000_000_000,
+                None
+            ))
+        )
+        .unwrap());
+
+        // charlie holds no native currency at all
+        let call = Call::TokenModule(token::Call::transfer(token, bob.clone(), 1000, None));
+        let mut info = call.get_dispatch_info();
+        info.weight += ChargeTokenPayment::swap_weight();
+        let xt = sign_with_fee_token(
+            AccountKeyring::Charlie,
+            call,
+            Some((token, 1_000_000_000_000)),
+        );
+        let fee = TransactionPayment::compute_fee(xt.encode().len() as u32, &info, 0);
+        let (best, amount_in) =
+            SwapModule::best_trade_pair_for_amount_out(token, wrapped, fee, 1_000_000_000_000)
+                .unwrap();
+        assert_eq!(best, tp_hash);
+        let (_, bought) = SwapModule::best_trade_pair(token, wrapped, amount_in).unwrap();
+        let issuance = Balances::total_issuance();
+        let consumed = System::block_weight().get(DispatchClass::Normal);
+
+        assert_ok!(Executive::apply_extrinsic(xt).unwrap());
+        // the swaps are charged and counted in the block along with the call
+        assert_eq!(
+            System::block_weight().get(DispatchClass::Normal),
+            consumed + info.weight + ExtrinsicBaseWeight::get()
+        );
+        assert_eq!(TokenModule::balance_of((bob, token)), 1000);
+        assert_eq!(
+            TokenModule::balance_of((charlie.clone(), token)),
+            1_000_000_000_000 - amount_in - 1000
+        );
+        // the few units bought beyond the fee are too few to swap back
+        assert!(bought - fee < ExistentialDeposit::get());
+        assert_eq!(
+            TokenModule::balance_of((charlie.clone(), wrapped)),
+            bought - fee
+        );
+        assert_eq!(Balances::free_balance(&charlie), 0);
+        assert_eq!(System::account_nonce(&charlie), 1);
+
+        // the fee is the native currency that backed the wrapped native token bought
+        assert_eq!(Balances::total_issuance(), issuance - fee);
+        assert_wrapped_native_backed(wrapped);
+    });
+}
+
+#[test]
+fn fee_token_beyond_slippage_bound_is_rejected() {
+    new_test_ext().execute_with(|| {
+        let bob: AccountId = AccountKeyring::Bob.to_account_id();
+        let charlie: AccountId = AccountKeyring::Charlie.to_account_id();
+        let (token, wrapped, _) = token_native_pair();
+        assert_ok!(apply(
+            AccountKeyring::Alice,
+            Call::TokenModule(token::Call::transfer(
+                token,
+                charlie.clone(),
+                1_000_000_000_000,
+                None
+            ))
+        )
+        .unwrap());
+
+        let xt = sign_with_fee_token(
+            AccountKeyring::Charlie,
+            Call::TokenModule(token::Call::transfer(token, bob, 1000, None)),
+            Some((token, 1000)),
+        );
+        assert_eq!(
+            Executive::validate_transaction(TransactionSource::External, xt.clone()),
+            Err(TransactionValidityError::Invalid(
+                InvalidTransaction::Payment
+            ))
+        );
+        assert_eq!(
+            Executive::apply_extrinsic(xt),
+            Err(TransactionValidityError::Invalid(
+                InvalidTransaction::Payment
+            ))
+        );
+        assert_eq!(TokenModule::balance_of((charlie, token)), 1_000_000_000_000);
+        assert_wrapped_native_backed(wrapped);
+    });
+}
+
+#[test]
+fn refunded_fee_is_swapped_back_to_fee_token() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.to_account_id();
+        let (token, wrapped, _) = token_native_pair();
+
+        let call = Call::Sudo(pallet_sudo::Call::sudo(Box::new(Call::System(
+            frame_system::Call::remark(vec![]),
+        ))));
+        let mut info = call.get_dispatch_info();
+        info.weight += ChargeTokenPayment::swap_weight();
+        let xt = sign_with_fee_token(
+            AccountKeyring::Alice,
+            call,
+            Some((token, 1_000_000_000_000)),
+        );
+        let fee = TransactionPayment::compute_fee(xt.encode().len() as u32, &info, 0);
+        let (_, amount_in) =
+            SwapModule::best_trade_pair_for_amount_out(token, wrapped, fee, 1_000_000_000_000)
+                .unwrap();
+
+        let tokens = TokenModule::balance_of((alice.clone(), token));
+        let wrapped_balance = TokenModule::balance_of((alice.clone(), wrapped));
+        let balance = Balances::free_balance(&alice);
+        let issuance = Balances::total_issuance();
+
+        // sudo calls pay no fee, so all of it comes back, less the swap fees both ways
+        assert_ok!(Executive::apply_extrinsic(xt).unwrap());
+        let spent = tokens - TokenModule::balance_of((alice.clone(), token));
+        assert!(spent > 0 && spent < amount_in);
+        assert_eq!(
+            TokenModule::balance_of((alice.clone(), wrapped)),
+            wrapped_balance
+        );
+        assert_eq!(Balances::free_balance(&alice), balance);
+        assert_eq!(Balances::total_issuance(), issuance);
+        assert_wrapped_native_backed(wrapped);
+    });
+}
+
+#[test]
+fn refund_is_left_wrapped_when_swapping_back_fails() {
+    new_test_ext().execute_with(|| {
+        let alice: AccountId = AccountKeyring::Alice.to_account_id();
+        let (token, wrapped, tp_hash) = token_native_pair();
+
+        // the call pauses the pair the refund would be swapped back through
+        let call = Call::Sudo(pallet_sudo::Call::sudo(Box::new(Call::SwapModule(
+            swap::Call::pause_pair(tp_hash),
+        ))));
+        let mut info = call.get_dispatch_info();
+        info.weight += ChargeTokenPayment::swap_weight();
+        let xt = sign_with_fee_token(
+            AccountKeyring::Alice,
+            call,
+            Some((token, 1_000_000_000_000)),
+        );
+        let fee = TransactionPayment::compute_fee(xt.encode().len() as u32, &info, 0);
+        let (_, amount_in) =
+            SwapModule::best_trade_pair_for_amount_out(token, wrapped, fee, 1_000_000_000_000)
+                .unwrap();
+        let (_, bought) = SwapModule::best_trade_pair(token, wrapped, amount_in).unwrap();
+
+        let tokens = TokenModule::balance_of((alice.clone(), token));
+        let wrapped_balance = TokenModule::balance_of((alice.clone(), wrapped));
+        let balance = Balances::free_balance(&alice);
+
+        // sudo calls pay no fee, so the whole fee is refunded in wrapped native currency
+        assert_ok!(Executive::apply_extrinsic(xt).unwrap());
+        assert!(SwapModule::is_pair_paused(tp_hash));
+        assert_eq!(
+            TokenModule::balance_of((alice.clone(), token)),
+            tokens - amount_in
+        );
+        assert_eq!(
+            TokenModule::balance_of((alice.clone(), wrapped)),
+            wrapped_balance + bought
+        );
+        assert_eq!(Balances::free_balance(&alice), balance);
+        assert_wrapped_native_backed(wrapped);
+    });
+}